@@ -0,0 +1,39 @@
+//! Compresses the static assets in every encoding they are served in, so they
+//! are not compressed again for every request.
+
+use std::{env, fs, io::Write as _, path::Path};
+
+/// Assets worth compressing, the images are compressed already.
+const ASSETS: [&str; 3] = ["favicon.ico", "manifest.json", "style.css"];
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = env::var("OUT_DIR")?;
+    let out_dir = Path::new(&out_dir);
+
+    for asset in ASSETS {
+        let path = Path::new("assets").join(asset);
+        println!("cargo::rerun-if-changed={}", path.display());
+
+        let content = fs::read(&path)?;
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        gzip.write_all(&content)?;
+        fs::write(out_dir.join(format!("{asset}.gz")), gzip.finish()?)?;
+
+        let mut br = Vec::new();
+        brotli::BrotliCompress(
+            &mut content.as_slice(),
+            &mut br,
+            &brotli::enc::BrotliEncoderParams {
+                quality: 11,
+                ..Default::default()
+            },
+        )?;
+        fs::write(out_dir.join(format!("{asset}.br")), br)?;
+
+        let zstd = zstd::encode_all(content.as_slice(), zstd::zstd_safe::max_c_level())?;
+        fs::write(out_dir.join(format!("{asset}.zst")), zstd)?;
+    }
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{num::NonZero, path::PathBuf, thread};
 
 use clap::Parser as _;
 use figment::{
@@ -29,20 +29,107 @@ pub struct Config {
     #[arg(short, long, default_value_t = String::new())]
     pub clone_base: String,
 
+    /// Public URL the site is reachable at, used for absolute links in feeds (without trailing slash)
+    #[arg(short, long, default_value_t = String::new())]
+    pub base_url: String,
+
+    /// Base URL to link raw files from, preferably a separate origin (without trailing slash)
+    #[arg(long, default_value_t = String::new())]
+    pub raw_base: String,
+
     /// Number of commits to be shown when paginating the log
     #[arg(short, long, default_value_t = default_log_per_page())]
     pub log_per_page: usize,
+
+    /// Largest file in bytes to be shown on its page, larger ones only link to the raw file
+    #[arg(long, default_value_t = default_max_file_size())]
+    pub max_file_size: u64,
+
+    /// Most changed lines of a diff to be shown on the commit and compare pages
+    #[arg(long, default_value_t = default_max_diff_lines())]
+    pub max_diff_lines: usize,
+
+    /// Most files and directories in an archive, larger trees are only offered to clone
+    #[arg(long, default_value_t = default_max_archive_entries())]
+    pub max_archive_entries: usize,
+
+    /// Largest total size in bytes of the files in an archive, before compression
+    #[arg(long, default_value_t = default_max_archive_size())]
+    pub max_archive_size: u64,
+
+    /// Most requests doing git work at once, history walks and diffs count as four
+    #[arg(long, default_value_t = default_max_workers())]
+    pub max_workers: usize,
+
+    /// Most requests waiting for a worker, more are turned away with 503 Service Unavailable
+    #[arg(long, default_value_t = default_max_queued())]
+    pub max_queued: usize,
+
+    /// Seconds a request waits for a worker before it is turned away
+    #[arg(long, default_value_t = default_queue_timeout())]
+    pub queue_timeout: u64,
+
+    /// Seconds a response waits for a client that stopped reading it, before it is cut off to free its worker
+    #[arg(long, default_value_t = default_send_timeout())]
+    pub send_timeout: u64,
+
+    /// Markdown extensions, only configurable through the config file
+    #[arg(skip)]
+    #[serde(default)]
+    pub markdown: Markdown,
+
+    /// Run a maintenance command instead of the server
+    #[command(subcommand)]
+    #[serde(skip)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Copy, clap::Subcommand)]
+pub enum Command {
+    /// Write commit-graphs with changed-path Bloom filters for every repository
+    ///
+    /// They speed up the log of a path and finding the last commits of files.
+    WriteCommitGraphs,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[allow(missing_copy_implementations, clippy::struct_excessive_bools)]
+pub struct Markdown {
+    /// GitHub flavored tables
+    pub tables: bool,
+
+    /// `~~strikethrough~~` text
+    pub strikethrough: bool,
+
+    /// Turn bare URLs and email addresses into links
+    pub autolinks: bool,
+
+    /// `[^1]` style footnotes
+    pub footnotes: bool,
+
+    /// `- [ ]` task lists
+    pub tasklists: bool,
+
+    /// `> [!NOTE]` style alerts
+    pub alerts: bool,
+
+    /// Give headings an id and an anchor link to it
+    pub heading_anchors: bool,
 }
 
 impl Config {
     pub fn load() -> crate::error::Result<Self> {
+        let args = Self::parse();
+        let command = args.command;
+
         let config: Self = Figment::new()
-            .merge(Serialized::defaults(Self::parse()))
+            .merge(Serialized::defaults(args))
             .merge(Toml::file("bile.toml"))
             .merge(Env::prefixed("BILE_"))
             .extract()?;
 
-        Ok(config)
+        Ok(Self { command, ..config })
     }
 
     pub fn finalize(self) -> crate::error::Result<Self> {
@@ -52,7 +139,19 @@ impl Config {
             site_name: self.site_name,
             export_ok: self.export_ok,
             clone_base: self.clone_base,
+            base_url: self.base_url,
+            raw_base: self.raw_base,
             log_per_page: self.log_per_page,
+            max_file_size: self.max_file_size,
+            max_diff_lines: self.max_diff_lines,
+            max_archive_entries: self.max_archive_entries,
+            max_archive_size: self.max_archive_size,
+            max_workers: self.max_workers,
+            max_queued: self.max_queued,
+            queue_timeout: self.queue_timeout,
+            send_timeout: self.send_timeout,
+            markdown: self.markdown,
+            command: self.command,
         })
     }
 }
@@ -65,7 +164,33 @@ impl Default for Config {
             site_name: default_site_name(),
             export_ok: default_export_ok(),
             clone_base: String::new(),
+            base_url: String::new(),
+            raw_base: String::new(),
             log_per_page: default_log_per_page(),
+            max_file_size: default_max_file_size(),
+            max_diff_lines: default_max_diff_lines(),
+            max_archive_entries: default_max_archive_entries(),
+            max_archive_size: default_max_archive_size(),
+            max_workers: default_max_workers(),
+            max_queued: default_max_queued(),
+            queue_timeout: default_queue_timeout(),
+            send_timeout: default_send_timeout(),
+            markdown: Markdown::default(),
+            command: None,
+        }
+    }
+}
+
+impl Default for Markdown {
+    fn default() -> Self {
+        Self {
+            tables: true,
+            strikethrough: true,
+            autolinks: true,
+            footnotes: true,
+            tasklists: true,
+            alerts: true,
+            heading_anchors: true,
         }
     }
 }
@@ -89,3 +214,35 @@ fn default_export_ok() -> String {
 const fn default_log_per_page() -> usize {
     100
 }
+
+const fn default_max_file_size() -> u64 {
+    10 * 1024 * 1024
+}
+
+const fn default_max_diff_lines() -> usize {
+    100_000
+}
+
+const fn default_max_archive_entries() -> usize {
+    100_000
+}
+
+const fn default_max_archive_size() -> u64 {
+    1024 * 1024 * 1024
+}
+
+fn default_max_workers() -> usize {
+    thread::available_parallelism().map_or(4, NonZero::get)
+}
+
+const fn default_max_queued() -> usize {
+    64
+}
+
+const fn default_queue_timeout() -> u64 {
+    5
+}
+
+const fn default_send_timeout() -> u64 {
+    30
+}
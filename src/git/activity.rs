@@ -0,0 +1,153 @@
+//! Recent commits and tags of every repository, merged into one timeline.
+
+use std::{
+    cmp::Reverse,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use git2::Time;
+
+use crate::{
+    config::Config,
+    error::{Context as _, Result},
+    git::{
+        Order, Repository, TagEntry, Tips,
+        metadata::{RepoCache, RepoInfo},
+    },
+};
+
+/// Events kept of the whole timeline, older ones are dropped.
+const MAX_EVENTS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EventKind {
+    Commit,
+    Tag,
+}
+
+/// A commit to the default branch or a new tag of a repository.
+pub(crate) struct Event {
+    pub repo: String,
+    pub kind: EventKind,
+    /// The commit id or tag name
+    pub name: String,
+    pub summary: String,
+    pub author: String,
+    pub email: String,
+    pub time: Time,
+    /// Page of the commit or tag, relative to the repository
+    pub link: String,
+}
+
+impl Event {
+    fn from_commit(repo: &str, commit: &git2::Commit<'_>) -> Self {
+        let author = commit.author();
+
+        Self {
+            repo: repo.to_string(),
+            kind: EventKind::Commit,
+            name: commit.id().to_string(),
+            summary: String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default())
+                .into_owned(),
+            author: String::from_utf8_lossy(author.name_bytes()).into_owned(),
+            email: String::from_utf8_lossy(author.email_bytes()).into_owned(),
+            time: commit.time(),
+            link: format!("commit/{}", commit.id()),
+        }
+    }
+
+    fn from_tag(repo: &str, tag: TagEntry) -> Self {
+        Self {
+            repo: repo.to_string(),
+            kind: EventKind::Tag,
+            summary: tag.message.lines().next().unwrap_or_default().to_string(),
+            time: tag.signature.when(),
+            author: tag.signature.name,
+            email: tag.signature.email,
+            link: tag.link,
+            name: tag.tag,
+        }
+    }
+}
+
+/// Events and the repositories as they were when the events were collected.
+type Collected = (Vec<Arc<RepoInfo>>, Arc<[Event]>);
+
+/// The timeline of all repositories, collected again once their refs change.
+#[derive(Default)]
+pub(crate) struct Activity {
+    cached: Mutex<Option<Collected>>,
+}
+
+impl Activity {
+    /// All events, newest first.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn events(&self, config: &Config, repos: &RepoCache) -> Result<Arc<[Event]>> {
+        let repos = repos.all(config)?;
+
+        // held while collecting so concurrent requests wait for one walk
+        let mut cached = self.cached.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some((collected, events)) = cached.as_ref()
+            && same_refs(collected, &repos)
+        {
+            return Ok(Arc::clone(events));
+        }
+
+        let events: Arc<[Event]> = collect(config, &repos)?.into();
+
+        *cached = Some((repos, Arc::clone(&events)));
+        drop(cached);
+
+        Ok(events)
+    }
+}
+
+/// Whether no branch or tag was added, moved or removed in between.
+fn same_refs(before: &[Arc<RepoInfo>], now: &[Arc<RepoInfo>]) -> bool {
+    before.len() == now.len()
+        && before.iter().zip(now).all(|(before, now)| {
+            before.name == now.name && before.head == now.head && before.tips == now.tips
+        })
+}
+
+#[tracing::instrument(skip_all)]
+fn collect(config: &Config, repos: &[Arc<RepoInfo>]) -> Result<Vec<Event>> {
+    let mut events = Vec::new();
+
+    for info in repos {
+        let Some(repo) =
+            Repository::open_path(config, &info.dir).context("failed to open repository")?
+        else {
+            continue;
+        };
+
+        if repo.is_empty()? {
+            continue;
+        }
+
+        let name = repo.name().unwrap_or_default();
+
+        if let Some(page) = repo
+            .commits_for_obj(Tips::Spec("HEAD"), MAX_EVENTS, None, None, Order::Time)
+            .context("failed to get commits")?
+        {
+            events.extend(
+                page.commits
+                    .iter()
+                    .map(|commit| Event::from_commit(name, commit)),
+            );
+        }
+
+        events.extend(
+            repo.tag_entries()?
+                .into_iter()
+                .map(|tag| Event::from_tag(name, tag)),
+        );
+    }
+
+    events.sort_by_key(|event| Reverse(event.time.seconds()));
+    events.truncate(MAX_EVENTS);
+
+    Ok(events)
+}
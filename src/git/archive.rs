@@ -0,0 +1,167 @@
+use std::{io, path::Path};
+
+use flate2::{Compression, write::GzEncoder};
+use git2::{Commit, ObjectType, Oid, TreeWalkMode, TreeWalkResult};
+use tar::{EntryType, Header};
+
+use crate::{
+    error::{Context as _, Result},
+    git::Repository,
+};
+
+impl Repository {
+    /// Whether the archive of a commit stays within `max_entries` entries and
+    /// `max_bytes` bytes of files, checked before any of it is sent.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn archive_fits(
+        &self,
+        commit: &Commit<'_>,
+        max_entries: usize,
+        max_bytes: u64,
+    ) -> Result<bool> {
+        let tree = commit.tree().context("failed to get commit tree")?;
+        let odb = self.inner.odb().context("failed to open object database")?;
+
+        let (mut entries, mut bytes) = (0_usize, 0_u64);
+        let mut fits = true;
+
+        let walked = tree.walk(TreeWalkMode::PreOrder, |_, entry| {
+            match entry.kind() {
+                Some(ObjectType::Tree) => {}
+                Some(ObjectType::Blob) => {
+                    let Ok((size, _)) = odb.read_header(entry.id()) else {
+                        return TreeWalkResult::Skip;
+                    };
+
+                    bytes = bytes.saturating_add(size as u64);
+                }
+                // submodules
+                _ => return TreeWalkResult::Skip,
+            }
+
+            entries += 1;
+            fits = entries <= max_entries && bytes <= max_bytes;
+
+            if fits {
+                TreeWalkResult::Ok
+            } else {
+                TreeWalkResult::Abort
+            }
+        });
+
+        // stopping early fails the walk too
+        if fits {
+            walked.context("failed to walk tree")?;
+        }
+
+        Ok(fits)
+    }
+
+    /// Packs the tree of a commit in the repository in `dir` into a gzipped
+    /// tarball, like `git archive`, handing it to `write` as it goes.
+    ///
+    /// Every path is put below `prefix`, submodules are left out.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn archive(
+        dir: &Path,
+        commit: Oid,
+        prefix: &str,
+        write: &mut dyn FnMut(&[u8]) -> bool,
+    ) -> Result<()> {
+        let repo = git2::Repository::open(dir).context("failed to open repository")?;
+        let commit = repo.find_commit(commit).context("failed to get commit")?;
+        let tree = commit.tree().context("failed to get commit tree")?;
+        let mtime = u64::try_from(commit.time().seconds()).unwrap_or_default();
+
+        let mut builder = tar::Builder::new(GzEncoder::new(Sink(write), Compression::default()));
+        let mut result = Ok(());
+
+        let walked = tree.walk(TreeWalkMode::PreOrder, |parent, entry| {
+            let path = format!(
+                "{prefix}/{parent}{}",
+                String::from_utf8_lossy(entry.name_bytes())
+            );
+
+            let mut header = Header::new_gnu();
+            header.set_mtime(mtime);
+
+            let appended = match entry.kind() {
+                Some(ObjectType::Tree) => {
+                    header.set_entry_type(EntryType::Directory);
+                    header.set_mode(0o755);
+                    header.set_size(0);
+
+                    builder.append_data(&mut header, &path, &[][..])
+                }
+                Some(ObjectType::Blob) => {
+                    let Ok(blob) = repo.find_blob(entry.id()) else {
+                        return TreeWalkResult::Skip;
+                    };
+
+                    if entry.filemode() == 0o120_000 {
+                        header.set_entry_type(EntryType::Symlink);
+                        header.set_mode(0o777);
+                        header.set_size(0);
+
+                        let target = String::from_utf8_lossy(blob.content()).into_owned();
+
+                        builder.append_link(&mut header, &path, target)
+                    } else {
+                        header.set_entry_type(EntryType::Regular);
+                        header.set_mode(if entry.filemode() == 0o100_755 {
+                            0o755
+                        } else {
+                            0o644
+                        });
+                        header.set_size(blob.size() as u64);
+
+                        builder.append_data(&mut header, &path, blob.content())
+                    }
+                }
+                // submodules
+                _ => return TreeWalkResult::Skip,
+            };
+
+            if let Err(err) = appended {
+                result = Err(err);
+
+                return TreeWalkResult::Abort;
+            }
+
+            TreeWalkResult::Ok
+        });
+
+        if result.is_ok() {
+            walked.context("failed to walk tree")?;
+        }
+
+        let finished = result.and_then(|()| builder.into_inner()?.finish().map(drop));
+
+        match finished {
+            // the client went away, there is no one left to tell
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            finished => {
+                finished.context("failed to write archive")?;
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Writes to a streamed body, failing once the client went away.
+struct Sink<'w>(&'w mut dyn FnMut(&[u8]) -> bool);
+
+impl io::Write for Sink<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if (self.0)(buf) {
+            Ok(buf.len())
+        } else {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+use std::path::Path;
+
+use gix::{
+    ObjectId,
+    actor::SignatureRef,
+    objs::{Kind as ObjectKind, tree::EntryMode},
+    revision::walk,
+    traverse::commit::{simple::CommitTimeOrder, topo},
+};
+
+use crate::{
+    error::{Context as _, Result},
+    git::backend::{Backend, Commit, Entry, Id, Kind, Signature, Sorting, Tag, Walk},
+};
+
+/// Objects kept in memory while walking the history, which looks up each
+/// commit twice.
+const OBJECT_CACHE: usize = 4 * 1024 * 1024;
+
+/// Reads repositories with gitoxide.
+pub struct Gitoxide(gix::Repository);
+
+impl From<ObjectId> for Id {
+    fn from(id: ObjectId) -> Self {
+        let mut bytes = [0; 20];
+        bytes.copy_from_slice(id.as_slice());
+
+        Self(bytes)
+    }
+}
+
+impl From<gix::Id<'_>> for Id {
+    fn from(id: gix::Id<'_>) -> Self {
+        id.detach().into()
+    }
+}
+
+impl From<Id> for ObjectId {
+    fn from(id: Id) -> Self {
+        Self::Sha1(id.0)
+    }
+}
+
+impl Backend for Gitoxide {
+    fn open(path: &Path) -> Result<Self> {
+        let mut repo = gix::open(path).context("failed to open repository")?;
+        repo.object_cache_size_if_unset(OBJECT_CACHE);
+
+        Ok(Self(repo))
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn head_name(&self) -> Result<String> {
+        let head = self.0.head()?;
+
+        // like libgit2, there is no head before the first commit
+        head.id().context("repository has no commits")?;
+
+        Ok(head
+            .referent_name()
+            .map_or_else(|| "HEAD".to_string(), |name| name.shorten().to_string()))
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn resolve(&self, spec: &str) -> Result<Option<Id>> {
+        let id = match self.0.rev_parse_single(spec) {
+            Ok(id) => id,
+            Err(err) => {
+                tracing::warn!(err=?err, "failed to revparse commit");
+                return Ok(None);
+            }
+        };
+
+        match id.object()?.peel_to_commit() {
+            Ok(commit) => Ok(Some(commit.id.into())),
+            Err(err) => {
+                tracing::warn!(err=?err, "failed to peel object to commit");
+                Ok(None)
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn commit(&self, spec: &str) -> Result<Option<Commit>> {
+        let Some(id) = self.resolve(spec)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(commit_of(&self.0.find_commit(ObjectId::from(id))?)?))
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn walk(&self, tips: &[Id], hidden: &[Id], sorting: Sorting) -> Result<Walk<'_>> {
+        let tips = tips.iter().copied().map(ObjectId::from);
+        let hidden = hidden.iter().copied().map(ObjectId::from);
+
+        let order = match sorting {
+            Sorting::Time => {
+                let walk = self
+                    .0
+                    .rev_walk(tips)
+                    .with_hidden(hidden)
+                    .sorting(walk::Sorting::ByCommitTime(CommitTimeOrder::NewestFirst))
+                    .all()?;
+
+                return Ok(Box::new(walk.map(|info| Ok(info?.id.into()))));
+            }
+            Sorting::Date => topo::Sorting::DateOrder,
+            Sorting::Topo => topo::Sorting::TopoOrder,
+        };
+
+        // the simple walk can't keep children before their parents
+        let walk = topo::Builder::from_iters(&self.0.objects, tips, Some(hidden))
+            .sorting(order)
+            .with_commit_graph(self.0.commit_graph_if_enabled().ok().flatten())
+            .build()?;
+
+        Ok(Box::new(walk.map(|info| Ok(info?.id.into()))))
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn changes(&self, commit: Id, path: &Path) -> Result<bool> {
+        let commit = self.0.find_commit(ObjectId::from(commit))?;
+        let id = self.path_id(&commit, path)?;
+
+        let mut parents = commit.parent_ids().peekable();
+
+        if parents.peek().is_none() {
+            return Ok(id.is_some());
+        }
+
+        for parent in parents {
+            let parent = parent.object()?.try_into_commit()?;
+
+            if self.path_id(&parent, path)? != id {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn entry(&self, tree: Id, path: &Path) -> Result<Option<Entry>> {
+        let tree = self.0.find_tree(ObjectId::from(tree))?;
+
+        let Some(entry) = tree.lookup_entry_by_path(path)? else {
+            return Ok(None);
+        };
+
+        Ok(entry_of(
+            entry.filename().to_string(),
+            entry.object_id(),
+            entry.mode(),
+        ))
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn tree(&self, id: Id) -> Result<Vec<Entry>> {
+        let tree = self.0.find_tree(ObjectId::from(id))?;
+
+        let mut entries = Vec::new();
+
+        for entry in tree.iter() {
+            let entry = entry?;
+
+            entries.extend(entry_of(
+                entry.filename().to_string(),
+                entry.object_id(),
+                entry.mode(),
+            ));
+        }
+
+        Ok(entries)
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn blob(&self, id: Id) -> Result<Vec<u8>> {
+        let mut blob = self.0.find_blob(ObjectId::from(id))?;
+
+        Ok(blob.take_data())
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn tags(&self) -> Result<Vec<Tag>> {
+        let references = self.0.references()?;
+
+        let mut tags = Vec::new();
+
+        for reference in references.tags()? {
+            let Ok(reference) = reference else {
+                tracing::warn!("failed to read tag reference");
+                continue;
+            };
+
+            let Ok(name) = str::from_utf8(reference.name().shorten()) else {
+                continue;
+            };
+
+            let Some(id) = reference.try_id() else {
+                continue;
+            };
+
+            match self.tag(name.to_string(), id.detach()) {
+                Ok(Some(tag)) => tags.push(tag),
+                Ok(None) => {}
+                Err(err) => tracing::warn!(err=?err, "failed to read tag"),
+            }
+        }
+
+        Ok(tags)
+    }
+}
+
+impl Gitoxide {
+    fn path_id(&self, commit: &gix::Commit<'_>, path: &Path) -> Result<Option<Id>> {
+        Ok(self
+            .entry(commit.tree_id()?.into(), path)?
+            .map(|entry| entry.id))
+    }
+
+    /// A tag, `None` for a lightweight tag of anything but a commit.
+    fn tag(&self, name: String, id: ObjectId) -> Result<Option<Tag>> {
+        let obj = self.0.find_object(id)?;
+
+        let (target, message, tagger) = match obj.kind {
+            ObjectKind::Tag => {
+                let commit = obj.clone().peel_to_commit().ok();
+                let tag = obj.try_into_tag()?;
+
+                let tagger = match (tag.tagger()?, &commit) {
+                    (Some(tagger), _) => signature_of(tagger)?,
+                    (None, Some(commit)) => signature_of(commit.committer()?)?,
+                    (None, None) => return Ok(None),
+                };
+
+                let target = match &commit {
+                    Some(commit) => commit.id.into(),
+                    None => tag.target_id()?.into(),
+                };
+
+                (target, message_of(&tag.data), tagger)
+            }
+            // lightweight tag
+            ObjectKind::Commit => {
+                let commit = obj.try_into_commit()?;
+
+                (
+                    commit.id.into(),
+                    String::new(),
+                    signature_of(commit.committer()?)?,
+                )
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(Tag {
+            name,
+            target,
+            message,
+            tagger,
+        }))
+    }
+}
+
+fn commit_of(commit: &gix::Commit<'_>) -> Result<Commit> {
+    Ok(Commit {
+        id: commit.id.into(),
+        tree: commit.tree_id()?.into(),
+        parents: commit.parent_ids().map(Id::from).collect(),
+        author: signature_of(commit.author()?)?,
+        committer: signature_of(commit.committer()?)?,
+        message: message_of(&commit.data),
+    })
+}
+
+/// The message of a commit or tag, everything after the headers as libgit2
+/// has it, including the signature of a signed tag.
+fn message_of(data: &[u8]) -> String {
+    let message = data
+        .windows(2)
+        .position(|pair| pair == b"\n\n")
+        .map_or(&[][..], |end| &data[end + 2..]);
+
+    String::from_utf8_lossy(message).into_owned()
+}
+
+fn signature_of(signature: SignatureRef<'_>) -> Result<Signature> {
+    let signature = signature.trim();
+    let time = signature.time()?;
+
+    Ok(Signature {
+        name: signature.name.to_string(),
+        email: signature.email.to_string(),
+        time: time.seconds,
+        offset: time.offset / 60,
+    })
+}
+
+fn entry_of(name: String, id: ObjectId, mode: EntryMode) -> Option<Entry> {
+    let kind = if mode.is_tree() {
+        Kind::Tree
+    } else if mode.is_commit() {
+        Kind::Commit
+    } else if mode.is_blob_or_symlink() {
+        Kind::Blob
+    } else {
+        return None;
+    };
+
+    Some(Entry {
+        name,
+        id: id.into(),
+        kind,
+        mode: u32::from(mode.value()),
+    })
+}
@@ -0,0 +1,241 @@
+use std::{ops::Deref, path::Path};
+
+use git2::{ErrorCode, ObjectType, Oid, Sort};
+
+use crate::{
+    error::{Context as _, Result},
+    git::backend::{Backend, Commit, Entry, Id, Kind, Signature, Sorting, Tag, Walk},
+};
+
+/// Reads repositories with libgit2, which is also used for everything that is
+/// not part of [`Backend`].
+pub struct Libgit2(git2::Repository);
+
+impl Deref for Libgit2 {
+    type Target = git2::Repository;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Backend for Libgit2 {
+    fn open(path: &Path) -> Result<Self> {
+        let repo = git2::Repository::open(path).context("failed to open repository")?;
+
+        Ok(Self(repo))
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn head_name(&self) -> Result<String> {
+        let head = self.0.head()?;
+
+        let name = if head.is_branch() {
+            head.shorthand().context("branch name is not UTF-8")?
+        } else {
+            "HEAD"
+        };
+
+        Ok(name.to_string())
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn resolve(&self, spec: &str) -> Result<Option<Id>> {
+        let obj = match self.0.revparse_single(spec) {
+            Ok(obj) => obj,
+            Err(err) => {
+                tracing::warn!(err=?err, "failed to revparse commit");
+                return Ok(None);
+            }
+        };
+
+        match obj.peel_to_commit() {
+            Ok(commit) => Ok(Some(commit.id().into())),
+            Err(err) => {
+                tracing::warn!(err=?err, "failed to peel object to commit");
+                Ok(None)
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn commit(&self, spec: &str) -> Result<Option<Commit>> {
+        let Some(id) = self.resolve(spec)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(commit_of(&self.0.find_commit(id.into())?)))
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn walk(&self, tips: &[Id], hidden: &[Id], sorting: Sorting) -> Result<Walk<'_>> {
+        let mut revwalk = self.0.revwalk().context("failed to create revwalk")?;
+
+        for tip in tips {
+            revwalk.push((*tip).into())?;
+        }
+
+        for id in hidden {
+            revwalk.hide((*id).into())?;
+        }
+
+        revwalk.set_sorting(match sorting {
+            // like `git log`, `Sort::TIME` would sort the whole history first and
+            // show a commit made with a clock ahead before its children
+            Sorting::Time => Sort::NONE,
+            Sorting::Date => Sort::TIME | Sort::TOPOLOGICAL,
+            Sorting::Topo => Sort::TOPOLOGICAL,
+        })?;
+
+        Ok(Box::new(revwalk.map(|id| Ok(id?.into()))))
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn changes(&self, commit: Id, path: &Path) -> Result<bool> {
+        let commit = self.0.find_commit(commit.into())?;
+        let id = self.path_id(&commit, path)?;
+
+        if commit.parent_count() == 0 {
+            return Ok(id.is_some());
+        }
+
+        for parent in commit.parents() {
+            if self.path_id(&parent, path)? != id {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn entry(&self, tree: Id, path: &Path) -> Result<Option<Entry>> {
+        let tree = self.0.find_tree(tree.into())?;
+
+        let entry = match tree.get_path(path) {
+            Ok(entry) => entry,
+            Err(err) if err.code() == ErrorCode::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(entry_of(&entry))
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn tree(&self, id: Id) -> Result<Vec<Entry>> {
+        let tree = self.0.find_tree(id.into())?;
+
+        Ok(tree.iter().filter_map(|entry| entry_of(&entry)).collect())
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn blob(&self, id: Id) -> Result<Vec<u8>> {
+        let blob = self.0.find_blob(id.into())?;
+
+        Ok(blob.content().to_vec())
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn tags(&self) -> Result<Vec<Tag>> {
+        let mut tags = Vec::new();
+
+        self.0.tag_foreach(|id, name| {
+            // remove prefix "refs/tags/"
+            let Ok(name) = String::from_utf8(name[10..].to_vec()) else {
+                return true;
+            };
+
+            match self.tag(name, id) {
+                Ok(Some(tag)) => tags.push(tag),
+                Ok(None) => {}
+                Err(err) => tracing::warn!(err=?err, "failed to read tag"),
+            }
+
+            true
+        })?;
+
+        Ok(tags)
+    }
+}
+
+impl Libgit2 {
+    fn path_id(&self, commit: &git2::Commit<'_>, path: &Path) -> Result<Option<Oid>> {
+        Ok(self
+            .entry(commit.tree_id().into(), path)?
+            .map(|entry| entry.id.into()))
+    }
+
+    /// A tag, `None` for a lightweight tag of anything but a commit.
+    fn tag(&self, name: String, id: Oid) -> Result<Option<Tag>> {
+        let obj = self.0.find_object(id, None)?;
+
+        let (target, message, tagger) = match obj.kind() {
+            Some(ObjectType::Tag) => {
+                let tag = obj.as_tag().context("git object is not a tag")?;
+                let commit = obj.peel_to_commit().ok();
+
+                let tagger = match (tag.tagger(), &commit) {
+                    (Some(tagger), _) => signature_of(&tagger),
+                    (None, Some(commit)) => signature_of(&commit.committer()),
+                    (None, None) => return Ok(None),
+                };
+
+                (
+                    commit.map_or_else(|| tag.target_id(), |commit| commit.id()),
+                    String::from_utf8_lossy(tag.message_bytes().unwrap_or_default()).into_owned(),
+                    tagger,
+                )
+            }
+            // lightweight tag
+            Some(ObjectType::Commit) => {
+                let commit = obj.as_commit().context("git object is not a commit")?;
+
+                (obj.id(), String::new(), signature_of(&commit.committer()))
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(Tag {
+            name,
+            target: target.into(),
+            message,
+            tagger,
+        }))
+    }
+}
+
+fn commit_of(commit: &git2::Commit<'_>) -> Commit {
+    Commit {
+        id: commit.id().into(),
+        tree: commit.tree_id().into(),
+        parents: commit.parent_ids().map(Id::from).collect(),
+        author: signature_of(&commit.author()),
+        committer: signature_of(&commit.committer()),
+        message: String::from_utf8_lossy(commit.message_raw_bytes()).into_owned(),
+    }
+}
+
+fn signature_of(signature: &git2::Signature<'_>) -> Signature {
+    Signature {
+        name: String::from_utf8_lossy(signature.name_bytes()).into_owned(),
+        email: String::from_utf8_lossy(signature.email_bytes()).into_owned(),
+        time: signature.when().seconds(),
+        offset: signature.when().offset_minutes(),
+    }
+}
+
+fn entry_of(entry: &git2::TreeEntry<'_>) -> Option<Entry> {
+    let kind = match entry.kind()? {
+        ObjectType::Tree => Kind::Tree,
+        ObjectType::Blob => Kind::Blob,
+        ObjectType::Commit => Kind::Commit,
+        _ => return None,
+    };
+
+    Some(Entry {
+        name: String::from_utf8_lossy(entry.name_bytes()).into_owned(),
+        id: entry.id().into(),
+        kind,
+        mode: u32::try_from(entry.filemode()).ok()?,
+    })
+}
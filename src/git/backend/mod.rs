@@ -0,0 +1,195 @@
+//! Reading repositories through either libgit2 or gitoxide.
+//!
+//! [`Repository`](super::Repository) looks up commits, trees, tags and the
+//! history through the [`Selected`] backend and hands out `git2` objects for
+//! the rest. The `gix` feature selects gitoxide, so the two can be compared on
+//! the same repositories, see `tests/backend.rs`.
+
+#[cfg(feature = "gix")]
+mod gitoxide;
+mod libgit2;
+
+use std::{fmt, path::Path};
+
+use crate::error::Result;
+
+#[cfg(feature = "gix")]
+pub use self::gitoxide::Gitoxide;
+pub use self::libgit2::Libgit2;
+
+/// The backend used to read repositories.
+#[cfg(feature = "gix")]
+pub type Selected = Gitoxide;
+/// The backend used to read repositories.
+#[cfg(not(feature = "gix"))]
+pub type Selected = Libgit2;
+
+/// The id of an object, a SHA-1 hash.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Id(pub [u8; 20]);
+
+impl fmt::Debug for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Id({self})")
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<git2::Oid> for Id {
+    fn from(id: git2::Oid) -> Self {
+        let mut bytes = [0; 20];
+        bytes.copy_from_slice(id.as_bytes());
+
+        Self(bytes)
+    }
+}
+
+impl From<Id> for git2::Oid {
+    fn from(id: Id) -> Self {
+        Self::from_bytes(&id.0).expect("an id is always 20 bytes")
+    }
+}
+
+/// Who made a commit or tag, and when.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub name: String,
+    pub email: String,
+    /// Seconds since the epoch
+    pub time: i64,
+    /// Minutes east of UTC
+    pub offset: i32,
+}
+
+impl Signature {
+    /// The time as `git2` has it, for the filters shared with `git2` signatures.
+    pub(crate) fn when(&self) -> git2::Time {
+        git2::Time::new(self.time, self.offset)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub id: Id,
+    pub tree: Id,
+    pub parents: Vec<Id>,
+    pub author: Signature,
+    pub committer: Signature,
+    pub message: String,
+}
+
+/// What a tree entry points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Tree,
+    Blob,
+    /// A submodule
+    Commit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub id: Id,
+    pub kind: Kind,
+    /// The file mode, like `0o100644`
+    pub mode: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    /// The name without `refs/tags/`
+    pub name: String,
+    /// The commit that is tagged, or the object for tags of anything else
+    pub target: Id,
+    /// Empty for lightweight tags
+    pub message: String,
+    /// The committer of the tagged commit for lightweight tags and tags
+    /// without a tagger
+    pub tagger: Signature,
+}
+
+/// The order a walk of the history visits commits in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sorting {
+    /// Newest commits first
+    Time,
+    /// Newest commits first but never before their children, like
+    /// `git log --date-order`
+    Date,
+    /// Commits of a branch together, like `git log --topo-order`
+    Topo,
+}
+
+/// The ids of the commits a walk visits, the walk ends at the first error.
+pub type Walk<'r> = Box<dyn Iterator<Item = Result<Id>> + 'r>;
+
+/// Reads a repository, everything a page needs to find its objects.
+///
+/// Objects that are missing are `None`, anything else that fails is an error.
+pub trait Backend: Sized {
+    fn open(path: &Path) -> Result<Self>;
+
+    /// The branch `HEAD` points at, or `HEAD` when it is detached.
+    fn head_name(&self) -> Result<String>;
+
+    /// The id of the commit a branch, tag or id names.
+    fn resolve(&self, spec: &str) -> Result<Option<Id>>;
+
+    /// The commit a branch, tag or id names.
+    fn commit(&self, spec: &str) -> Result<Option<Commit>>;
+
+    /// Walks the commits reachable from `tips` but not from `hidden`, like
+    /// `git log tips ^hidden`.
+    fn walk(&self, tips: &[Id], hidden: &[Id], sorting: Sorting) -> Result<Walk<'_>>;
+
+    /// Whether a commit changes `path`, that is the entry at `path` differs from
+    /// the one in at least one parent, or it is a root commit that has it.
+    fn changes(&self, commit: Id, path: &Path) -> Result<bool>;
+
+    /// The ids of up to `amount` commits reachable from `tip`, newest first
+    /// but never before their children, like `git log`.
+    ///
+    /// With a `path` only the commits [changing](Self::changes) it.
+    fn history(&self, tip: Id, path: Option<&Path>, amount: usize) -> Result<Vec<Id>> {
+        let mut ids = Vec::new();
+
+        for id in self.walk(&[tip], &[], Sorting::Date)? {
+            if ids.len() == amount {
+                break;
+            }
+
+            let id = id?;
+
+            if let Some(path) = path
+                && !self.changes(id, path)?
+            {
+                continue;
+            }
+
+            ids.push(id);
+        }
+
+        Ok(ids)
+    }
+
+    /// The entry at `path` in a tree, looking through the trees on the way.
+    fn entry(&self, tree: Id, path: &Path) -> Result<Option<Entry>>;
+
+    /// The entries of a tree, in the order they are stored in.
+    fn tree(&self, id: Id) -> Result<Vec<Entry>>;
+
+    fn blob(&self, id: Id) -> Result<Vec<u8>>;
+
+    /// All tags, in no particular order.
+    fn tags(&self) -> Result<Vec<Tag>>;
+}
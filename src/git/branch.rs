@@ -1,6 +1,11 @@
-use git2::{Branch, BranchType, Reference};
+use std::collections::HashMap;
 
-use crate::{error::Result, git::Repository};
+use git2::{Oid, Reference};
+
+use crate::{
+    error::Result,
+    git::{Decoration, Repository},
+};
 
 impl Repository {
     #[tracing::instrument(skip_all)]
@@ -15,14 +20,77 @@ impl Repository {
         Ok(branches)
     }
 
-    #[tracing::instrument(skip_all)]
-    pub(crate) fn branches_of_type(&self, typ: BranchType) -> Result<Vec<Branch<'_>>> {
-        let branches = self
-            .inner
-            .branches(Some(typ))?
-            .filter_map(|x| if let Ok(x) = x { Some(x.0) } else { None })
+    /// The branches and tags pointing at a commit, branches first.
+    pub(crate) fn decorations(&self, id: Oid) -> &[Decoration] {
+        self.all_decorations().get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// The commits any branch or tag points at.
+    pub(crate) fn tips(&self) -> Vec<Oid> {
+        let mut tips: Vec<Oid> = self.all_decorations().keys().copied().collect();
+        tips.sort_unstable();
+
+        tips
+    }
+
+    /// Every branch and tag with the commit it points at, sorted by name.
+    pub(crate) fn ref_tips(&self) -> Vec<(String, Oid)> {
+        let mut tips: Vec<(String, Oid)> = self
+            .all_decorations()
+            .iter()
+            .flat_map(|(id, list)| {
+                list.iter().map(|decoration| {
+                    let kind = if decoration.is_tag { "tags" } else { "heads" };
+
+                    (format!("{kind}/{}", decoration.name), *id)
+                })
+            })
             .collect();
+        tips.sort_unstable();
 
-        Ok(branches)
+        tips
+    }
+
+    #[tracing::instrument(skip_all)]
+    fn all_decorations(&self) -> &HashMap<Oid, Vec<Decoration>> {
+        self.decorations.get_or_init(|| {
+            let mut decorations: HashMap<Oid, Vec<Decoration>> = HashMap::new();
+
+            let references = match self.inner.references() {
+                Ok(references) => references,
+                Err(err) => {
+                    tracing::error!(err=?err, "failed to create references iterator");
+                    return decorations;
+                }
+            };
+
+            for reference in references.filter_map(Result::ok) {
+                let is_tag = reference.is_tag();
+
+                if !is_tag && !reference.is_branch() {
+                    continue;
+                }
+
+                // tags can point at trees or blobs too
+                let (Some(name), Ok(commit)) = (reference.shorthand(), reference.peel_to_commit())
+                else {
+                    continue;
+                };
+
+                decorations
+                    .entry(commit.id())
+                    .or_default()
+                    .push(Decoration {
+                        name: name.to_string(),
+                        is_tag,
+                    });
+            }
+
+            for list in decorations.values_mut() {
+                list.sort_by(|a, b| (a.is_tag, &a.name).cmp(&(b.is_tag, &b.name)));
+            }
+
+            decorations
+        })
     }
 }
@@ -0,0 +1,65 @@
+//! Last commits of paths, which walk a lot of history to find.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use git2::Oid;
+
+/// Entries kept before the oldest ones are dropped.
+const CAPACITY: usize = 100_000;
+
+/// The repository, the commit looked up from and the path in it.
+type Key = (PathBuf, Oid, PathBuf);
+
+/// The last commit changing a path, as seen from a commit.
+///
+/// Commits never change, so neither does their history and an entry never goes
+/// stale, it only has to make room for newer ones.
+#[derive(Default)]
+pub(crate) struct LastCommitCache {
+    entries: Mutex<Entries>,
+}
+
+#[derive(Default)]
+struct Entries {
+    last_commits: HashMap<Key, Oid>,
+    /// Insertion order, oldest first
+    order: VecDeque<Key>,
+}
+
+impl LastCommitCache {
+    pub(crate) fn get(&self, repo: &Path, commit: Oid, path: &Path) -> Option<Oid> {
+        let key = (repo.to_path_buf(), commit, path.to_path_buf());
+
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .last_commits
+            .get(&key)
+            .copied()
+    }
+
+    /// Remembers `last` as the last commit changing `path` for all of `commits`.
+    pub(crate) fn insert(&self, repo: &Path, commits: &[Oid], path: &Path, last: Oid) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        for commit in commits {
+            let key = (repo.to_path_buf(), *commit, path.to_path_buf());
+
+            if entries.last_commits.insert(key.clone(), last).is_none() {
+                entries.order.push_back(key);
+            }
+        }
+
+        while entries.order.len() > CAPACITY {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.last_commits.remove(&oldest);
+            }
+        }
+
+        drop(entries);
+    }
+}
@@ -1,29 +1,32 @@
-use std::ffi::CString;
-
-use git2::{Commit, Diff, DiffOptions, DiffStats, Sort, Tree};
-
-use crate::{error::Context as _, error::Result, git::Repository, http::extractor::ObjectName};
+use std::{collections::VecDeque, path::PathBuf};
+
+use git2::{Commit, Diff, DiffOptions, DiffStats, Oid, Tree};
+
+use crate::{
+    error::Context as _,
+    error::Result,
+    git::{
+        Cursor, Order, Page, Repository, Tips,
+        backend::{self, Backend as _, Sorting},
+        commit_graph::BloomKey,
+        graph::{Lanes, Row},
+    },
+    http::extractor::ObjectName,
+};
 
 impl Repository {
     #[tracing::instrument(skip_all)]
     pub(crate) fn commit(&self, spec: &str) -> Result<Option<Commit<'_>>> {
-        let obj = match self.inner.revparse_single(spec) {
-            Ok(obj) => obj,
-            Err(err) => {
-                tracing::warn!(err=?err, "failed to revparse commit");
-                return Ok(None);
-            }
+        let Some(id) = self.backend()?.resolve(spec)? else {
+            return Ok(None);
         };
 
-        let commit = match obj.peel_to_commit() {
-            Ok(commit) => commit,
-            Err(err) => {
-                tracing::warn!(err=?err, "failed to peel object to commit");
-                return Ok(None);
-            }
-        };
+        Ok(Some(self.inner.find_commit(id.into())?))
+    }
 
-        Ok(Some(commit))
+    /// A commit by its id, which unlike [`Self::commit`] doesn't parse a revision.
+    pub(crate) fn find_commit(&self, id: Oid) -> Result<Commit<'_>> {
+        Ok(self.inner.find_commit(id)?)
     }
 
     #[tracing::instrument(skip_all)]
@@ -43,6 +46,44 @@ impl Repository {
         Ok(diff)
     }
 
+    /// The changes between two commits, as `git diff from to`.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn compare_diff(&self, from: &Commit<'_>, to: &Commit<'_>) -> Result<Diff<'_>> {
+        let mut options = DiffOptions::new();
+
+        let diff = self.inner.diff_tree_to_tree(
+            Some(&from.tree()?),
+            Some(&to.tree()?),
+            Some(&mut options),
+        )?;
+
+        Ok(diff)
+    }
+
+    /// Commits reachable from `to` but not from `from`, newest first.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn commits_between(
+        &self,
+        from: &Commit<'_>,
+        to: &Commit<'_>,
+        amount: usize,
+    ) -> Result<Vec<Commit<'_>>> {
+        let walk = self
+            .backend()?
+            .walk(&[to.id().into()], &[from.id().into()], Sorting::Time)
+            .context("failed to walk history")?;
+
+        let commits = walk
+            .filter_map(|id| {
+                id.ok()
+                    .and_then(|id| self.inner.find_commit(id.into()).ok())
+            })
+            .take(amount)
+            .collect();
+
+        Ok(commits)
+    }
+
     #[tracing::instrument(skip_all)]
     pub(crate) fn commit_stats(&self, commit: &Commit<'_>) -> Result<DiffStats> {
         let diff = self.commit_diff(commit)?;
@@ -78,110 +119,159 @@ impl Repository {
         spec: &str,
         amount: usize,
     ) -> Result<Option<Vec<Commit<'_>>>> {
-        let mut revwalk = self.inner.revwalk()?;
+        let backend = self.backend()?;
 
-        let Some(commit) = self.commit(spec)? else {
+        let Some(tip) = backend.resolve(spec)? else {
             return Ok(None);
         };
 
-        revwalk.push(commit.id())?;
-
-        revwalk.set_sorting(Sort::TIME)?;
-
-        let commits = revwalk
-            .filter_map(|oid| oid.ok().and_then(|oid| self.inner.find_commit(oid).ok()))
-            .take(amount)
+        let commits = backend
+            .history(tip, None, amount)?
+            .into_iter()
+            .filter_map(|id| self.inner.find_commit(id.into()).ok())
             .collect();
 
         Ok(Some(commits))
     }
 
+    /// A page of the commits reachable from `tips`, only those changing `obj` if given.
+    ///
+    /// Starts at the tip or continues a previous page from its cursor, which has
+    /// to be walked in the same order.
     #[tracing::instrument(skip_all)]
     pub(crate) fn commits_for_obj(
         &self,
-        spec: &str,
+        tips: Tips<'_>,
         amount: usize,
         obj: Option<&ObjectName>,
-    ) -> Result<Option<Vec<Commit<'_>>>> {
+        from: Option<&Cursor>,
+        order: Order,
+    ) -> Result<Option<Page<'_>>> {
         if self.is_shallow() {
-            return self
+            let tips = self.tip_ids(tips)?.unwrap_or_default();
+
+            return Ok(self
                 .commits_shallow()
-                .context("failed to get commits on shallow repo");
+                .context("failed to get commits on shallow repo")?
+                .map(|commits| Page {
+                    tips,
+                    commits,
+                    graph: Vec::new(),
+                    next: None,
+                    has_prev: false,
+                }));
         }
 
-        let mut revwalk = self.inner.revwalk().context("failed to create revwalk")?;
-
-        let Some(commit) = self.commit(spec).context("failed to get commit")? else {
+        let Some(tips) = self.tip_ids(tips)? else {
             return Ok(None);
         };
 
-        revwalk
-            .push(commit.id())
-            .context("failed to set root commit for revwalk")?;
+        let pending = match from {
+            Some(Cursor::Pending(pending)) => Some(pending.as_slice()),
+            _ => None,
+        };
 
-        revwalk
-            .set_sorting(Sort::TIME)
-            .context("failed to set revwalk sorting mode")?;
+        let mut walk = Walk::new(self, tips.clone(), pending, obj, order)?;
 
-        let commits =
-            revwalk.filter_map(|oid| oid.ok().and_then(|oid| self.inner.find_commit(oid).ok()));
+        let mut commits = Vec::new();
+        let mut graph = Vec::new();
+        let mut next = None;
 
-        let Some(Ok(path)) = obj.map(|n| n.0.as_str()).map(CString::new) else {
-            return Ok(Some(commits.take(amount).collect()));
+        if let Some(Cursor::Rewalk(id)) = from {
+            match walk.find(|commit| commit.id() == *id) {
+                Some(commit) => {
+                    commits.push(commit);
+                    graph.extend(walk.row());
+                }
+                // the page no longer exists, e.g. after a force push
+                None => walk = Walk::new(self, tips.clone(), None, obj, order)?,
+            }
+        }
+
+        while let Some(commit) = walk.next() {
+            if commits.len() == amount {
+                // there is another page, starting with this commit
+                next = Some(walk.cursor(commit.id()));
+                break;
+            }
+
+            commits.push(commit);
+            graph.extend(walk.row());
+        }
+
+        Ok(Some(Page {
+            tips,
+            commits,
+            graph,
+            next,
+            has_prev: from.is_some(),
+        }))
+    }
+
+    /// The page of commits right before `before`, walking from `tips`.
+    ///
+    /// Pages only link forward, so going back has to find where the page started.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn commits_before_obj(
+        &self,
+        tips: Tips<'_>,
+        amount: usize,
+        obj: Option<&ObjectName>,
+        before: Oid,
+        order: Order,
+    ) -> Result<Option<Page<'_>>> {
+        if self.is_shallow() {
+            return self.commits_for_obj(tips, amount, obj, None, order);
+        }
+
+        let Some(tips) = self.tip_ids(tips)? else {
+            return Ok(None);
         };
 
-        // filter for specific file if necessary
-        let mut options = DiffOptions::new();
-        options.pathspec(path);
+        let mut walk = Walk::new(self, tips.clone(), None, obj, order)?;
 
-        let commits = commits
-            .into_iter()
-            .filter(|walked_commit| {
-                let old_tree = match walked_commit.tree() {
-                    Ok(tree) => tree,
-                    Err(err) => {
-                        tracing::error!(err=?err, "failed to get commit tree");
-                        return false;
-                    }
-                };
-
-                // check that the given file was affected from any of the parents
-                walked_commit.parents().any(|parent| {
-                    let new_tree = match parent.tree() {
-                        Ok(tree) => tree,
-                        Err(err) => {
-                            tracing::error!(err=?err, "failed to get parent commit tree");
-                            return false;
-                        }
-                    };
-
-                    let diff = match self.inner.diff_tree_to_tree(
-                        Some(&old_tree),
-                        Some(&new_tree),
-                        Some(&mut options),
-                    ) {
-                        Ok(diff) => diff,
-                        Err(err) => {
-                            tracing::error!(err=?err, "failed to diff trees");
-                            return false;
-                        }
-                    };
-
-                    let stats = match diff.stats() {
-                        Ok(stats) => stats,
-                        Err(err) => {
-                            tracing::error!(err=?err, "failed to get diff stats");
-                            return false;
-                        }
-                    };
-
-                    stats.files_changed() > 0
-                })
-            })
-            .take(amount)
-            .collect();
+        let mut commits = VecDeque::with_capacity(amount);
+        let mut graph = VecDeque::with_capacity(amount);
+        let mut has_prev = false;
 
-        Ok(Some(commits))
+        loop {
+            // the page no longer exists, e.g. after a force push
+            let Some(commit) = walk.next() else {
+                return Ok(None);
+            };
+
+            if commit.id() == before {
+                break;
+            }
+
+            if commits.len() == amount {
+                commits.pop_front();
+                graph.pop_front();
+                has_prev = true;
+            }
+
+            commits.push_back(commit);
+            graph.extend(walk.row());
+        }
+
+        Ok(Some(Page {
+            tips,
+            commits: commits.into(),
+            graph: graph.into(),
+            next: Some(walk.cursor(before)),
+            has_prev,
+        }))
+    }
+
+    /// The commits a walk starts at, `None` if `tips` does not name a commit.
+    fn tip_ids(&self, tips: Tips<'_>) -> Result<Option<Vec<Oid>>> {
+        match tips {
+            Tips::Spec(spec) => Ok(self
+                .commit(spec)
+                .context("failed to get commit")?
+                .map(|commit| vec![commit.id()])),
+            Tips::All => Ok(Some(self.tips())),
+        }
     }
 
     #[tracing::instrument(skip_all)]
@@ -196,81 +286,129 @@ impl Repository {
 
         Ok(Some(commits))
     }
+}
+
+/// A walk of the history that remembers where it is, so it can be resumed.
+struct Walk<'r> {
+    repo: &'r Repository,
+    /// The commits in the order they are walked
+    ids: backend::Walk<'r>,
+    /// The commits the walk is waiting for, once they are reached
+    lanes: Lanes,
+    /// Where the walk started, until the walk reaches them
+    tips: Vec<Oid>,
+    /// The lanes and tips before the last commit, to continue from it
+    previous: Vec<Oid>,
+    /// How to draw the last commit in the graph, not drawn when filtering for a path
+    row: Option<Row>,
+    /// Set when filtering for a path
+    path: Option<PathBuf>,
+    /// The path to look up in the Bloom filters of the commit-graph, if it has them
+    key: Option<BloomKey>,
+}
+
+impl<'r> Walk<'r> {
+    /// Walks from `tips`, or from the commits a previous walk stopped at.
+    fn new(
+        repo: &'r Repository,
+        tips: Vec<Oid>,
+        pending: Option<&[Oid]>,
+        obj: Option<&ObjectName>,
+        order: Order,
+    ) -> Result<Self> {
+        // lanes continue from the previous page, tips start their own once reached
+        let (tips, lanes) = pending.map_or_else(
+            || (tips, Vec::new()),
+            |pending| (pending.to_vec(), pending.to_vec()),
+        );
+
+        let tip_ids: Vec<_> = tips.iter().copied().map(backend::Id::from).collect();
+        let ids = repo
+            .backend()?
+            .walk(&tip_ids, &[], order.sorting())
+            .context("failed to walk history")?;
+
+        let path = obj.map(|name| PathBuf::from(name.0.trim_matches('/')));
+        let key = path.as_deref().and_then(|path| repo.bloom_key(path));
+
+        Ok(Self {
+            repo,
+            ids,
+            previous: tips.clone(),
+            tips,
+            lanes: Lanes::new(lanes),
+            row: None,
+            path,
+            key,
+        })
+    }
 
-    #[tracing::instrument(skip_all)]
-    pub(crate) fn file_last_commit<P: git2::IntoCString>(
-        &self,
-        spec: &str,
-        path: P,
-    ) -> Result<Option<Commit<'_>>> {
-        let mut revwalk = self.inner.revwalk()?;
+    /// Continues the walk with `next`, the last commit returned by
+    /// [`Iterator::next`].
+    fn cursor(&self, next: Oid) -> Cursor {
+        Cursor::new(self.previous.clone(), next)
+    }
 
-        let Some(commit) = self.commit(spec)? else {
-            return Ok(None);
-        };
+    /// How to draw the last commit returned by [`Iterator::next`].
+    const fn row(&mut self) -> Option<Row> {
+        self.row.take()
+    }
+}
 
-        revwalk.push(commit.id())?;
+impl<'r> Iterator for Walk<'r> {
+    type Item = Commit<'r>;
 
-        revwalk.set_sorting(Sort::TIME)?;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let oid = match self.ids.next()? {
+                Ok(id) => id.into(),
+                Err(err) => {
+                    tracing::error!(err=?err, "failed to walk history");
+                    return None;
+                }
+            };
 
-        let mut options = DiffOptions::new();
-        options.pathspec(path.into_c_string()?);
+            let Some(parents) = self.repo.parent_ids(oid) else {
+                continue;
+            };
+
+            self.previous = self.lanes.ids().to_vec();
+            for tip in &self.tips {
+                if !self.previous.contains(tip) {
+                    self.previous.push(*tip);
+                }
+            }
+            self.tips.retain(|tip| *tip != oid);
 
-        let last_commit = revwalk
-            .filter_map(|oid| oid.ok().and_then(|oid| self.inner.find_commit(oid).ok()))
-            .find(|walked_commit| {
-                let commit_tree = match walked_commit.tree() {
-                    Ok(tree) => tree,
+            let row = self.lanes.advance(oid, &parents);
+
+            // the commit-graph rules out most commits without diffing them, as
+            // long as there is no other parent the path could differ from
+            if parents.len() <= 1 && self.repo.unchanged(oid, self.key.as_ref()) {
+                continue;
+            }
+
+            if let Some(path) = &self.path {
+                match self
+                    .repo
+                    .backend()
+                    .and_then(|backend| backend.changes(oid.into(), path))
+                {
+                    Ok(true) => {}
+                    Ok(false) => continue,
                     Err(err) => {
-                        tracing::error!(err=?err, "failed to get commit tree");
-                        return false;
+                        tracing::error!(err=?err, "failed to check commit for changes");
+                        continue;
                     }
-                };
-
-                let mut files_changed = |child: &Tree<'_>, parent: Option<&Tree<'_>>| {
-                    let diff = match self.inner.diff_tree_to_tree(
-                        parent,
-                        Some(child),
-                        Some(&mut options),
-                    ) {
-                        Ok(diff) => diff,
-                        Err(err) => {
-                            tracing::error!(err=?err, "failed to diff trees");
-                            return false;
-                        }
-                    };
-
-                    let stats = match diff.stats() {
-                        Ok(stats) => stats,
-                        Err(err) => {
-                            tracing::error!(err=?err, "failed to get diff stats");
-                            return false;
-                        }
-                    };
-
-                    stats.files_changed() > 0
-                };
-
-                if walked_commit.parent_count() == 0 {
-                    files_changed(&commit_tree, None)
-                } else {
-                    // check that the given file was affected from any of the parents
-                    walked_commit.parents().any(|parent| {
-                        let parent_tree = match parent.tree() {
-                            Ok(tree) => tree,
-                            Err(err) => {
-                                tracing::error!(err=?err, "failed to get parent commit tree, this should not happen");
-                                return false;
-                            }
-                        };
-
-                        files_changed(&commit_tree, Some(&parent_tree))
-                    })
                 }
-            });
+            }
+
+            let Ok(commit) = self.repo.inner.find_commit(oid) else {
+                continue;
+            };
 
-        Ok(Some(
-            last_commit.context("file was not part of any commit")?,
-        ))
+            self.row = self.path.is_none().then_some(row);
+            return Some(commit);
+        }
     }
 }
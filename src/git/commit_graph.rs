@@ -0,0 +1,580 @@
+//! The `commit-graph` files git writes next to the objects.
+//!
+//! They hold the parents and root tree of every commit, so walks don't have to
+//! read the commits themselves, and Bloom filters of the paths each commit
+//! changed. libgit2 already reads the parents and generation numbers for its
+//! revwalks, but not the Bloom filters, which are what rule out most commits
+//! when looking for the ones that changed a path.
+//!
+//! See `gitformat-commit-graph(5)` for the layout.
+
+use std::{
+    collections::HashMap,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex, PoisonError},
+    time::SystemTime,
+};
+
+use git2::Oid;
+
+use crate::{
+    error::{Context as _, Result},
+    git::{Repository, backend::Backend as _},
+};
+
+const HASH_LEN: usize = 20;
+/// Tree id, two parents, and the generation number and commit time
+const COMMIT_DATA_LEN: usize = HASH_LEN + 16;
+/// A parent slot that is not used
+const NO_PARENT: u32 = 0x7000_0000;
+/// Set on the second parent when the rest are in the extra edges, and on the
+/// last of those
+const EXTRA_EDGES: u32 = 0x8000_0000;
+
+/// The commit-graph of a repository, possibly split into a chain of files.
+pub struct CommitGraph {
+    /// Oldest first, positions of commits continue from one layer to the next
+    layers: Vec<Layer>,
+}
+
+/// A single commit-graph file.
+struct Layer {
+    data: Vec<u8>,
+    /// Number of commits in the layers before this one
+    base: u32,
+    len: u32,
+    fanout: usize,
+    ids: usize,
+    commits: usize,
+    edges: Option<usize>,
+    bloom: Option<Bloom>,
+}
+
+/// The changed-path Bloom filters of a layer.
+struct Bloom {
+    /// End offset of each commit's filter in `data`
+    index: usize,
+    data: Range<usize>,
+    settings: BloomSettings,
+}
+
+/// How the filters of a layer were written, git only uses those of layers
+/// written like the first layer with filters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BloomSettings {
+    /// 1 hashes paths as signed chars, 2 as unsigned ones
+    version: u32,
+    hashes: u32,
+    bits_per_entry: u32,
+}
+
+/// A path and its parent directories hashed for the Bloom filters.
+///
+/// Git adds the directories of a changed path to the filters as well, looking
+/// them up too rules out more commits.
+pub struct BloomKey {
+    /// For version 1 filters, which hash bytes as signed chars
+    v1: Vec<(u32, u32)>,
+    v2: Vec<(u32, u32)>,
+}
+
+impl CommitGraph {
+    /// Reads the commit-graph in an objects directory, `None` if there is none.
+    #[tracing::instrument(skip_all)]
+    pub fn open(objects: &Path) -> Option<Self> {
+        let info = objects.join("info");
+
+        let files: Vec<_> = fs::read_to_string(info.join("commit-graphs/commit-graph-chain"))
+            .map_or_else(
+                |_| vec![info.join("commit-graph")],
+                |chain| {
+                    chain
+                        .lines()
+                        .map(|hash| info.join(format!("commit-graphs/graph-{hash}.graph")))
+                        .collect()
+                },
+            );
+
+        let mut layers: Vec<Layer> = Vec::with_capacity(files.len());
+
+        for file in files {
+            let base = layers.last().map_or(0, |layer| layer.base + layer.len);
+
+            let Some(layer) = Layer::read(&file, base) else {
+                if file.exists() {
+                    tracing::warn!(file=?file.display(), "ignoring unreadable commit-graph");
+                }
+
+                return None;
+            };
+
+            layers.push(layer);
+        }
+
+        drop_mixed_bloom(&mut layers);
+
+        Some(Self { layers })
+    }
+
+    /// Whether any layer has Bloom filters.
+    #[must_use]
+    pub fn has_bloom(&self) -> bool {
+        self.layers.iter().any(|layer| layer.bloom.is_some())
+    }
+
+    /// The id of a commit's root tree.
+    #[must_use]
+    pub fn tree_id(&self, id: Oid) -> Option<Oid> {
+        let (layer, at) = self.find(id)?;
+
+        Oid::from_bytes(layer.data.get(at..at + HASH_LEN)?).ok()
+    }
+
+    /// The parents of a commit, in order.
+    #[must_use]
+    pub fn parent_ids(&self, id: Oid) -> Option<Vec<Oid>> {
+        let (layer, at) = self.find(id)?;
+
+        let first = be_u32(&layer.data, at + HASH_LEN)?;
+        let second = be_u32(&layer.data, at + HASH_LEN + 4)?;
+
+        let mut positions = Vec::with_capacity(2);
+
+        if first != NO_PARENT {
+            positions.push(first);
+        }
+
+        if second & EXTRA_EDGES != 0 {
+            let edges = layer.edges?;
+            let mut edge_at = edges + (second & !EXTRA_EDGES) as usize * 4;
+
+            loop {
+                let edge = be_u32(&layer.data, edge_at)?;
+                positions.push(edge & !EXTRA_EDGES);
+
+                if edge & EXTRA_EDGES != 0 {
+                    break;
+                }
+
+                edge_at += 4;
+            }
+        } else if second != NO_PARENT {
+            positions.push(second);
+        }
+
+        positions.into_iter().map(|pos| self.id_at(pos)).collect()
+    }
+
+    /// Whether the Bloom filters rule out that a commit changed the path
+    /// compared to its first parent.
+    ///
+    /// `false` does not mean the path changed, the filters only ever say a path
+    /// might have.
+    #[must_use]
+    pub fn unchanged(&self, id: Oid, key: &BloomKey) -> bool {
+        let Some((layer, at)) = self.find(id) else {
+            return false;
+        };
+
+        let Some(bloom) = &layer.bloom else {
+            return false;
+        };
+
+        let pos = (at - layer.commits) / COMMIT_DATA_LEN;
+
+        let start = match pos {
+            0 => Some(0),
+            _ => be_u32(&layer.data, bloom.index + (pos - 1) * 4),
+        };
+        let end = be_u32(&layer.data, bloom.index + pos * 4);
+
+        let Some(filter) = start.zip(end).and_then(|(start, end)| {
+            layer
+                .data
+                .get(bloom.data.start + start as usize..bloom.data.start + end as usize)
+        }) else {
+            return false;
+        };
+
+        // commits without a computed filter have an empty one
+        if filter.is_empty() {
+            return false;
+        }
+
+        let hashes = if bloom.settings.version == 1 {
+            &key.v1
+        } else {
+            &key.v2
+        };
+
+        hashes
+            .iter()
+            .any(|&(first, second)| !contains(filter, bloom.settings.hashes, first, second))
+    }
+
+    /// The layer holding a commit, and where its data starts in it.
+    fn find(&self, id: Oid) -> Option<(&Layer, usize)> {
+        self.layers.iter().find_map(|layer| {
+            let pos = layer.position(id)?;
+
+            Some((layer, layer.commits + pos as usize * COMMIT_DATA_LEN))
+        })
+    }
+
+    /// The commit at a position across all layers.
+    fn id_at(&self, pos: u32) -> Option<Oid> {
+        let layer = self
+            .layers
+            .iter()
+            .find(|layer| pos >= layer.base && pos < layer.base + layer.len)?;
+
+        let at = layer.ids + (pos - layer.base) as usize * HASH_LEN;
+
+        Oid::from_bytes(layer.data.get(at..at + HASH_LEN)?).ok()
+    }
+}
+
+/// Drops the filters of layers written differently than the first one with
+/// filters, like git does.
+fn drop_mixed_bloom(layers: &mut [Layer]) {
+    let first = layers
+        .iter()
+        .find_map(|layer| Some(layer.bloom.as_ref()?.settings));
+
+    for layer in layers {
+        if layer
+            .bloom
+            .as_ref()
+            .is_some_and(|bloom| Some(bloom.settings) != first)
+        {
+            tracing::warn!("ignoring Bloom filters of a commit-graph layer written differently");
+            layer.bloom = None;
+        }
+    }
+}
+
+impl Layer {
+    fn read(file: &Path, base: u32) -> Option<Self> {
+        let data = fs::read(file).ok()?;
+
+        // signature, version 1 and SHA-1
+        if data.get(..6)? != b"CGPH\x01\x01" {
+            return None;
+        }
+
+        let mut chunks = HashMap::new();
+
+        for i in 0..usize::from(*data.get(6)?) {
+            let at = 8 + i * 12;
+            let id: [u8; 4] = data.get(at..at + 4)?.try_into().ok()?;
+            let start = usize::try_from(be_u64(&data, at + 4)?).ok()?;
+            let end = usize::try_from(be_u64(&data, at + 16)?).ok()?;
+
+            chunks.insert(id, start..end);
+        }
+
+        let fanout = chunks.get(b"OIDF")?.start;
+        let len = be_u32(&data, fanout + 255 * 4)?;
+
+        let ids = chunks.get(b"OIDL")?;
+        let commits = chunks.get(b"CDAT")?;
+
+        if ids.len() != len as usize * HASH_LEN
+            || commits.len() != len as usize * COMMIT_DATA_LEN
+            || data.len() < commits.end.max(ids.end)
+        {
+            return None;
+        }
+
+        let bloom = chunks
+            .get(b"BIDX")
+            .zip(chunks.get(b"BDAT"))
+            .filter(|(index, bloom)| index.len() == len as usize * 4 && bloom.len() >= 12)
+            .and_then(|(index, bloom)| {
+                Some(Bloom {
+                    index: index.start,
+                    data: bloom.start + 12..bloom.end,
+                    settings: BloomSettings {
+                        version: be_u32(&data, bloom.start)?,
+                        hashes: be_u32(&data, bloom.start + 4)?,
+                        bits_per_entry: be_u32(&data, bloom.start + 8)?,
+                    },
+                })
+            })
+            .filter(|bloom| {
+                let settings = bloom.settings;
+
+                matches!(settings.version, 1 | 2)
+                    && settings.hashes > 0
+                    && settings.bits_per_entry > 0
+            });
+
+        Some(Self {
+            base,
+            len,
+            fanout,
+            ids: ids.start,
+            commits: commits.start,
+            edges: chunks.get(b"EDGE").map(|edges| edges.start),
+            bloom,
+            data,
+        })
+    }
+
+    /// Where a commit is in this layer, ids are sorted and the fanout tells
+    /// where each first byte starts.
+    fn position(&self, id: Oid) -> Option<u32> {
+        let first = usize::from(id.as_bytes()[0]);
+
+        let mut low = match first {
+            0 => 0,
+            _ => be_u32(&self.data, self.fanout + (first - 1) * 4)?,
+        };
+        let mut high = be_u32(&self.data, self.fanout + first * 4)?;
+
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let at = self.ids + mid as usize * HASH_LEN;
+
+            match self.data.get(at..at + HASH_LEN)?.cmp(id.as_bytes()) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some(mid),
+            }
+        }
+
+        None
+    }
+}
+
+impl BloomKey {
+    /// Hashes a path, `None` for the root which every commit changes.
+    #[must_use]
+    pub fn new(path: &Path) -> Option<Self> {
+        let path = path.to_str()?.trim_matches('/');
+
+        if path.is_empty() {
+            return None;
+        }
+
+        let prefixes: Vec<&[u8]> = path
+            .match_indices('/')
+            .map(|(i, _)| &path.as_bytes()[..i])
+            .chain([path.as_bytes()])
+            .collect();
+
+        let hash = |signed: bool| {
+            prefixes
+                .iter()
+                .map(|prefix| {
+                    (
+                        murmur3(0x293a_e76f, prefix, signed),
+                        murmur3(0x7e64_6e2c, prefix, signed),
+                    )
+                })
+                .collect()
+        };
+
+        Some(Self {
+            v1: hash(true),
+            v2: hash(false),
+        })
+    }
+}
+
+/// When the files of a commit-graph were last changed, git replaces them
+/// instead of writing to them.
+type Stamp = [Option<(SystemTime, u64)>; 2];
+
+/// A commit-graph and when it was read, `None` if there was none.
+type Cached = (Stamp, Option<Arc<CommitGraph>>);
+
+/// Commit-graphs already read, so requests don't read them again until git
+/// writes a new one.
+#[derive(Default)]
+pub(crate) struct CommitGraphCache {
+    graphs: Mutex<HashMap<PathBuf, Cached>>,
+}
+
+impl CommitGraphCache {
+    /// The commit-graph in an objects directory, read again when it changed.
+    pub(crate) fn get(&self, objects: &Path) -> Option<Arc<CommitGraph>> {
+        let info = objects.join("info");
+        let stamp = [
+            info.join("commit-graph"),
+            info.join("commit-graphs/commit-graph-chain"),
+        ]
+        .map(|file| {
+            let metadata = fs::metadata(file).ok()?;
+
+            Some((metadata.modified().ok()?, metadata.len()))
+        });
+
+        if let Some((cached, graph)) = self
+            .graphs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(objects)
+            && *cached == stamp
+        {
+            return graph.clone();
+        }
+
+        let graph = CommitGraph::open(objects).map(Arc::new);
+
+        self.graphs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(objects.to_path_buf(), (stamp, graph.clone()));
+
+        graph
+    }
+}
+
+impl Repository {
+    /// The repository's commit-graph, from the cache when the repository was
+    /// opened with one.
+    pub(crate) fn commit_graph(&self) -> Option<&CommitGraph> {
+        self.commit_graph
+            .get_or_init(|| {
+                let objects = self.inner.path().join("objects");
+
+                self.commit_graphs.as_ref().map_or_else(
+                    || CommitGraph::open(&objects).map(Arc::new),
+                    |cache| cache.get(&objects),
+                )
+            })
+            .as_deref()
+    }
+
+    /// The parents of a commit, from the commit-graph if it has the commit.
+    pub(crate) fn parent_ids(&self, id: Oid) -> Option<Vec<Oid>> {
+        self.commit_graph()
+            .and_then(|graph| graph.parent_ids(id))
+            .or_else(|| {
+                let commit = self.backend().ok()?.commit(&id.to_string()).ok()??;
+
+                Some(commit.parents.into_iter().map(Oid::from).collect())
+            })
+    }
+
+    /// The root tree of a commit, from the commit-graph if it has the commit.
+    pub(crate) fn tree_id(&self, id: Oid) -> Option<Oid> {
+        self.commit_graph()
+            .and_then(|graph| graph.tree_id(id))
+            .or_else(|| {
+                let commit = self.backend().ok()?.commit(&id.to_string()).ok()??;
+
+                Some(commit.tree.into())
+            })
+    }
+
+    /// Hashes a path for [`Self::unchanged`], `None` without any Bloom filters.
+    pub(crate) fn bloom_key(&self, path: &Path) -> Option<BloomKey> {
+        self.commit_graph()
+            .filter(|graph| graph.has_bloom())
+            .and_then(|_| BloomKey::new(path))
+    }
+
+    /// Whether the commit-graph rules out that a commit changed a path compared
+    /// to its first parent.
+    pub(crate) fn unchanged(&self, id: Oid, key: Option<&BloomKey>) -> bool {
+        self.commit_graph()
+            .zip(key)
+            .is_some_and(|(graph, key)| graph.unchanged(id, key))
+    }
+
+    /// Writes a commit-graph with changed-path Bloom filters for everything
+    /// reachable from the refs, replacing any previous one.
+    ///
+    /// libgit2 can't write Bloom filters, so this runs `git` itself.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn write_commit_graph(&self) -> Result<()> {
+        let status = Command::new("git")
+            .arg("--git-dir")
+            .arg(self.path())
+            .args(["commit-graph", "write", "--reachable", "--changed-paths"])
+            .status()
+            .context("failed to run git")?;
+
+        status
+            .success()
+            .then_some(())
+            .with_context(|| format!("git commit-graph write failed with {status}"))
+    }
+}
+
+/// Whether a filter might contain the hashed path.
+fn contains(filter: &[u8], hashes: u32, first: u32, second: u32) -> bool {
+    let bits = filter.len() as u64 * 8;
+
+    (0..hashes).all(|i| {
+        let bit = u64::from(first.wrapping_add(i.wrapping_mul(second))) % bits;
+
+        filter[(bit / 8) as usize] & (1 << (bit % 8)) != 0
+    })
+}
+
+/// 32 bit murmur3, as git hashes paths for the Bloom filters.
+///
+/// Git used to read the bytes as signed chars, filters written that way are
+/// version 1 and only differ for paths with non ASCII bytes.
+fn murmur3(seed: u32, data: &[u8], signed: bool) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+
+    let byte = |b: u8| {
+        if signed {
+            i32::from(b.cast_signed()).cast_unsigned()
+        } else {
+            u32::from(b)
+        }
+    };
+
+    let mut hash = seed;
+    let mut blocks = data.chunks_exact(4);
+
+    for block in &mut blocks {
+        let mut k =
+            byte(block[0]) | byte(block[1]) << 8 | byte(block[2]) << 16 | byte(block[3]) << 24;
+
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+        hash ^= k;
+        hash = hash
+            .rotate_left(13)
+            .wrapping_mul(5)
+            .wrapping_add(0xe654_6b64);
+    }
+
+    let tail = blocks.remainder();
+
+    if !tail.is_empty() {
+        let mut k = 0;
+
+        for (i, b) in tail.iter().enumerate() {
+            k ^= byte(*b) << (8 * i);
+        }
+
+        hash ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+    }
+
+    // paths are nowhere near 4 GiB long
+    hash ^= u32::try_from(data.len()).unwrap_or(u32::MAX);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
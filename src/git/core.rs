@@ -1,9 +1,19 @@
-use std::path::Path;
+use std::{ffi::OsStr, path::Path};
 
 use git2::{Reference, Time};
 use syntect::parsing::SyntaxSet;
 
-use crate::{error::Context as _, error::Result, git::Repository, http::extractor::Ref};
+use crate::{
+    config::Markdown,
+    error::Context as _,
+    error::Result,
+    git::{
+        Repository,
+        backend::{Backend as _, Kind},
+    },
+    http::extractor::Ref,
+    utils::markup::Markup,
+};
 
 impl Repository {
     #[must_use]
@@ -47,7 +57,7 @@ impl Repository {
             // use the path for bare repositories
             .unwrap_or_else(|| self.inner.path())
             .file_name()
-            .and_then(std::ffi::OsStr::to_str)
+            .and_then(OsStr::to_str)
     }
 
     #[must_use]
@@ -72,72 +82,61 @@ impl Repository {
     }
 
     #[must_use]
-    pub(crate) fn readme(&self, syntaxes: &SyntaxSet) -> String {
-        use askama::filters::Escaper as _;
-
-        enum ReadmeFormat {
-            Plaintext,
-            Html,
-            Markdown,
-        }
-
-        let mut format = ReadmeFormat::Plaintext;
-
-        self.inner
-            .revparse_single("HEAD:readme")
-            .or_else(|_| self.inner.revparse_single("HEAD:README.txt"))
-            .or_else(|_| self.inner.revparse_single("HEAD:readme.txt"))
-            .or_else(|_| self.inner.revparse_single("HEAD:README.txt"))
-            .or_else(|_| {
-                format = ReadmeFormat::Markdown;
-                self.inner.revparse_single("HEAD:readme.md")
-            })
-            .or_else(|_| self.inner.revparse_single("HEAD:README.md"))
-            .or_else(|_| self.inner.revparse_single("HEAD:readme.mdown"))
-            .or_else(|_| self.inner.revparse_single("HEAD:README.mdown"))
-            .or_else(|_| self.inner.revparse_single("HEAD:readme.markdown"))
-            .or_else(|_| self.inner.revparse_single("HEAD:README.markdown"))
-            .or_else(|_| {
-                format = ReadmeFormat::Html;
-                self.inner.revparse_single("HEAD:readme.html")
+    pub(crate) fn readme(&self, syntaxes: &SyntaxSet, markdown: &Markdown) -> String {
+        let Ok(backend) = self.backend() else {
+            return String::new();
+        };
+
+        let Ok(Some(head)) = backend.commit("HEAD") else {
+            return String::new();
+        };
+
+        let Ok(entries) = backend.tree(head.tree) else {
+            return String::new();
+        };
+
+        // if there are multiple readmes pick the one whose format is registered first
+        let readme = entries
+            .into_iter()
+            .filter(|entry| entry.kind == Kind::Blob)
+            .filter_map(|entry| {
+                let path = Path::new(&entry.name);
+
+                let is_readme = path
+                    .file_stem()
+                    .and_then(OsStr::to_str)
+                    .is_some_and(|stem| stem.eq_ignore_ascii_case("readme"));
+
+                let markup = Markup::from_path(path).filter(|_| is_readme)?;
+
+                Some((markup, entry.name, entry.id))
             })
-            .or_else(|_| self.inner.revparse_single("HEAD:README.html"))
-            .or_else(|_| self.inner.revparse_single("HEAD:readme.htm"))
-            .or_else(|_| self.inner.revparse_single("HEAD:README.htm"))
-            .ok()
-            .and_then(|readme| readme.into_blob().ok())
-            .map(|blob| {
-                let text = str::from_utf8(blob.content()).unwrap_or_default();
+            .min_by(|(a, a_name, _), (b, b_name, _)| {
+                a.priority()
+                    .cmp(&b.priority())
+                    .then_with(|| a_name.cmp(b_name))
+            });
+
+        readme
+            .and_then(|(markup, _, id)| {
+                let content = backend.blob(id).ok()?;
+                let text = str::from_utf8(&content).unwrap_or_default();
 
                 // render the file contents to HTML
-                match format {
-                    // render plaintext as preformatted text
-                    ReadmeFormat::Plaintext => {
-                        let mut output = "<pre>".to_string();
-                        if let Err(err) = askama::filters::Html.write_escaped_str(&mut output, text)
-                        {
-                            tracing::error!(err=?err, "failed to write escaped plaintext readme");
-                        }
-                        output.push_str("</pre>");
-                        output
-                    }
-                    // already is HTML
-                    ReadmeFormat::Html => text.to_string(),
-                    // render Markdown to HTML
-                    ReadmeFormat::Markdown => crate::utils::markdown::render(syntaxes, text),
-                }
+                Some(markup.render(syntaxes, markdown, text))
             })
             .unwrap_or_default()
     }
 
     #[tracing::instrument(skip_all)]
     pub(crate) fn ref_or_head_shorthand(&self, r#ref: Option<&Ref>) -> Result<String> {
-        let head = self.head()?;
-
-        let spec = r#ref
-            .map(|r| r.0.clone())
-            .or_else(move || head.shorthand().map(str::to_string))
-            .context("failed to get repo ref spec")?;
+        let spec = match r#ref {
+            Some(r) => r.0.clone(),
+            None => self
+                .backend()?
+                .head_name()
+                .context("failed to get repo ref spec")?,
+        };
 
         Ok(spec)
     }
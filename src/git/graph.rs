@@ -0,0 +1,114 @@
+use git2::Oid;
+
+/// The lines of a commit graph, like `git log --graph` draws them.
+///
+/// Every lane is waiting for the commit it leads to, the walk has to visit
+/// children before their parents for the lanes to line up.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Lanes {
+    ids: Vec<Oid>,
+}
+
+/// The part of the graph next to a single commit.
+#[derive(Debug, Clone)]
+pub(crate) struct Row {
+    /// Lane of the commit itself
+    pub node: usize,
+    /// Number of lanes the row needs room for
+    pub width: usize,
+    pub lines: Vec<Line>,
+}
+
+/// A line between two lanes, from the top of a row down to its bottom.
+///
+/// Heights are `0` for the top, `1` for the commit and `2` for the bottom.
+#[derive(Debug, Clone)]
+pub(crate) struct Line {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    /// Lane the line belongs to, to color it
+    pub lane: usize,
+}
+
+impl Lanes {
+    /// Lanes waiting for the given commits, in this order.
+    pub(crate) const fn new(ids: Vec<Oid>) -> Self {
+        Self { ids }
+    }
+
+    /// The commits the lanes are waiting for.
+    pub(crate) fn ids(&self) -> &[Oid] {
+        &self.ids
+    }
+
+    /// Moves the lanes past a commit, returning how to draw it.
+    pub(crate) fn advance(&mut self, id: Oid, parents: &[Oid]) -> Row {
+        let before = std::mem::take(&mut self.ids);
+
+        let node = before
+            .iter()
+            .position(|lane| *lane == id)
+            .unwrap_or(before.len());
+
+        // the commit continues with its first parent, unless another lane already leads there
+        let first_parent = parents.first().filter(|parent| !before.contains(parent));
+        let mut at = None;
+
+        for (i, lane) in before.iter().enumerate() {
+            if i == node {
+                self.ids.extend(first_parent);
+                at = Some(self.ids.len());
+            } else if *lane != id {
+                self.ids.push(*lane);
+            }
+        }
+
+        // a commit no lane was waiting for starts a new one
+        if node == before.len() {
+            self.ids.extend(first_parent);
+        }
+
+        // merged branches get new lanes right next to the commit
+        let mut at = at.unwrap_or(self.ids.len());
+        for parent in parents {
+            if !self.ids.contains(parent) {
+                self.ids.insert(at, *parent);
+                at += 1;
+            }
+        }
+
+        let mut lines = Vec::new();
+
+        for (i, lane) in before.iter().enumerate() {
+            if *lane == id {
+                lines.push(Line {
+                    from: (i, 0),
+                    to: (node, 1),
+                    lane: i,
+                });
+            } else if let Some(j) = self.ids.iter().position(|other| other == lane) {
+                lines.push(Line {
+                    from: (i, 0),
+                    to: (j, 2),
+                    lane: j,
+                });
+            }
+        }
+
+        for parent in parents {
+            if let Some(j) = self.ids.iter().position(|other| other == parent) {
+                lines.push(Line {
+                    from: (node, 1),
+                    to: (j, 2),
+                    lane: j,
+                });
+            }
+        }
+
+        Row {
+            node,
+            width: before.len().max(node + 1).max(self.ids.len()),
+            lines,
+        }
+    }
+}
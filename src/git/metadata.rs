@@ -0,0 +1,218 @@
+//! What the index lists about every repository, kept between requests.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::SystemTime,
+};
+
+use git2::{Oid, Time};
+
+use crate::{
+    config::Config,
+    error::{Context as _, Result},
+    git::Repository,
+    http::extractor::RepoName,
+};
+
+/// Files in the git directory the metadata is read from, besides the refs.
+const WATCHED: [&str; 4] = ["HEAD", "config", "description", "packed-refs"];
+
+/// The metadata of a repository, as of the last time any of it changed.
+#[derive(Debug)]
+pub(crate) struct RepoInfo {
+    pub name: String,
+    /// Directory in the project root
+    pub dir: PathBuf,
+    /// First line of the `description` file
+    pub description: String,
+    /// `gitweb.owner` from the config
+    pub owner: Option<String>,
+    /// `bile.section` from the config
+    pub section: Option<String>,
+    /// Branch `HEAD` points to, `None` for detached or empty repositories
+    pub default_branch: Option<String>,
+    /// Commit `HEAD` points to, `None` for empty repositories
+    pub head: Option<Oid>,
+    /// Commit time of `HEAD`
+    pub last_modified: Option<Time>,
+    /// Every branch and tag with the commit it points at, sorted by name
+    pub tips: Vec<(String, Oid)>,
+}
+
+/// When the files the metadata is read from were last modified.
+///
+/// Git updates a loose ref by renaming a new file over it, which touches the
+/// directory it is in, so the directories under `refs/` are enough to notice
+/// every push.
+#[derive(Debug, PartialEq, Eq)]
+struct Stamp(Vec<(PathBuf, Option<SystemTime>)>);
+
+/// The metadata read at a stamp, `None` for directories that are not exported
+/// repositories.
+type Entry = (Stamp, Option<Arc<RepoInfo>>);
+
+/// The metadata of every repository, read again once its files change.
+#[derive(Default)]
+pub(crate) struct RepoCache {
+    /// By directory
+    entries: Mutex<HashMap<PathBuf, Entry>>,
+}
+
+impl RepoCache {
+    /// Every exported repository sorted by name, only reading the ones that changed.
+    ///
+    /// The files are read without holding the lock, so a slow repository only holds up
+    /// the requests that need it.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn all(&self, config: &Config) -> Result<Vec<Arc<RepoInfo>>> {
+        let stamps: Vec<(PathBuf, Stamp)> = Repository::dirs(config)?
+            .into_iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .map(|dir| {
+                let stamp = Stamp::read(config, &dir);
+                (dir, stamp)
+            })
+            .collect();
+
+        let mut current = HashMap::with_capacity(stamps.len());
+        let mut changed = Vec::new();
+
+        {
+            let entries = self.entries();
+
+            for (dir, stamp) in stamps {
+                match entries.get(&dir) {
+                    Some((cached, info)) if *cached == stamp => {
+                        current.insert(dir, (stamp, info.clone()));
+                    }
+                    _ => changed.push((dir, stamp)),
+                }
+            }
+        }
+
+        for (dir, stamp) in changed {
+            let info = RepoInfo::read(config, &dir)?;
+            current.insert(dir, (stamp, info));
+        }
+
+        let mut repos: Vec<Arc<RepoInfo>> = current
+            .values()
+            .filter_map(|(_, info)| info.clone())
+            .collect();
+
+        // removed repositories are dropped along the way
+        *self.entries() = current;
+
+        repos.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(repos)
+    }
+
+    /// A single repository, `None` if there is no exported one with that name.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn get(&self, config: &Config, name: &RepoName) -> Result<Option<Arc<RepoInfo>>> {
+        // the same directory as `all` finds, however the name is spelled
+        let Ok(dir) = config.project_root.join(&name.0).canonicalize() else {
+            return Ok(None);
+        };
+
+        let stamp = Stamp::read(config, &dir);
+
+        if let Some((cached, info)) = self.entries().get(&dir)
+            && *cached == stamp
+        {
+            return Ok(info.clone());
+        }
+
+        let info = RepoInfo::read(config, &dir)?;
+
+        // only names that exist are kept, anything else could fill the cache
+        if info.is_some() {
+            self.entries().insert(dir, (stamp, info.clone()));
+        }
+
+        Ok(info)
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<PathBuf, Entry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl RepoInfo {
+    #[tracing::instrument(skip_all)]
+    fn read(config: &Config, dir: &Path) -> Result<Option<Arc<Self>>> {
+        let Some(repo) = Repository::open_path(config, dir).context("failed to open repository")?
+        else {
+            return Ok(None);
+        };
+
+        let head = repo.head().ok();
+
+        Ok(Some(Arc::new(Self {
+            name: repo.name().unwrap_or_default().to_string(),
+            dir: dir.to_path_buf(),
+            description: repo.description(),
+            owner: repo.owner(),
+            section: repo.section(),
+            default_branch: head
+                .as_ref()
+                .filter(|head| head.is_branch())
+                .and_then(|head| head.shorthand())
+                .map(str::to_string),
+            head: head
+                .and_then(|head| head.peel_to_commit().ok())
+                .map(|commit| commit.id()),
+            last_modified: repo.last_modified().ok(),
+            tips: repo.ref_tips(),
+        })))
+    }
+}
+
+impl Stamp {
+    fn read(config: &Config, dir: &Path) -> Self {
+        let git_dir = dir.join(".git");
+        let git_dir = if git_dir.is_dir() {
+            git_dir
+        } else {
+            dir.to_path_buf()
+        };
+
+        let modified = |path: PathBuf| {
+            let time = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+
+            (path, time)
+        };
+
+        let mut times: Vec<_> = WATCHED
+            .iter()
+            .chain([&config.export_ok.as_str()])
+            .map(|file| modified(git_dir.join(file)))
+            .collect();
+
+        let mut dirs = vec![git_dir.join("refs")];
+
+        while let Some(refs) = dirs.pop() {
+            let Ok(read) = fs::read_dir(&refs) else {
+                continue;
+            };
+
+            dirs.extend(
+                read.filter_map(std::result::Result::ok)
+                    .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+                    .map(|entry| entry.path()),
+            );
+
+            times.push(modified(refs));
+        }
+
+        times.sort();
+
+        Self(times)
+    }
+}
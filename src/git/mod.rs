@@ -1,70 +1,239 @@
+pub(crate) mod activity;
+mod archive;
+pub mod backend;
 mod branch;
+pub(crate) mod cache;
 mod commit;
+pub mod commit_graph;
 mod core;
+pub(crate) mod graph;
+pub(crate) mod metadata;
 mod tag;
 mod tree;
 
-use std::path::{Path, PathBuf};
+use std::{
+    cell::OnceCell,
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use git2::{Object, Signature};
+use base64::{Engine as _, prelude::BASE64_URL_SAFE_NO_PAD};
+use git2::{Commit, Oid};
 
-use crate::{config::Config, error::Context as _, error::Result, http::extractor::RepoName};
+use crate::{
+    config::Config,
+    error::Context as _,
+    error::Result,
+    git::backend::{Backend as _, Libgit2, Sorting},
+    http::{BileState, extractor::RepoName},
+};
 
 pub(crate) struct TagEntry {
     pub link: String,
     pub tag: String,
+    /// The commit that is tagged
+    pub target: Oid,
     pub message: String,
-    pub signature: Signature<'static>,
+    /// Kept as the backend has it, `git2` can't hold every name and email git allows
+    pub signature: backend::Signature,
 }
 
-impl TagEntry {
-    #[tracing::instrument(skip_all)]
-    fn try_from_commit(name: String, obj: &Object<'_>) -> Result<Self> {
-        Ok(Self {
-            link: format!("refs/{name}"),
-            tag: name,
-            message: String::new(),
-            signature: obj
-                .as_commit()
-                .context("git object is not a commit")?
-                .committer()
-                .to_owned(),
-        })
+/// Where a walk of the history stopped, so the next page can continue from there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Cursor {
+    /// The commits the walk would have visited next, pushing them onto a new
+    /// walk in the same [`Order`] carries on without starting from the tip.
+    Pending(Vec<Oid>),
+    /// The commit the next page starts with, found again by walking from the
+    /// tips, for walks waiting on more commits than fit in a URL.
+    Rewalk(Oid),
+}
+
+impl Cursor {
+    /// Pending commits kept in a cursor, e.g. walking every branch at once
+    /// waits on each of them.
+    const MAX_PENDING: usize = 16;
+
+    /// A cursor starting a walk at a single commit.
+    pub(crate) fn at(id: Oid) -> Self {
+        Self::Pending(vec![id])
     }
 
-    #[tracing::instrument(skip_all)]
-    fn try_from_tag(name: String, obj: &Object<'_>) -> Result<Self> {
-        let tag = obj.as_tag().context("git object is not a tag")?;
-
-        Ok(Self {
-            link: format!("refs/{name}"),
-            tag: name,
-            message: tag.message().unwrap_or("").to_string(),
-            signature: tag
-                .tagger()
-                .context("git tag does not have a tagger")
-                .or_else(|_| -> Result<Signature<'_>> {
-                    let signature = obj
-                        .peel_to_commit()
-                        .context("failed to peel object to commit")?
-                        .committer()
-                        .to_owned();
-
-                    Ok(signature)
-                })?
-                .to_owned(),
-        })
+    /// Continues a walk waiting on `pending`, with `next` as the first commit.
+    fn new(pending: Vec<Oid>, next: Oid) -> Self {
+        if pending.len() > Self::MAX_PENDING {
+            Self::Rewalk(next)
+        } else {
+            Self::Pending(pending)
+        }
+    }
+
+    /// Parses a cursor, plain commit ids are accepted too.
+    pub(crate) fn parse(cursor: &str) -> Option<Self> {
+        let id = |hex: &str| Oid::from_str(hex).ok().filter(|_| hex.len() == 40);
+
+        if let Some(hex) = cursor.strip_prefix('~') {
+            return id(hex).map(Self::Rewalk);
+        }
+
+        if let Some(id) = id(cursor) {
+            return Some(Self::at(id));
+        }
+
+        let bytes = BASE64_URL_SAFE_NO_PAD.decode(cursor).ok()?;
+
+        if bytes.is_empty() || bytes.len() % 20 != 0 || bytes.len() > Self::MAX_PENDING * 20 {
+            return None;
+        }
+
+        let pending = bytes
+            .chunks_exact(20)
+            .map(Oid::from_bytes)
+            .collect::<Result<_, _>>()
+            .ok()?;
+
+        Some(Self::Pending(pending))
+    }
+
+    /// The commit the walk continues with, for a single commit cursor.
+    pub(crate) fn first(&self) -> Option<Oid> {
+        match self {
+            Self::Pending(pending) => pending.first().copied(),
+            Self::Rewalk(id) => Some(*id),
+        }
+    }
+
+    /// Whether every commit of the cursor is in the repository, walks can only
+    /// continue from those.
+    pub(crate) fn exists_in(&self, repo: &Repository) -> bool {
+        let ids = match self {
+            Self::Pending(pending) => pending.as_slice(),
+            Self::Rewalk(id) => std::slice::from_ref(id),
+        };
+
+        ids.iter().all(|id| repo.inner.find_commit(*id).is_ok())
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pending(pending) => {
+                let bytes: Vec<u8> = pending
+                    .iter()
+                    .flat_map(|id| id.as_bytes().iter().copied())
+                    .collect();
+
+                f.write_str(&BASE64_URL_SAFE_NO_PAD.encode(bytes))
+            }
+            Self::Rewalk(id) => write!(f, "~{id}"),
+        }
+    }
+}
+
+/// Where a walk of the history starts.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Tips<'a> {
+    /// A branch, tag or commit
+    Spec(&'a str),
+    /// Every branch and tag
+    All,
+}
+
+/// The order the history is walked in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Order {
+    /// Newest commits first
+    #[default]
+    Time,
+    /// Commits of a branch together, like `git log --topo-order`
+    Topo,
+    /// Newest commits first but never before their children, like
+    /// `git log --date-order`, the graph needs it when a clock was ahead
+    #[serde(skip)]
+    Date,
+}
+
+impl Order {
+    /// The order to switch to.
+    pub(crate) const fn other(self) -> Self {
+        match self {
+            Self::Time | Self::Date => Self::Topo,
+            Self::Topo => Self::Time,
+        }
+    }
+
+    /// The order to walk in to draw the graph, which can't have parents
+    /// before their children.
+    pub(crate) const fn drawn(self) -> Self {
+        match self {
+            Self::Time | Self::Date => Self::Date,
+            Self::Topo => Self::Topo,
+        }
+    }
+
+    const fn sorting(self) -> Sorting {
+        match self {
+            Self::Time => Sorting::Time,
+            Self::Topo => Sorting::Topo,
+            Self::Date => Sorting::Date,
+        }
     }
 }
 
+/// A page of the history of a ref.
+pub(crate) struct Page<'r> {
+    /// The commits the walk started at
+    pub tips: Vec<Oid>,
+    pub commits: Vec<Commit<'r>>,
+    /// How to draw each commit in a graph, empty when filtering for a path
+    pub graph: Vec<graph::Row>,
+    /// Continues after the last commit, `None` on the last page
+    pub next: Option<Cursor>,
+    /// There are newer commits than the ones on this page
+    pub has_prev: bool,
+}
+
+/// A tag and the tag before it, to show what changed in between.
+pub(crate) struct Release {
+    pub tag: TagEntry,
+    pub previous: Option<String>,
+}
+
+/// A branch or tag pointing at a commit, like `git log --decorate` shows them.
+#[derive(Debug, Clone)]
+pub(crate) struct Decoration {
+    pub name: String,
+    pub is_tag: bool,
+}
+
 pub(crate) struct Repository {
-    inner: git2::Repository,
+    /// Also used for everything that is not part of the backend
+    inner: Libgit2,
+    /// Opened on the first lookup, many pages never need it
+    #[cfg(feature = "gix")]
+    gix: OnceCell<backend::Gitoxide>,
+    /// Branches and tags by the commit they point at, looked up once per request
+    decorations: OnceCell<HashMap<Oid, Vec<Decoration>>>,
+    /// Read on the first history query that can use it
+    commit_graph: OnceCell<Option<Arc<commit_graph::CommitGraph>>>,
+    commit_graphs: Option<Arc<commit_graph::CommitGraphCache>>,
 }
 
 impl Repository {
+    /// Opens a repository for a request, sharing the commit-graphs read by
+    /// earlier ones.
     #[tracing::instrument(skip_all)]
-    pub(crate) fn open(config: &Config, name: &RepoName) -> Result<Option<Self>> {
-        Self::open_path(config, &PathBuf::from(&name.0))
+    pub(crate) fn open(state: &BileState, name: &RepoName) -> Result<Option<Self>> {
+        let repo = Self::open_path(&state.config, &PathBuf::from(&name.0))?;
+
+        Ok(repo.map(|repo| Self {
+            commit_graphs: Some(Arc::clone(&state.commit_graphs)),
+            ..repo
+        }))
     }
 
     pub(crate) fn open_path(config: &Config, path: &Path) -> Result<Option<Self>> {
@@ -94,7 +263,7 @@ impl Repository {
             return Ok(None);
         }
 
-        let inner = git2::Repository::open(path).context("failed to actually open the repo")?;
+        let inner = Libgit2::open(&path).context("failed to actually open the repo")?;
 
         if !inner.path().join(&config.export_ok).exists() {
             tracing::warn!("tried to access private repo");
@@ -102,11 +271,95 @@ impl Repository {
             return Ok(None);
         }
 
-        Ok(Some(Self { inner }))
+        Ok(Some(Self {
+            #[cfg(feature = "gix")]
+            gix: OnceCell::new(),
+            inner,
+            decorations: OnceCell::new(),
+            commit_graph: OnceCell::new(),
+            commit_graphs: None,
+        }))
+    }
+
+    /// Opens every exported repository in the project root.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn all(config: &Config) -> Result<Vec<Self>> {
+        let mut repos = Vec::new();
+
+        for dir in Self::dirs(config)? {
+            let Some(repo) = Self::open_path(config, &dir).context("failed to open repository")?
+            else {
+                continue;
+            };
+
+            // check for the export file in the git directory
+            // (the .git subfolder for non-bare repos)
+            if !repo.path().join(&config.export_ok).exists() {
+                continue;
+            }
+
+            repos.push(repo);
+        }
+
+        repos.sort_by(|a, b| a.name().cmp(&b.name()));
+
+        Ok(repos)
+    }
+
+    /// The directories in the project root that can be repositories.
+    pub(crate) fn dirs(config: &Config) -> Result<Vec<PathBuf>> {
+        let Ok(read) = fs::read_dir(&config.project_root) else {
+            return Ok(Vec::new());
+        };
+
+        let mut dirs = Vec::new();
+
+        for entry in read {
+            let entry = entry.context("failed to open directory entry")?;
+            let metadata = entry.metadata().context("failed to get file metadata")?;
+
+            if !metadata.is_dir() {
+                continue;
+            }
+
+            if entry
+                .file_name()
+                .to_str()
+                .is_some_and(|p| p != "." && p.starts_with('.'))
+            {
+                continue;
+            }
+
+            dirs.push(entry.path());
+        }
+
+        Ok(dirs)
     }
 
     #[must_use]
-    pub(crate) const fn as_inner(&self) -> &git2::Repository {
+    pub(crate) fn as_inner(&self) -> &git2::Repository {
         &self.inner
     }
+
+    /// The backend commits, trees and tags are looked up with.
+    #[cfg(feature = "gix")]
+    pub(crate) fn backend(&self) -> Result<&backend::Selected> {
+        if let Some(gix) = self.gix.get() {
+            return Ok(gix);
+        }
+
+        let gix = backend::Gitoxide::open(self.inner.path())?;
+
+        Ok(self.gix.get_or_init(|| gix))
+    }
+
+    /// The backend commits, trees and tags are looked up with.
+    #[cfg(not(feature = "gix"))]
+    #[expect(
+        clippy::unnecessary_wraps,
+        reason = "opening gitoxide can fail when it is selected"
+    )]
+    pub(crate) const fn backend(&self) -> Result<&backend::Selected> {
+        Ok(&self.inner)
+    }
 }
@@ -1,8 +1,6 @@
-use git2::ObjectType;
-
 use crate::{
     error::Result,
-    git::{Repository, TagEntry},
+    git::{Release, Repository, TagEntry, backend::Backend as _},
     http::extractor::Tag,
 };
 
@@ -11,35 +9,40 @@ impl Repository {
     pub(crate) fn tag_entries(&self) -> Result<Vec<TagEntry>> {
         let mut tags = Vec::new();
 
-        self.inner.tag_foreach(|oid, name_bytes| {
-            // remove prefix "ref/tags/"
-            let Ok(name) = String::from_utf8(name_bytes[10..].to_vec()) else {
-                return true;
-            };
-
-            let Ok(obj) = self.inner.find_object(oid, None) else {
-                return true;
-            };
-
-            let tag = match obj.kind() {
-                Some(ObjectType::Tag) => TagEntry::try_from_tag(name, &obj),
-                // lightweight tag
-                Some(ObjectType::Commit) => TagEntry::try_from_commit(name, &obj),
-                _ => unreachable!("a tag was not a tag or lightweight tag"),
-            };
-
-            let Ok(tag) = tag else {
-                return true;
-            };
-
-            tags.push(tag);
-
-            true
-        })?;
+        for tag in self.backend()?.tags()? {
+            tags.push(TagEntry {
+                link: format!("refs/{}", tag.name),
+                tag: tag.name,
+                target: tag.target.into(),
+                message: tag.message,
+                signature: tag.tagger,
+            });
+        }
 
         Ok(tags)
     }
 
+    /// Tags newest first, each with the tag made before it.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn releases(&self) -> Result<Vec<Release>> {
+        let mut tags = self.tag_entries()?;
+
+        tags.sort_unstable_by(|a, b| a.signature.when().cmp(&b.signature.when()).reverse());
+
+        let previous: Vec<_> = tags
+            .iter()
+            .skip(1)
+            .map(|tag| Some(tag.tag.clone()))
+            .chain([None])
+            .collect();
+
+        Ok(tags
+            .into_iter()
+            .zip(previous)
+            .map(|(tag, previous)| Release { tag, previous })
+            .collect())
+    }
+
     #[tracing::instrument(skip_all)]
     pub(crate) fn tag(&self, spec: &Tag) -> Result<git2::Tag<'_>> {
         let tag = self.inner.revparse_single(&spec.0)?.peel_to_tag()?;
@@ -1,9 +1,19 @@
-use std::path::Path;
+use std::{collections::HashMap, path::Path};
 
-use anyhow::Context as _;
-use git2::{Blob, Object, Tree};
+use git2::{Blob, Commit, Object, Oid, Tree};
 
-use crate::{error::Result, git::Repository};
+use crate::{
+    error::{Context as _, Result},
+    git::{
+        Repository,
+        backend::{Backend as _, Sorting},
+        cache::LastCommitCache,
+        commit_graph::BloomKey,
+    },
+};
+
+/// Ancestors checked for cached last commits before walking the history.
+const PARENT_DEPTH: usize = 32;
 
 impl Repository {
     #[tracing::instrument(skip_all)]
@@ -17,21 +27,274 @@ impl Repository {
         Ok(Some(blob))
     }
 
+    /// Reads a blob from the repository in `dir`, for bodies that are written
+    /// after the repository the blob was found in is gone.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn read_blob<R>(dir: &Path, id: Oid, read: impl FnOnce(&[u8]) -> R) -> Result<R> {
+        let repo = git2::Repository::open(dir).context("failed to open repository")?;
+        let blob = repo.find_blob(id)?;
+
+        Ok(read(blob.content()))
+    }
+
     #[tracing::instrument(skip_all)]
     pub(crate) fn tree_object(&self, tree: &Tree<'_>, path: &Path) -> Result<Option<Object<'_>>> {
-        let entry = match tree
-            .get_path(path)
-            .context("failed to get object from tree")
-        {
-            Ok(entry) => entry,
+        let entry = match self.backend()?.entry(tree.id().into(), path) {
+            Ok(Some(entry)) => entry,
+            Ok(None) => return Ok(None),
             Err(err) => {
                 tracing::error!(err=?err, "failed to get object from tree");
                 return Ok(None);
             }
         };
 
-        let obj = entry.to_object(&self.inner)?;
+        let obj = self.inner.find_object(entry.id.into(), None)?;
 
         Ok(Some(obj))
     }
+
+    /// The last commit changing `path`, following the history while it stays the same.
+    ///
+    /// Every commit passed on the way is cached, so looking up the path from a newer
+    /// commit stops at the first commit seen before.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn file_last_commit(
+        &self,
+        cache: &LastCommitCache,
+        spec: &str,
+        path: &Path,
+    ) -> Result<Option<Commit<'_>>> {
+        let Some(start) = self.commit(spec)? else {
+            return Ok(None);
+        };
+
+        let Some(id) = self.path_id(start.id(), path) else {
+            return Ok(None);
+        };
+
+        let key = self.bloom_key(path);
+        let mut current = start.id();
+        let mut passed = Vec::new();
+
+        let last = loop {
+            if let Some(last) = cache.get(self.path(), current, path) {
+                break last;
+            }
+
+            passed.push(current);
+
+            let parents = self.parent_ids(current).unwrap_or_default();
+
+            // the first parent has the same path if the commit-graph rules out a change
+            if let Some(first) = parents.first()
+                && self.unchanged(current, key.as_ref())
+            {
+                current = *first;
+                continue;
+            }
+
+            // a merge only changed the path if it differs from every parent
+            let same = parents
+                .into_iter()
+                .find(|parent| self.path_id(*parent, path) == Some(id));
+
+            match same {
+                Some(parent) => current = parent,
+                None => break current,
+            }
+        };
+
+        cache.insert(self.path(), &passed, path, last);
+
+        Ok(Some(self.inner.find_commit(last)?))
+    }
+
+    /// The last commit changing each entry of the directory at `path`, by name.
+    ///
+    /// That is the newest commit having the entry as it is now while none of its
+    /// parents do, so a single walk of the history finds it for every entry.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn last_commits(
+        &self,
+        cache: &LastCommitCache,
+        commit: &Commit<'_>,
+        path: &Path,
+    ) -> Result<HashMap<String, Commit<'_>>> {
+        let mut last_commits = HashMap::new();
+
+        let Some(dir) = self.dir_at(commit.id(), path) else {
+            return Ok(last_commits);
+        };
+
+        let mut pending: HashMap<String, Oid> = HashMap::new();
+
+        for (name, id) in dir.entries {
+            match cache.get(self.path(), commit.id(), &path.join(&name)) {
+                Some(last) => {
+                    last_commits.insert(name, self.inner.find_commit(last)?);
+                }
+                None => {
+                    pending.insert(name, id);
+                }
+            }
+        }
+
+        for (name, last) in self.last_commits_from_parents(cache, commit.id(), path, &mut pending) {
+            cache.insert(self.path(), &[commit.id()], &path.join(&name), last);
+            last_commits.insert(name, self.inner.find_commit(last)?);
+        }
+
+        let keys: HashMap<String, BloomKey> = pending
+            .keys()
+            .filter_map(|name| Some((name.clone(), self.bloom_key(&path.join(name))?)))
+            .collect();
+
+        let walk = self
+            .backend()?
+            .walk(&[commit.id().into()], &[], Sorting::Time)?;
+
+        for id in walk {
+            if pending.is_empty() {
+                break;
+            }
+
+            let walked = Oid::from(id?);
+
+            // the commit-graph rules out most commits without reading any trees
+            if pending
+                .keys()
+                .all(|name| self.unchanged(walked, keys.get(name)))
+            {
+                continue;
+            }
+
+            let Some(walked_dir) = self.dir_at(walked, path) else {
+                continue;
+            };
+
+            // parents without the directory do not have any of its entries either
+            let parents: Vec<Dir> = self
+                .parent_ids(walked)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|parent| self.dir_at(parent, path))
+                .collect();
+
+            // nothing in the directory changed compared to this parent
+            if parents.iter().any(|parent| parent.id == walked_dir.id) {
+                continue;
+            }
+
+            let changed: Vec<String> = pending
+                .iter()
+                .filter(|&(name, entry_id)| {
+                    walked_dir.get(name) == Some(*entry_id)
+                        && parents
+                            .iter()
+                            .all(|parent| parent.get(name) != Some(*entry_id))
+                })
+                .map(|(name, _)| name.clone())
+                .collect();
+
+            if changed.is_empty() {
+                continue;
+            }
+
+            let walked = self.inner.find_commit(walked)?;
+
+            for name in changed {
+                pending.remove(&name);
+                cache.insert(self.path(), &[commit.id()], &path.join(&name), walked.id());
+                last_commits.insert(name, walked.clone());
+            }
+        }
+
+        Ok(last_commits)
+    }
+
+    /// Resolves pending entries from the cached results of recent ancestors.
+    ///
+    /// Only goes down commits with a single parent, as long as no ancestor can be
+    /// newer than the ones passed. An entry the parent has differently was last
+    /// changed by the commit, an unchanged one has the parent's last commit.
+    fn last_commits_from_parents(
+        &self,
+        cache: &LastCommitCache,
+        commit: Oid,
+        path: &Path,
+        pending: &mut HashMap<String, Oid>,
+    ) -> Vec<(String, Oid)> {
+        let mut resolved = Vec::new();
+        let mut current = commit;
+
+        for _ in 0..PARENT_DEPTH {
+            if pending.is_empty() {
+                break;
+            }
+
+            let Some(&[parent]) = self.parent_ids(current).as_deref() else {
+                break;
+            };
+
+            let parent_dir = self.dir_at(parent, path);
+
+            pending.retain(|name, entry_id| {
+                let parent_id = parent_dir.as_ref().and_then(|dir| dir.get(name));
+
+                let last = if parent_id == Some(*entry_id) {
+                    cache.get(self.path(), parent, &path.join(name.as_str()))
+                } else {
+                    Some(current)
+                };
+
+                last.map(|last| resolved.push((name.clone(), last)))
+                    .is_none()
+            });
+
+            current = parent;
+        }
+
+        resolved
+    }
+
+    /// The id of the tree or blob at `path` in a commit.
+    fn path_id(&self, commit: Oid, path: &Path) -> Option<Oid> {
+        let tree_id = self.tree_id(commit)?;
+
+        if path.as_os_str().is_empty() {
+            return Some(tree_id);
+        }
+
+        self.backend()
+            .ok()?
+            .entry(tree_id.into(), path)
+            .ok()?
+            .map(|entry| entry.id.into())
+    }
+
+    /// The directory at `path` in a commit, `None` if it has no such directory.
+    fn dir_at(&self, commit: Oid, path: &Path) -> Option<Dir> {
+        let id = self.path_id(commit, path)?;
+        let entries = self.backend().ok()?.tree(id.into()).ok()?;
+
+        Some(Dir {
+            id,
+            entries: entries
+                .into_iter()
+                .map(|entry| (entry.name, entry.id.into()))
+                .collect(),
+        })
+    }
+}
+
+/// The entries of a directory, by name.
+struct Dir {
+    id: Oid,
+    entries: HashMap<String, Oid>,
+}
+
+impl Dir {
+    fn get(&self, name: &str) -> Option<Oid> {
+        self.entries.get(name).copied()
+    }
 }
@@ -0,0 +1,129 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse as _, Response},
+};
+use git2::Time;
+use jiff::Timestamp;
+
+use crate::{
+    BileState,
+    config::Config,
+    error::Result,
+    git::activity::{Event, EventKind},
+    handlers::repo_log_feed::Feed,
+    http::{
+        pool::Cost,
+        response::{Atom, ErrorPage, Html, Xml},
+    },
+    utils::filters,
+};
+
+#[derive(askama::Template)]
+#[template(path = "activity.html")]
+struct ActivityTemplate<'a> {
+    config: &'a Config,
+    events: &'a [Event],
+    newer_page: Option<usize>,
+    older_page: Option<usize>,
+}
+
+#[derive(askama::Template)]
+#[template(path = "activity.xml")]
+struct ActivityFeedTemplate<'a> {
+    config: &'a Config,
+    events: &'a [Event],
+    updated: Time,
+}
+
+#[derive(askama::Template)]
+#[template(path = "activity.atom", escape = "html")]
+struct ActivityAtomTemplate<'a> {
+    config: &'a Config,
+    events: &'a [Event],
+    updated: Time,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct Params {
+    /// Starts at 1
+    page: Option<usize>,
+}
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(state: State<BileState>, Query(params): Query<Params>) -> Response {
+    state
+        .spawn(Cost::Heavy, move |state| {
+            inner(&state, params.page.unwrap_or(1))
+        })
+        .await
+}
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get_feed(state: State<BileState>) -> Response {
+    state
+        .spawn(Cost::Heavy, move |state| feed(&state, Feed::Rss))
+        .await
+}
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get_atom(state: State<BileState>) -> Response {
+    state
+        .spawn(Cost::Heavy, move |state| feed(&state, Feed::Atom))
+        .await
+}
+
+fn inner(state: &BileState, page: usize) -> Result<Response> {
+    let events = state.activity.events(&state.config, &state.repos)?;
+
+    let per_page = state.config.log_per_page;
+    let start = page.saturating_sub(1).saturating_mul(per_page);
+
+    let Some(shown) = events.get(start..) else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    if page == 0 || (shown.is_empty() && page > 1) {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    }
+
+    let (shown, rest) = shown.split_at(per_page.min(shown.len()));
+
+    Ok(Html(ActivityTemplate {
+        config: &state.config,
+        events: shown,
+        newer_page: (page > 1).then(|| page - 1),
+        older_page: (!rest.is_empty()).then_some(page + 1),
+    })
+    .into_response())
+}
+
+fn feed(state: &BileState, feed: Feed) -> Result<Response> {
+    let events = state.activity.events(&state.config, &state.repos)?;
+    let events = &events[..state.config.log_per_page.min(events.len())];
+
+    // feeds need a date even when there is nothing in them
+    let updated = events.first().map_or_else(
+        || Time::new(Timestamp::now().as_second(), 0),
+        |event| event.time,
+    );
+
+    Ok(match feed {
+        Feed::Rss => Xml(ActivityFeedTemplate {
+            config: &state.config,
+            events,
+            updated,
+        })
+        .into_response(),
+        Feed::Atom => Atom(ActivityAtomTemplate {
+            config: &state.config,
+            events,
+            updated,
+        })
+        .into_response(),
+    })
+}
@@ -0,0 +1,95 @@
+use std::path;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse as _, Response},
+};
+
+use crate::{
+    BileState,
+    error::{Context as _, Result},
+    git::Repository,
+    handlers::api::schema::{Blob, Error},
+    http::{
+        conditional::Revision,
+        extractor::{ObjectName, Ref, RepoName},
+        path::Path,
+        pool::Cost,
+        response::{ApiJson, ErrorPage},
+    },
+};
+
+/// Get the metadata of a file
+#[utoipa::path(
+    get,
+    path = "/repos/{name}/blob/{ref}/{path}",
+    operation_id = "get_blob",
+    params(
+        ("name" = String, Path, description = "Name of the repository"),
+        ("ref" = String, Path, description = "Branch, tag or commit"),
+        (
+            "path" = String,
+            Path,
+            description = "Path inside the repository, its `/` are not encoded",
+            allow_reserved,
+        ),
+    ),
+    responses(
+        (status = OK, body = Blob),
+        (
+            status = NOT_FOUND,
+            description = "The repository or object does not exist",
+            body = Error,
+        ),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(
+    state: State<BileState>,
+    Path((repo_name, r#ref, object_name)): Path<(RepoName, Ref, ObjectName)>,
+) -> Response {
+    state
+        .spawn(Cost::Light, move |state| {
+            inner(&state, &repo_name, &r#ref, &object_name)
+        })
+        .await
+}
+
+fn inner(
+    state: &BileState,
+    repo_name: &RepoName,
+    r#ref: &Ref,
+    object_name: &ObjectName,
+) -> Result<Response> {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    let Some((commit, tree)) = repo
+        .commit_tree(&r#ref.0)
+        .context("failed to get commit tree")?
+    else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    let path = path::Path::new(object_name.0.trim_matches('/'));
+
+    let Some(blob) = repo
+        .tree_object(&tree, path)?
+        .and_then(|obj| obj.into_blob().ok())
+    else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    let blob = Blob::new(&state.config, repo_name, &r#ref.0, &commit, path, &blob);
+    let revision = Revision::of_commit(&commit).named_by([r#ref.0.as_str()]);
+
+    Ok((revision, ApiJson(blob)).into_response())
+}
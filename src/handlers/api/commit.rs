@@ -0,0 +1,69 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse as _, Response},
+};
+
+use crate::{
+    BileState,
+    error::{Context as _, Result},
+    git::Repository,
+    handlers::api::schema::{CommitDetail, Error},
+    http::{
+        conditional::Revision,
+        extractor::{Commit, RepoName},
+        path::Path,
+        pool::Cost,
+        response::{ApiJson, ErrorPage},
+    },
+    utils::diff::Changes,
+};
+
+/// Get a commit and the files it changed
+#[utoipa::path(
+    get,
+    path = "/repos/{name}/commits/{id}",
+    operation_id = "get_commit",
+    params(
+        ("name" = String, Path, description = "Name of the repository"),
+        ("id" = String, Path, description = "Full or abbreviated commit id"),
+    ),
+    responses(
+        (status = OK, body = CommitDetail),
+        (
+            status = NOT_FOUND,
+            description = "The repository or object does not exist",
+            body = Error,
+        ),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(
+    state: State<BileState>,
+    Path((repo_name, commit)): Path<(RepoName, Commit)>,
+) -> Response {
+    state
+        .spawn(Cost::Heavy, move |state| inner(&state, &repo_name, &commit))
+        .await
+}
+
+fn inner(state: &BileState, repo_name: &RepoName, name: &Commit) -> Result<Response> {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    let Some(commit) = repo.commit(&name.0).context("failed to get commit")? else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    let diff = Changes::Commit(commit.id()).diff(&repo)?;
+
+    let detail = CommitDetail::new(&commit, &diff).context("failed to get diff stats")?;
+    let revision = Revision::of_commit(&commit).named_by([name.0.as_str()]);
+
+    Ok((revision, ApiJson(detail)).into_response())
+}
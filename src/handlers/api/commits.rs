@@ -0,0 +1,118 @@
+#![allow(unused_qualifications, reason = "generated by utoipa::path")]
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse as _, Response},
+};
+
+use utoipa::IntoParams;
+
+use crate::{
+    BileState,
+    error::{Context as _, Result},
+    git::{Cursor, Order, Repository, Tips},
+    handlers::api::schema::{Commit, CommitList, Error},
+    http::{
+        conditional::Revision,
+        extractor::{ObjectName, Ref, RepoName},
+        path::Path,
+        pool::Cost,
+        response::{ApiJson, ErrorPage},
+    },
+};
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct Params {
+    /// Branch, tag or commit to start from, defaults to `HEAD`
+    #[param(value_type = Option<String>)]
+    pub(crate) r#ref: Option<Ref>,
+    /// Only list commits changing this path
+    #[param(value_type = Option<String>)]
+    pub(crate) path: Option<ObjectName>,
+    /// `next_cursor` of the previous page
+    pub(crate) cursor: Option<String>,
+}
+
+/// List the history of a ref, newest first
+#[utoipa::path(
+    get,
+    path = "/repos/{name}/commits",
+    operation_id = "list_commits",
+    params(("name" = String, Path, description = "Name of the repository"), Params),
+    responses(
+        (status = OK, body = CommitList),
+        (
+            status = BAD_REQUEST,
+            description = "The cursor is not valid",
+            body = Error,
+        ),
+        (
+            status = NOT_FOUND,
+            description = "The repository or object does not exist",
+            body = Error,
+        ),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(
+    state: State<BileState>,
+    Path(repo_name): Path<RepoName>,
+    Query(params): Query<Params>,
+) -> Response {
+    state
+        .spawn(Cost::Heavy, move |state| inner(&state, &repo_name, &params))
+        .await
+}
+
+fn inner(state: &BileState, repo_name: &RepoName, params: &Params) -> Result<Response> {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    let from = match params.cursor.as_deref().map(Cursor::parse) {
+        Some(Some(from)) if from.exists_in(&repo) => Some(from),
+        Some(_) => {
+            return Ok(ErrorPage::from(state)
+                .with_status(StatusCode::BAD_REQUEST)
+                .into_response());
+        }
+        None => None,
+    };
+
+    let spec = params.r#ref.as_ref().map_or("HEAD", |r| r.0.as_str());
+
+    let Some(page) = repo
+        .commits_for_obj(
+            Tips::Spec(spec),
+            state.config.log_per_page,
+            params.path.as_ref(),
+            from.as_ref(),
+            Order::Time,
+        )
+        .context("failed to get commits for object")?
+    else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    let revision = page
+        .tips
+        .iter()
+        .copied()
+        .collect::<Revision>()
+        .named_by([spec]);
+
+    Ok((
+        revision,
+        ApiJson(CommitList {
+            commits: page.commits.iter().map(Commit::from).collect(),
+            next_cursor: page.next.as_ref().map(ToString::to_string),
+        }),
+    )
+        .into_response())
+}
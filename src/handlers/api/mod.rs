@@ -0,0 +1,42 @@
+//! Version 1 of the JSON API, see [`schema`] for what each endpoint returns.
+
+pub(crate) mod blob;
+pub(crate) mod commit;
+pub(crate) mod commits;
+pub(crate) mod refs;
+pub(crate) mod repo;
+pub(crate) mod repos;
+pub(crate) mod schema;
+pub(crate) mod tree;
+
+use axum::response::{IntoResponse as _, Response};
+use utoipa::OpenApi as _;
+
+use crate::http::response::ApiJson;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    info(
+        title = "bile",
+        version = "1",
+        description = "Read only access to the repositories of a bile instance",
+    ),
+    servers((url = "/api/v1")),
+    paths(
+        repos::get,
+        repo::get,
+        refs::get,
+        commits::get,
+        commit::get,
+        tree::get_1,
+        tree::get_2,
+        blob::get,
+    ),
+)]
+struct ApiDoc;
+
+/// Serves the `OpenAPI` description of every endpoint, generated from the handlers and [`schema`].
+#[tracing::instrument(skip_all)]
+pub(crate) async fn openapi() -> Response {
+    ApiJson(ApiDoc::openapi()).into_response()
+}
@@ -0,0 +1,76 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse as _, Response},
+};
+
+use crate::{
+    BileState,
+    error::{Context as _, Result},
+    git::Repository,
+    handlers::api::schema::{Branch, Error, Refs, Signature, Tag},
+    http::{
+        extractor::RepoName,
+        path::Path,
+        pool::Cost,
+        response::{ApiJson, ErrorPage},
+    },
+};
+
+/// List the branches and tags of a repository
+#[utoipa::path(
+    get,
+    path = "/repos/{name}/refs",
+    operation_id = "list_refs",
+    params(("name" = String, Path, description = "Name of the repository")),
+    responses(
+        (status = OK, body = Refs),
+        (
+            status = NOT_FOUND,
+            description = "The repository or object does not exist",
+            body = Error,
+        ),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(state: State<BileState>, Path(repo_name): Path<RepoName>) -> Response {
+    state
+        .spawn(Cost::Light, move |state| inner(&state, &repo_name))
+        .await
+}
+
+fn inner(state: &BileState, repo_name: &RepoName) -> Result<Response> {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    let branches = repo
+        .branches()?
+        .iter()
+        .filter_map(|branch| {
+            Some(Branch {
+                name: branch.shorthand()?.to_string(),
+                commit: branch.peel_to_commit().ok()?.id().to_string(),
+            })
+        })
+        .collect();
+
+    let mut tags = repo.tag_entries()?;
+
+    // sort so that newest tags are at the top
+    tags.sort_unstable_by(|a, b| a.signature.when().cmp(&b.signature.when()).reverse());
+
+    let tags = tags
+        .into_iter()
+        .map(|tag| Tag {
+            commit: tag.target.to_string(),
+            tagger: Signature::from(&tag.signature),
+            name: tag.tag,
+            message: tag.message,
+        })
+        .collect();
+
+    Ok(ApiJson(Refs { branches, tags }).into_response())
+}
@@ -0,0 +1,53 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse as _, Response},
+};
+
+use crate::{
+    BileState,
+    error::{Context as _, Result},
+    handlers::api::schema::{Error, Repo},
+    http::{
+        extractor::RepoName,
+        path::Path,
+        pool::Cost,
+        response::{ApiJson, ErrorPage},
+    },
+};
+
+/// Get a repository
+#[utoipa::path(
+    get,
+    path = "/repos/{name}",
+    operation_id = "get_repo",
+    params(("name" = String, Path, description = "Name of the repository")),
+    responses(
+        (status = OK, body = Repo),
+        (
+            status = NOT_FOUND,
+            description = "The repository or object does not exist",
+            body = Error,
+        ),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(state: State<BileState>, Path(repo_name): Path<RepoName>) -> Response {
+    state
+        .spawn(Cost::Light, move |state| inner(&state, &repo_name))
+        .await
+}
+
+fn inner(state: &BileState, repo_name: &RepoName) -> Result<Response> {
+    let Some(repo) = state
+        .repos
+        .get(&state.config, repo_name)
+        .context("opening repository")?
+    else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    Ok(ApiJson(Repo::new(&state.config, &repo)).into_response())
+}
@@ -0,0 +1,34 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse as _, Response},
+};
+
+use crate::{
+    BileState,
+    error::Result,
+    handlers::api::schema::{Repo, RepoList},
+    http::{pool::Cost, response::ApiJson},
+};
+
+/// List all repositories
+#[utoipa::path(
+    get,
+    path = "/repos",
+    operation_id = "list_repos",
+    responses((status = OK, body = RepoList)),
+)]
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(state: State<BileState>) -> Response {
+    state.spawn(Cost::Light, move |state| inner(&state)).await
+}
+
+fn inner(state: &BileState) -> Result<Response> {
+    let repos = state
+        .repos
+        .all(&state.config)?
+        .iter()
+        .map(|repo| Repo::new(&state.config, repo))
+        .collect();
+
+    Ok(ApiJson(RepoList { repos }).into_response())
+}
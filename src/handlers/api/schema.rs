@@ -0,0 +1,404 @@
+//! The types returned by version 1 of the JSON API.
+//!
+//! Fields are only ever added to these, anything else needs a new API version.
+//! Object ids are full hexadecimal SHA-1 hashes and times are RFC 3339 strings
+//! in the offset they were recorded with.
+
+use std::path::Path;
+
+use git2::{Delta, ObjectType, Patch};
+use jiff::{Timestamp, tz::Offset};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    config::Config,
+    error::Result,
+    git::{Repository, backend, metadata::RepoInfo},
+    handlers::repo_file_raw,
+    http::extractor::RepoName,
+    utils::blob_mime,
+};
+
+/// An error response, sent with the same status code as the response.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Error {
+    pub status: u16,
+    pub message: String,
+    /// Also sent as the `x-request-id` header, `null` when there is none
+    pub request_id: Option<String>,
+}
+
+/// Every exported repository, sorted by name.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct RepoList {
+    pub repos: Vec<Repo>,
+}
+
+/// A repository.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Repo {
+    pub name: String,
+    /// First line of the repository's `description` file
+    pub description: String,
+    /// `gitweb.owner` from the repository's config
+    pub owner: Option<String>,
+    /// `bile.section` from the repository's config
+    pub section: Option<String>,
+    /// Branch `HEAD` points to, `null` for detached or empty repositories
+    pub default_branch: Option<String>,
+    /// Commit time of `HEAD`, `null` for empty repositories
+    #[schema(format = DateTime)]
+    pub last_modified: Option<String>,
+    pub clone_url: String,
+}
+
+impl Repo {
+    pub(crate) fn new(config: &Config, repo: &RepoInfo) -> Self {
+        Self {
+            clone_url: format!("{}/{}", config.clone_base, repo.name),
+            name: repo.name.clone(),
+            description: repo.description.clone(),
+            owner: repo.owner.clone(),
+            section: repo.section.clone(),
+            default_branch: repo.default_branch.clone(),
+            last_modified: repo.last_modified.map(time),
+        }
+    }
+}
+
+/// The branches and tags of a repository.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Refs {
+    pub branches: Vec<Branch>,
+    /// Newest first
+    pub tags: Vec<Tag>,
+}
+
+/// A branch of a repository.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Branch {
+    pub name: String,
+    /// Commit the branch points to
+    pub commit: String,
+}
+
+/// A tag of a repository.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Tag {
+    pub name: String,
+    /// Commit the tag points to
+    pub commit: String,
+    /// Empty for lightweight tags
+    pub message: String,
+    /// The tagger, or the committer for lightweight tags
+    pub tagger: Signature,
+}
+
+/// The author, committer or tagger of an object.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Signature {
+    pub name: String,
+    pub email: String,
+    #[schema(format = DateTime)]
+    pub time: String,
+}
+
+impl From<&git2::Signature<'_>> for Signature {
+    fn from(signature: &git2::Signature<'_>) -> Self {
+        Self {
+            name: String::from_utf8_lossy(signature.name_bytes()).into_owned(),
+            email: String::from_utf8_lossy(signature.email_bytes()).into_owned(),
+            time: time(signature.when()),
+        }
+    }
+}
+
+impl From<&backend::Signature> for Signature {
+    fn from(signature: &backend::Signature) -> Self {
+        Self {
+            name: signature.name.clone(),
+            email: signature.email.clone(),
+            time: time(signature.when()),
+        }
+    }
+}
+
+/// A page of commits.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct CommitList {
+    /// Newest first
+    pub commits: Vec<Commit>,
+    /// Pass as `cursor` to get the next page, `null` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// A commit without its changes.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Commit {
+    pub id: String,
+    /// First line of the message
+    pub summary: String,
+    pub message: String,
+    pub author: Signature,
+    pub committer: Signature,
+    pub parents: Vec<String>,
+}
+
+impl From<&git2::Commit<'_>> for Commit {
+    fn from(commit: &git2::Commit<'_>) -> Self {
+        Self {
+            id: commit.id().to_string(),
+            summary: String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default())
+                .into_owned(),
+            message: String::from_utf8_lossy(commit.message_bytes()).into_owned(),
+            author: Signature::from(&commit.author()),
+            committer: Signature::from(&commit.committer()),
+            parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+        }
+    }
+}
+
+/// A commit with the files it changed.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct CommitDetail {
+    #[serde(flatten)]
+    pub commit: Commit,
+    /// Totals of `files`
+    pub stats: Stats,
+    /// Changes compared to the first parent
+    pub files: Vec<FileChange>,
+}
+
+/// Number of changed files and lines.
+#[derive(Default, Serialize, ToSchema)]
+pub(crate) struct Stats {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// A file changed by a commit.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct FileChange {
+    pub path: String,
+    /// Previous path of renamed and copied files
+    pub old_path: Option<String>,
+    pub status: ChangeStatus,
+    pub binary: bool,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// How a file was changed.
+#[derive(Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ChangeStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Copied,
+    TypeChanged,
+}
+
+impl CommitDetail {
+    pub(crate) fn new(commit: &git2::Commit<'_>, diff: &git2::Diff<'_>) -> Result<Self> {
+        let mut totals = Stats::default();
+        let mut files = Vec::new();
+
+        for (idx, delta) in diff.deltas().enumerate() {
+            let status = match delta.status() {
+                Delta::Added => ChangeStatus::Added,
+                Delta::Deleted => ChangeStatus::Deleted,
+                Delta::Renamed => ChangeStatus::Renamed,
+                Delta::Copied => ChangeStatus::Copied,
+                Delta::Typechange => ChangeStatus::TypeChanged,
+                _ => ChangeStatus::Modified,
+            };
+
+            let (insertions, deletions) = match Patch::from_diff(diff, idx)? {
+                Some(patch) => {
+                    let (_, insertions, deletions) = patch.line_stats()?;
+                    (insertions, deletions)
+                }
+                None => (0, 0),
+            };
+
+            let path = |file: git2::DiffFile<'_>| {
+                file.path().map(|path| path.to_string_lossy().into_owned())
+            };
+
+            let new_path = path(delta.new_file());
+            let old_path = path(delta.old_file());
+
+            totals.files_changed += 1;
+            totals.insertions += insertions;
+            totals.deletions += deletions;
+
+            files.push(FileChange {
+                path: new_path
+                    .clone()
+                    .or_else(|| old_path.clone())
+                    .unwrap_or_default(),
+                old_path: old_path
+                    .filter(|_| matches!(status, ChangeStatus::Renamed | ChangeStatus::Copied)),
+                status,
+                binary: delta.flags().is_binary(),
+                insertions,
+                deletions,
+            });
+        }
+
+        Ok(Self {
+            commit: Commit::from(commit),
+            stats: totals,
+            files,
+        })
+    }
+}
+
+/// The entries of a directory.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Tree {
+    /// The requested ref
+    pub r#ref: String,
+    /// Commit the ref resolved to
+    pub commit: String,
+    /// Empty for the root of the repository
+    pub path: String,
+    pub entries: Vec<TreeEntry>,
+}
+
+/// A file, directory or submodule in a directory.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct TreeEntry {
+    pub name: String,
+    pub path: String,
+    pub kind: EntryKind,
+    /// Unix file mode
+    pub mode: i32,
+    pub id: String,
+    /// Size in bytes, `null` for anything but blobs
+    pub size: Option<usize>,
+}
+
+impl Tree {
+    pub(crate) fn new(
+        repo: &Repository,
+        r#ref: &str,
+        commit: &git2::Commit<'_>,
+        tree: &git2::Tree<'_>,
+        path: &str,
+    ) -> Result<Self> {
+        let odb = repo.as_inner().odb()?;
+
+        let entries = tree
+            .iter()
+            .filter_map(|entry| {
+                let kind = EntryKind::new(entry.kind())?;
+                let name = String::from_utf8_lossy(entry.name_bytes()).into_owned();
+
+                let size = match kind {
+                    EntryKind::Blob => odb.read_header(entry.id()).ok().map(|(size, _)| size),
+                    EntryKind::Tree | EntryKind::Commit => None,
+                };
+
+                Some(TreeEntry {
+                    path: if path.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{path}/{name}")
+                    },
+                    name,
+                    kind,
+                    mode: entry.filemode(),
+                    id: entry.id().to_string(),
+                    size,
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            r#ref: r#ref.to_string(),
+            commit: commit.id().to_string(),
+            path: path.to_string(),
+            entries,
+        })
+    }
+}
+
+/// The type of object of a tree entry.
+#[derive(Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EntryKind {
+    Blob,
+    Tree,
+    /// A submodule
+    Commit,
+}
+
+impl EntryKind {
+    pub(crate) const fn new(kind: Option<ObjectType>) -> Option<Self> {
+        match kind {
+            Some(ObjectType::Blob) => Some(Self::Blob),
+            Some(ObjectType::Tree) => Some(Self::Tree),
+            Some(ObjectType::Commit) => Some(Self::Commit),
+            _ => None,
+        }
+    }
+}
+
+/// Metadata of a file, its contents are at `raw_url`.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Blob {
+    /// The requested ref
+    pub r#ref: String,
+    /// Commit the ref resolved to
+    pub commit: String,
+    pub path: String,
+    pub id: String,
+    /// Size in bytes
+    pub size: usize,
+    pub binary: bool,
+    /// Guessed from the file extension and contents
+    pub mime: String,
+    /// Where to get the contents from
+    pub raw_url: String,
+}
+
+impl Blob {
+    pub(crate) fn new(
+        config: &Config,
+        repo_name: &RepoName,
+        r#ref: &str,
+        commit: &git2::Commit<'_>,
+        path: &Path,
+        blob: &git2::Blob<'_>,
+    ) -> Self {
+        let extension = path
+            .extension()
+            .and_then(std::ffi::OsStr::to_str)
+            .unwrap_or_default();
+
+        Self {
+            r#ref: r#ref.to_string(),
+            commit: commit.id().to_string(),
+            path: path.display().to_string(),
+            id: blob.id().to_string(),
+            size: blob.size(),
+            binary: blob.is_binary(),
+            mime: blob_mime(blob, extension).to_string(),
+            raw_url: repo_file_raw::url(config, repo_name, r#ref, path),
+        }
+    }
+}
+
+/// Formats a git time as RFC 3339 in its original offset.
+fn time(time: git2::Time) -> String {
+    let offset = Offset::from_seconds(time.offset_minutes() * 60).unwrap_or(Offset::UTC);
+
+    Timestamp::from_second(time.seconds())
+        .map(|timestamp| timestamp.display_with_offset(offset).to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,131 @@
+use std::path;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse as _, Response},
+};
+
+use crate::{
+    BileState,
+    error::{Context as _, Result},
+    git::Repository,
+    handlers::api::schema::{Error, Tree},
+    http::{
+        conditional::Revision,
+        extractor::{ObjectName, Ref, RepoName},
+        path::Path,
+        pool::Cost,
+        response::{ApiJson, ErrorPage},
+    },
+};
+
+/// List the root directory of a ref
+#[utoipa::path(
+    get,
+    path = "/repos/{name}/tree/{ref}",
+    operation_id = "get_root_tree",
+    params(
+        ("name" = String, Path, description = "Name of the repository"),
+        ("ref" = String, Path, description = "Branch, tag or commit"),
+    ),
+    responses(
+        (status = OK, body = Tree),
+        (
+            status = NOT_FOUND,
+            description = "The repository or object does not exist",
+            body = Error,
+        ),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get_1(
+    state: State<BileState>,
+    Path((repo_name, r#ref)): Path<(RepoName, Ref)>,
+) -> Response {
+    state
+        .spawn(Cost::Light, move |state| {
+            inner(&state, &repo_name, &r#ref, None)
+        })
+        .await
+}
+
+/// List a directory of a ref
+#[utoipa::path(
+    get,
+    path = "/repos/{name}/tree/{ref}/{path}",
+    operation_id = "get_tree",
+    params(
+        ("name" = String, Path, description = "Name of the repository"),
+        ("ref" = String, Path, description = "Branch, tag or commit"),
+        (
+            "path" = String,
+            Path,
+            description = "Path inside the repository, its `/` are not encoded",
+            allow_reserved,
+        ),
+    ),
+    responses(
+        (status = OK, body = Tree),
+        (
+            status = NOT_FOUND,
+            description = "The repository or object does not exist",
+            body = Error,
+        ),
+    ),
+)]
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get_2(
+    state: State<BileState>,
+    Path((repo_name, r#ref, object_name)): Path<(RepoName, Ref, ObjectName)>,
+) -> Response {
+    state
+        .spawn(Cost::Light, move |state| {
+            inner(&state, &repo_name, &r#ref, Some(&object_name))
+        })
+        .await
+}
+
+fn inner(
+    state: &BileState,
+    repo_name: &RepoName,
+    r#ref: &Ref,
+    object_name: Option<&ObjectName>,
+) -> Result<Response> {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    let Some((commit, root)) = repo
+        .commit_tree(&r#ref.0)
+        .context("failed to get commit tree")?
+    else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    let path = object_name.map_or("", |name| name.0.trim_matches('/'));
+
+    let tree = if path.is_empty() {
+        root
+    } else {
+        let Some(tree) = repo
+            .tree_object(&root, path::Path::new(path))?
+            .and_then(|obj| obj.into_tree().ok())
+        else {
+            return Ok(ErrorPage::from(state)
+                .with_status(StatusCode::NOT_FOUND)
+                .into_response());
+        };
+
+        tree
+    };
+
+    let tree = Tree::new(&repo, &r#ref.0, &commit, &tree, path)?;
+    let revision = Revision::of_commit(&commit).named_by([r#ref.0.as_str()]);
+
+    Ok((revision, ApiJson(tree)).into_response())
+}
@@ -8,7 +8,7 @@ use crate::{
     BileState,
     error::{Context as _, Result},
     git::Repository,
-    http::{extractor::RepoName, path::Path, response::ErrorPage},
+    http::{extractor::RepoName, path::Path, pool::Cost, range::Streamed, response::ErrorPage},
 };
 
 #[tracing::instrument(skip_all)]
@@ -18,7 +18,7 @@ pub(crate) async fn get_1(
     Path(repo_name): Path<RepoName>,
 ) -> Response {
     state
-        .spawn(move |state| inner(&state, &uri, &repo_name))
+        .spawn(Cost::Light, move |state| inner(&state, &uri, &repo_name))
         .await
 }
 
@@ -29,13 +29,12 @@ pub(crate) async fn get_2(
     Path((repo_name, _)): Path<(RepoName, String)>,
 ) -> Response {
     state
-        .spawn(move |state| inner(&state, &uri, &repo_name))
+        .spawn(Cost::Light, move |state| inner(&state, &uri, &repo_name))
         .await
 }
 
 fn inner(state: &BileState, uri: &Uri, repo_name: &RepoName) -> Result<Response> {
-    let Some(repo) = Repository::open(&state.config, repo_name).context("opening repository")?
-    else {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
         return Ok(ErrorPage::from(state)
             .with_status(StatusCode::NOT_FOUND)
             .into_response());
@@ -73,7 +72,7 @@ fn inner(state: &BileState, uri: &Uri, repo_name: &RepoName) -> Result<Response>
             .into_response());
     }
 
-    let body = std::fs::read(&path).context("reading file")?;
+    let len = path.metadata().context("reading file metadata")?.len();
 
     Ok((
         StatusCode::OK,
@@ -81,7 +80,7 @@ fn inner(state: &BileState, uri: &Uri, repo_name: &RepoName) -> Result<Response>
             header::CONTENT_TYPE,
             HeaderValue::from_static(mime::APPLICATION_OCTET_STREAM.as_ref()),
         )],
-        body,
+        Streamed::file(path, len),
     )
         .into_response())
 }
@@ -1,4 +1,4 @@
-use std::fs;
+use std::sync::Arc;
 
 use axum::{
     extract::State,
@@ -8,9 +8,10 @@ use axum::{
 use crate::{
     BileState,
     config::Config,
-    error::{Context as _, Result},
-    git::Repository,
-    http::response::Html,
+    error::Result,
+    git::metadata::RepoInfo,
+    handlers::api,
+    http::{negotiate::Format, pool::Cost, response::Html},
     utils::filters,
 };
 
@@ -23,54 +24,23 @@ struct IndexTemplate<'a> {
 
 struct Section {
     name: Option<String>,
-    repos: Vec<Repository>,
+    repos: Vec<Arc<RepoInfo>>,
 }
 
 #[tracing::instrument(skip_all)]
-pub(crate) async fn get(state: State<BileState>) -> Response {
-    state.spawn(move |state| inner(&state)).await
+pub(crate) async fn get(state: State<BileState>, format: Format) -> Response {
+    if format == Format::Json {
+        return api::repos::get(state).await;
+    }
+
+    state.spawn(Cost::Light, move |state| inner(&state)).await
 }
 
 fn inner(state: &BileState) -> Result<Response> {
-    let Ok(read) = fs::read_dir(&state.config.project_root) else {
-        return Ok(Html(IndexTemplate {
-            config: &state.config,
-            sections: Vec::new(),
-        })
-        .into_response());
-    };
-
     let mut sections = Vec::new();
 
-    for entry in read {
-        let entry = entry.context("failed to open directory entry")?;
-        let metadata = entry.metadata().context("failed to get file metadata")?;
-
-        if !metadata.is_dir() {
-            continue;
-        }
-
-        if entry
-            .file_name()
-            .to_str()
-            .is_some_and(|p| p != "." && p.starts_with('.'))
-        {
-            continue;
-        }
-
-        let Some(repo) = Repository::open_path(&state.config, &entry.path())
-            .context("failed to open repository")?
-        else {
-            continue;
-        };
-
-        // check for the export file in the git directory
-        // (the .git subfolder for non-bare repos)
-        if !repo.path().join(&state.config.export_ok).exists() {
-            continue;
-        }
-
-        let repo_section = repo.section();
+    for repo in state.repos.all(&state.config)? {
+        let repo_section = repo.section.clone();
         let section = sections
             .iter_mut()
             .find(|s: &&mut Section| s.name == repo_section);
@@ -83,10 +53,8 @@ fn inner(state: &BileState) -> Result<Response> {
         }
     }
 
+    // repos are already sorted by name
     sections.sort_by(|a, b| a.name.cmp(&b.name));
-    for section in &mut sections {
-        section.repos.sort_by(|a, b| a.name().cmp(&b.name()));
-    }
 
     Ok(Html(IndexTemplate {
         config: &state.config,
@@ -1,6 +1,10 @@
+pub(crate) mod activity;
+pub(crate) mod api;
 pub(crate) mod git;
 pub(crate) mod index;
+pub(crate) mod repo_archive;
 pub(crate) mod repo_commit;
+pub(crate) mod repo_compare;
 pub(crate) mod repo_file;
 pub(crate) mod repo_file_raw;
 pub(crate) mod repo_home;
@@ -0,0 +1,96 @@
+use axum::{
+    extract::State,
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse as _, Response},
+};
+
+use crate::{
+    BileState,
+    error::{Context as _, Result},
+    git::Repository,
+    handlers::repo_file_raw::sanitize_file_name,
+    http::{
+        conditional::Revision,
+        extractor::{ObjectName, RepoName},
+        path::Path,
+        pool::{Cost, Worker},
+        response::ErrorPage,
+        stream,
+    },
+};
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(
+    state: State<BileState>,
+    Path((repo_name, object_name)): Path<(RepoName, ObjectName)>,
+) -> Response {
+    state
+        .spawn(Cost::Heavy, move |state| {
+            inner(&state, &repo_name, &object_name)
+        })
+        .await
+}
+
+#[tracing::instrument(skip_all)]
+fn inner(state: &BileState, repo_name: &RepoName, object_name: &ObjectName) -> Result<Response> {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    // the ref can contain slashes, so the extension is part of the path
+    let Some(spec) = object_name.0.strip_suffix(".tar.gz") else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    let Some(commit) = repo.commit(spec).context("failed to get commit")? else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    // named like the archives of most forges, e.g. `bile-v1.0`
+    let prefix = format!(
+        "{}-{}",
+        repo_name.0.trim_end_matches(".git"),
+        spec.replace('/', "-")
+    );
+
+    let config = &state.config;
+    if !repo.archive_fits(&commit, config.max_archive_entries, config.max_archive_size)? {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::FORBIDDEN)
+            .with_message(
+                "This tree is too large to download as an archive, clone the repository instead.",
+            )
+            .into_response());
+    }
+
+    let disposition = HeaderValue::from_str(&format!(
+        "attachment; filename=\"{}.tar.gz\"",
+        sanitize_file_name(&prefix)
+    ))?;
+
+    // written while it is sent, holding on to the worker of this request
+    let (dir, id) = (repo.path().to_path_buf(), commit.id());
+    let archive = stream::body(
+        Worker::current(),
+        Box::new(move |write| Repository::archive(&dir, id, &prefix, write)),
+    );
+
+    Ok((
+        Revision::of_commit(&commit).named_by([spec]),
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/gzip"),
+            ),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        archive,
+    )
+        .into_response())
+}
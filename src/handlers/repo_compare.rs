@@ -0,0 +1,122 @@
+use askama::Template as _;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse as _, Response},
+};
+
+use crate::{
+    BileState,
+    config::Config,
+    error::{Context as _, Result},
+    git::Repository,
+    http::{
+        conditional::Revision,
+        extractor::{ObjectName, RepoName},
+        path::Path,
+        pool::Cost,
+        response::{ErrorPage, Html, STREAMED, StreamedHtml},
+    },
+    utils::{diff::Changes, filters},
+};
+
+#[derive(askama::Template)]
+#[template(path = "compare.html")]
+struct RepoCompareTemplate<'a> {
+    config: &'a Config,
+    repo: &'a Repository,
+    from: &'a str,
+    to: &'a str,
+    commits: Vec<git2::Commit<'a>>,
+    // there were more commits than shown
+    truncated: bool,
+    stats: git2::DiffStats,
+    /// `None` when the diff has too many changed lines to show
+    diff: Option<&'a str>,
+}
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(
+    state: State<BileState>,
+    Path((repo_name, range)): Path<(RepoName, ObjectName)>,
+) -> Response {
+    state
+        .spawn(Cost::Heavy, move |state| inner(&state, &repo_name, &range))
+        .await
+}
+
+#[tracing::instrument(skip_all)]
+fn inner(state: &BileState, repo_name: &RepoName, range: &ObjectName) -> Result<Response> {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    // refs can not contain "..", so the range splits unambiguously
+    let Some((from, to)) = range
+        .0
+        .split_once("...")
+        .or_else(|| range.0.split_once(".."))
+    else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    // the diff takes over the repository, so the commits are found again in it
+    let (revision, from_id, to_id) = {
+        let (Some(from_commit), Some(to_commit)) = (repo.commit(from)?, repo.commit(to)?) else {
+            return Ok(ErrorPage::from(state)
+                .with_status(StatusCode::NOT_FOUND)
+                .into_response());
+        };
+
+        let revision = Revision::of_commit(&to_commit)
+            .and(from_commit.id())
+            .named_by([to, from])
+            .on_page(&repo);
+
+        (revision, from_commit.id(), to_commit.id())
+    };
+
+    let diff = Changes::Between(from_id, to_id).diff_owned(repo)?;
+    let diff_stats = diff.stats()?;
+
+    let find = |id: git2::Oid| {
+        diff.repo()
+            .find_commit(id)
+            .context("commit of the diff is missing")
+    };
+    let (from_commit, to_commit) = (find(from_id)?, find(to_id)?);
+
+    let mut commits = diff
+        .repo()
+        .commits_between(&from_commit, &to_commit, state.config.log_per_page + 1)
+        .context("failed to get commits between refs")?;
+
+    let truncated = commits.len() > state.config.log_per_page;
+    commits.truncate(state.config.log_per_page);
+
+    let too_large = diff_stats.insertions() + diff_stats.deletions() > state.config.max_diff_lines;
+
+    let template = RepoCompareTemplate {
+        config: &state.config,
+        repo: diff.repo(),
+        from,
+        to,
+        commits,
+        truncated,
+        stats: diff_stats,
+        diff: (!too_large).then_some(STREAMED),
+    };
+
+    if too_large {
+        return Ok((revision, Html(template)).into_response());
+    }
+
+    let page = template.render()?;
+    drop((template, from_commit, to_commit));
+
+    Ok((revision, StreamedHtml(page, diff.stream(&state.syntax))).into_response())
+}
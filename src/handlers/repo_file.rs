@@ -1,27 +1,47 @@
-use std::{fmt::Write as _, path};
+use askama::{
+    Template as _,
+    filters::{urlencode, urlencode_strict},
+};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    path::{self, PathBuf},
+    sync::Arc,
+};
 
 use axum::{
     extract::State,
     http::StatusCode,
     response::{IntoResponse as _, Response},
 };
-use syntect::{
-    html::{ClassStyle, ClassedHTMLGenerator},
-    parsing::SyntaxSet,
-    util::LinesWithEndings,
-};
+use git2::Oid;
+use syntect::{parsing::SyntaxSet, util::LinesWithEndings};
 
 use crate::{
     BileState,
     config::Config,
     error::{Context as _, Result},
     git::Repository,
+    handlers::{
+        api::schema::{Blob, Tree},
+        repo_file_raw,
+    },
     http::{
+        conditional::Revision,
         extractor::{ObjectName, Ref, RepoName},
+        negotiate::Format,
         path::Path,
-        response::{ErrorPage, Html, Redirect},
+        pool::Cost,
+        response::{ApiJson, Content, ErrorPage, Html, Redirect, STREAMED, StreamedHtml},
+    },
+    utils::{
+        blob_mime, filters,
+        highlight::Highlighter,
+        markup::{Markup, escape},
+        notebook,
+        switcher::RefSwitcher,
+        table,
     },
-    utils::{blob_mime, filters},
 };
 
 #[derive(askama::Template)]
@@ -33,6 +53,12 @@ struct RepoTreeTemplate<'a> {
     path: &'a path::Path,
     spec: &'a str,
     last_commit: git2::Commit<'a>,
+    /// The last commit of each entry, by name
+    last_commits: HashMap<String, git2::Commit<'a>>,
+    /// The URL named the commit by its full id, so the page leaves out its
+    /// branches and tags
+    pinned: bool,
+    switcher: RefSwitcher,
 }
 
 #[derive(askama::Template)]
@@ -44,43 +70,59 @@ struct RepoFileTemplate<'a> {
     file_text: &'a str,
     spec: &'a str,
     last_commit: git2::Commit<'a>,
+    /// The URL named the commit by its full id, so the page leaves out its
+    /// branches and tags
+    pinned: bool,
+    switcher: RefSwitcher,
 }
 
 #[tracing::instrument(skip_all)]
-pub(crate) async fn get_1(state: State<BileState>, Path(repo_name): Path<RepoName>) -> Response {
+pub(crate) async fn get_1(
+    state: State<BileState>,
+    format: Format,
+    Path(repo_name): Path<RepoName>,
+) -> Response {
     state
-        .spawn(move |state| inner(&state, &repo_name, None, None))
+        .spawn(Cost::Heavy, move |state| {
+            inner(&state, format, &repo_name, None, None)
+        })
         .await
 }
 
 #[tracing::instrument(skip_all)]
 pub(crate) async fn get_2(
     state: State<BileState>,
+    format: Format,
     Path((repo_name, r#ref)): Path<(RepoName, Ref)>,
 ) -> Response {
     state
-        .spawn(move |state| inner(&state, &repo_name, Some(&r#ref), None))
+        .spawn(Cost::Heavy, move |state| {
+            inner(&state, format, &repo_name, Some(&r#ref), None)
+        })
         .await
 }
 
 #[tracing::instrument(skip_all)]
 pub(crate) async fn get_3(
     state: State<BileState>,
+    format: Format,
     Path((repo_name, r#ref, object_name)): Path<(RepoName, Ref, ObjectName)>,
 ) -> Response {
     state
-        .spawn(move |state| inner(&state, &repo_name, Some(&r#ref), Some(&object_name)))
+        .spawn(Cost::Heavy, move |state| {
+            inner(&state, format, &repo_name, Some(&r#ref), Some(&object_name))
+        })
         .await
 }
 
 fn inner(
     state: &BileState,
+    format: Format,
     repo_name: &RepoName,
     r#ref: Option<&Ref>,
     object_name: Option<&ObjectName>,
 ) -> Result<Response> {
-    let Some(repo) = Repository::open(&state.config, repo_name).context("opening repository")?
-    else {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
         return Ok(ErrorPage::from(state)
             .with_status(StatusCode::NOT_FOUND)
             .into_response());
@@ -116,24 +158,52 @@ fn inner(
             .into_response());
     };
 
-    let Some(last_commit) = repo.file_last_commit(&spec, path)? else {
+    if format == Format::Json {
+        return json(state, &repo, repo_name, &spec, &commit, path, &tree_obj);
+    }
+
+    let Some(last_commit) = repo.file_last_commit(&state.last_commits, &spec, path)? else {
         return Ok(ErrorPage::from(state)
             .with_status(StatusCode::NOT_FOUND)
             .into_response());
     };
 
+    let revision = Revision::of_commit(&commit).named_by([spec.as_str()]);
+    let pinned = revision.immutable();
+
+    let (revision, switcher) = if pinned {
+        let all = format!("/{repo_name}/refs");
+
+        (revision, RefSwitcher::pinned(spec.clone(), all))
+    } else {
+        (
+            revision.on_page(&repo),
+            switcher(&repo, repo_name, &spec, path)?,
+        )
+    };
+
     let tree_obj = match tree_obj.into_tree() {
         // this is a subtree
         Ok(sub_tree) => {
-            return Ok(Html(RepoTreeTemplate {
-                config: &state.config,
-                repo: &repo,
-                tree: sub_tree,
-                path,
-                spec: &spec,
-                last_commit,
-            })
-            .into_response());
+            let last_commits = repo
+                .last_commits(&state.last_commits, &commit, path)
+                .context("failed to get last commits of entries")?;
+
+            return Ok((
+                revision,
+                Html(RepoTreeTemplate {
+                    config: &state.config,
+                    repo: &repo,
+                    tree: sub_tree,
+                    path,
+                    spec: &spec,
+                    last_commit,
+                    last_commits,
+                    pinned,
+                    switcher,
+                }),
+            )
+                .into_response());
         }
         // this is not a subtree, so it should be a blob i.e. file
         Err(tree_obj) => tree_obj,
@@ -145,99 +215,256 @@ fn inner(
             .into_response());
     };
 
-    let output = render(&state.syntax, repo_name, path, &spec, &commit, blob)?;
+    let (html, content) = match render(state, &repo, repo_name, path, &spec, &commit, blob)? {
+        View::Html(html) => (html, None),
+        View::Code(content) => (STREAMED.to_string(), Some(content)),
+    };
 
-    Ok(Html(RepoFileTemplate {
+    let template = RepoFileTemplate {
         config: &state.config,
         repo: &repo,
         path,
-        file_text: &output,
+        file_text: &html,
         spec: &spec,
         last_commit,
+        pinned,
+        switcher,
+    };
+
+    Ok(match content {
+        Some(content) => (revision, StreamedHtml(template.render()?, content)).into_response(),
+        None => (revision, Html(template)).into_response(),
     })
-    .into_response())
+}
+
+/// Switching to another ref keeps showing the same path.
+fn switcher(
+    repo: &Repository,
+    repo_name: &RepoName,
+    spec: &str,
+    path: &path::Path,
+) -> Result<RefSwitcher> {
+    let item = path.to_string_lossy();
+    let item = item.trim_matches('/');
+
+    RefSwitcher::new(
+        repo,
+        spec.to_string(),
+        format!("/{repo_name}/tree/"),
+        if item.is_empty() {
+            String::new()
+        } else {
+            format!("/item/{item}")
+        },
+    )
+}
+
+/// The tree or blob as returned by the JSON API.
+fn json(
+    state: &BileState,
+    repo: &Repository,
+    repo_name: &RepoName,
+    spec: &str,
+    commit: &git2::Commit<'_>,
+    path: &path::Path,
+    obj: &git2::Object<'_>,
+) -> Result<Response> {
+    let revision = Revision::of_commit(commit).named_by([spec]);
+
+    if let Some(tree) = obj.as_tree() {
+        let path = path.to_string_lossy();
+        let tree = Tree::new(repo, spec, commit, tree, path.trim_matches('/'))?;
+
+        return Ok((revision, ApiJson(tree)).into_response());
+    }
+
+    let Some(blob) = obj.as_blob() else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response());
+    };
+
+    let blob = Blob::new(&state.config, repo_name, spec, commit, path, blob);
+
+    Ok((revision, ApiJson(blob)).into_response())
+}
+
+/// How a blob is shown on its page.
+enum View {
+    Html(String),
+    /// Code, which is highlighted while the page is sent.
+    Code(Content),
 }
 
 // TODO: make sure I am escaping html properly here
 // TODO: allow disabling of syntax highlighting
-// TODO: -- dont pull in memory, use iterators if possible
 fn render(
-    syntaxes: &SyntaxSet,
+    state: &BileState,
+    repo: &Repository,
     repo_name: &RepoName,
     path: &path::Path,
     spec: &str,
     commit: &git2::Commit<'_>,
     blob: &git2::Blob<'_>,
-) -> Result<String> {
+) -> Result<View> {
+    let (syntaxes, config) = (&*state.syntax, &*state.config);
+
     let extension = path
         .extension()
         .and_then(std::ffi::OsStr::to_str)
         .unwrap_or_default();
 
-    if blob.is_binary() {
-        // this is not a text file, but try to serve the file if the MIME type
-        // can give a hint at how
-        let mime = blob_mime(blob, extension);
-
-        let output = match mime.type_() {
-            mime::TEXT => unreachable!("git detected this file as binary"),
-            mime::IMAGE | mime::BMP | mime::GIF | mime::JPEG | mime::PNG | mime::SVG => format!(
-                "<img src=\"/{}/tree/{}/raw/{}\" />",
-                repo_name,
-                spec,
-                path.display()
-            ),
-            tag @ (mime::AUDIO | mime::VIDEO) => format!(
-                "<{} src=\"/{}/tree/{}/raw/{}\" controls>Your browser does not have support for playing this {0} file.</{0}>",
-                tag,
-                repo_name,
-                spec,
-                path.display()
-            ),
-            name => {
-                tracing::warn!(mime=?mime, name=?name, "unsupported mime type");
-                "Cannot display binary file.".to_string()
-            }
-        };
+    let raw = escape(&repo_file_raw::url(config, repo_name, spec, path));
 
-        return Ok(output);
+    if blob.is_binary() {
+        return Ok(View::Html(binary(&raw, extension, blob)));
     }
 
-    let syntax = syntaxes
-        .find_syntax_by_extension(extension)
-        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+    if u64::try_from(blob.size())? > config.max_file_size {
+        return Ok(View::Html(format!(
+            "<p>This file is too large to show, it can be viewed <a href=\"{raw}\">raw</a>.</p>"
+        )));
+    }
 
     // get file contents from git object
     let file_string = str::from_utf8(blob.content())?;
 
-    // create a highlighter that uses CSS classes so we can use prefers-color-scheme
-    let mut highlighter =
-        ClassedHTMLGenerator::new_with_class_style(syntax, syntaxes, ClassStyle::Spaced);
-    LinesWithEndings::from(file_string).for_each(|line| {
-        if let Err(err) = highlighter.parse_html_for_line_which_includes_newline(line) {
-            tracing::error!(err=?err, "failed to highlight code");
+    if extension.eq_ignore_ascii_case("ipynb") {
+        match notebook::render(syntaxes, &config.markdown, file_string) {
+            Ok(output) => return Ok(View::Html(output)),
+            Err(err) => {
+                tracing::warn!(err=?err, "failed to render notebook, showing its source instead");
+            }
         }
-    });
+    }
 
     // use oid so it is a permalink
-    let prefix = format!(
-        "/{}/tree/{}/item/{}",
-        repo_name,
-        commit.id(),
-        path.display()
-    );
-
-    let mut output = String::from("<pre>\n");
-    for (n, line) in highlighter.finalize().lines().enumerate() {
+    let Ok(name) = urlencode_strict(repo_name);
+    let Ok(encoded_path) = urlencode(path.display());
+    let prefix = escape(&format!("/{name}/tree/{}/item/{encoded_path}", commit.id()));
+
+    let delimiter = match extension.to_ascii_lowercase().as_str() {
+        "csv" => Some(b','),
+        "tsv" => Some(b'\t'),
+        _ => None,
+    };
+
+    if let Some(delimiter) = delimiter {
+        match table::render(file_string, delimiter, &prefix, &raw) {
+            Ok(output) => return Ok(View::Html(output)),
+            Err(err) => {
+                tracing::warn!(err=?err, "failed to render table, showing its source instead");
+            }
+        }
+    }
+
+    // show documents the same way readmes are shown, the source is still available as raw
+    if let Some(markup) = Markup::from_path(path).filter(|markup| markup.is_document()) {
+        return Ok(View::Html(format!(
+            "<div class=\"readme\">\n{}</div>\n",
+            markup.render(syntaxes, &config.markdown, file_string)
+        )));
+    }
+
+    Ok(View::Code(code(
+        Arc::clone(&state.syntax),
+        repo.path().to_path_buf(),
+        blob.id(),
+        extension.to_string(),
+        prefix,
+        raw,
+    )))
+}
+
+/// Writes the blob as highlighted code with numbered lines, reading it again
+/// once the page is sent.
+fn code(
+    syntaxes: Arc<SyntaxSet>,
+    dir: PathBuf,
+    id: Oid,
+    extension: String,
+    prefix: String,
+    raw: String,
+) -> Content {
+    Box::new(move |write| {
+        let syntax = syntaxes
+            .find_syntax_by_extension(&extension)
+            .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+
+        // images can not run scripts, so showing an SVG this way is safe
+        if extension.eq_ignore_ascii_case("svg")
+            && !write(&format!("<p><img class=\"preview\" src=\"{raw}\" /></p>\n"))
+        {
+            return Ok(());
+        }
+
+        if !write("<pre>\n") {
+            return Ok(());
+        }
+
+        Repository::read_blob(&dir, id, |content| -> Result<()> {
+            let mut highlighter = Highlighter::new(syntax, &syntaxes);
+            // spans closed after the end of a line go on the next one
+            let mut html = String::new();
+            let mut n = 0;
+
+            for line in LinesWithEndings::from(str::from_utf8(content)?) {
+                html.push_str(&highlighter.line(line));
+
+                let Some(end) = html.rfind('\n') else {
+                    continue;
+                };
+
+                if !write(&numbered(&prefix, &mut n, &html[..=end])) {
+                    return Ok(());
+                }
+
+                html.drain(..=end);
+            }
+
+            html.push_str(&highlighter.finish());
+            write(&numbered(&prefix, &mut n, &html));
+
+            Ok(())
+        })??;
+
+        write("</pre>\n");
+
+        Ok(())
+    })
+}
+
+/// Links each line of the highlighted code, counting on from `n`.
+fn numbered(prefix: &str, n: &mut usize, html: &str) -> String {
+    let mut output = String::new();
+
+    for line in html.lines() {
+        *n += 1;
         let _ = writeln!(
             &mut output,
-            "<a href='{1}#L{0}' id='L{0}' class='line'>{0}</a>{2}",
-            n + 1,
-            prefix,
-            line,
+            "<a href='{prefix}#L{n}' id='L{n}' class='line'>{n}</a>{line}"
         );
     }
-    output.push_str("</pre>\n");
 
-    Ok(output)
+    output
+}
+
+fn binary(raw: &str, extension: &str, blob: &git2::Blob<'_>) -> String {
+    // this is not a text file, but try to serve the file if the MIME type
+    // can give a hint at how
+    let mime = blob_mime(blob, extension);
+
+    match mime.type_() {
+        mime::TEXT => unreachable!("git detected this file as binary"),
+        mime::IMAGE | mime::BMP | mime::GIF | mime::JPEG | mime::PNG | mime::SVG => {
+            format!("<img src=\"{raw}\" />")
+        }
+        tag @ (mime::AUDIO | mime::VIDEO) => format!(
+            "<{tag} src=\"{raw}\" controls>Your browser does not have support for playing this {tag} file.</{tag}>"
+        ),
+        name => {
+            tracing::warn!(mime=?mime, name=?name, "unsupported mime type");
+            "Cannot display binary file.".to_string()
+        }
+    }
 }
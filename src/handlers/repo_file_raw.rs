@@ -1,30 +1,51 @@
 use std::path;
 
+use askama::filters::{urlencode, urlencode_strict};
 use axum::{
     extract::State,
-    http::{StatusCode, header},
+    http::{HeaderValue, StatusCode, header},
     response::{IntoResponse as _, Response},
 };
 
 use crate::{
     BileState,
+    config::Config,
     error::{Context as _, Result},
     git::Repository,
     http::{
+        conditional::Revision,
         extractor::{ObjectName, Ref, RepoName},
         path::Path,
+        pool::Cost,
+        range::Streamed,
         response::ErrorPage,
     },
     utils::blob_mime,
 };
 
+/// Raw files are served as they are, so nothing in them is allowed to run.
+const SANDBOX_POLICY: &str =
+    "default-src 'none'; img-src 'self' data:; style-src 'unsafe-inline'; sandbox";
+
+/// Where a file is served raw, at the raw origin if there is one.
+pub(crate) fn url(config: &Config, repo_name: &RepoName, r#ref: &str, path: &path::Path) -> String {
+    // the name and ref are a single part of the URL while the path keeps its slashes
+    let Ok(name) = urlencode_strict(repo_name);
+    let Ok(r#ref) = urlencode_strict(r#ref);
+    let Ok(path) = urlencode(path.display());
+
+    format!("{}/{name}/tree/{ref}/raw/{path}", config.raw_base)
+}
+
 #[tracing::instrument(skip_all)]
 pub(crate) async fn get(
     state: State<BileState>,
     Path((repo_name, r#ref, object_name)): Path<(RepoName, Ref, ObjectName)>,
 ) -> Response {
     state
-        .spawn(move |state| inner(&state, &repo_name, &r#ref, &object_name))
+        .spawn(Cost::Light, move |state| {
+            inner(&state, &repo_name, &r#ref, &object_name)
+        })
         .await
 }
 
@@ -35,7 +56,7 @@ fn inner(
     r#ref: &Ref,
     object_name: &ObjectName,
 ) -> Result<Response> {
-    let repo = match Repository::open(&state.config, repo_name).context("opening repository") {
+    let repo = match Repository::open(state, repo_name).context("opening repository") {
         Ok(Some(repo)) => repo,
         Ok(None) => {
             return Ok(ErrorPage::from(state)
@@ -53,7 +74,7 @@ fn inner(
 
     let path = path::Path::new(&object_name.0);
 
-    let Some((_, tree)) = repo
+    let Some((commit, tree)) = repo
         .commit_tree(&r#ref.0)
         .context("failed to get commit tree")?
     else {
@@ -74,11 +95,66 @@ fn inner(
         .unwrap_or_default();
 
     let mime = blob_mime(&blob, extension);
+    let (dir, id) = (repo.path().to_path_buf(), blob.id());
 
-    Ok((
+    let mut response = (
         StatusCode::OK,
-        [(header::CONTENT_TYPE, mime.as_ref())],
-        blob.content().to_vec(),
+        Revision::of_blob(blob.id(), &commit, &r#ref.0),
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_str(mime.as_ref())?),
+            (
+                header::CONTENT_SECURITY_POLICY,
+                HeaderValue::from_static(SANDBOX_POLICY),
+            ),
+        ],
+        Streamed::new(u64::try_from(blob.size())?, move |range, mut write| {
+            let range = usize::try_from(range.start)?..usize::try_from(range.end)?;
+
+            Repository::read_blob(&dir, id, |content| content.get(range).map(&mut write))?
+                .context("range is outside of the blob")?;
+
+            Ok(())
+        }),
     )
-        .into_response())
+        .into_response();
+
+    // a browser would run scripts in these when the URL is opened directly
+    if is_active(&mime) {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        response.headers_mut().insert(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_str(&format!(
+                "attachment; filename=\"{}\"",
+                sanitize_file_name(&file_name)
+            ))?,
+        );
+    }
+
+    Ok(response)
+}
+
+fn is_active(mime: &mime::Mime) -> bool {
+    matches!(
+        (mime.type_(), mime.subtype()),
+        (mime::TEXT, mime::HTML | mime::XML)
+            | (mime::APPLICATION, mime::XML)
+            | (mime::IMAGE, mime::SVG)
+    ) || mime.suffix() == Some(mime::XML)
+}
+
+/// Keeps the file name inside the quotes of a `Content-Disposition` header.
+pub(crate) fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c == ' ' || (c.is_ascii_graphic() && !matches!(c, '"' | '\\')) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
 }
@@ -9,9 +9,13 @@ use crate::{
     config::Config,
     error::{Context as _, Result},
     git::Repository,
+    handlers::api,
     http::{
+        conditional::Revision,
         extractor::RepoName,
+        negotiate::Format,
         path::Path,
+        pool::Cost,
         response::{ErrorPage, Html},
     },
     utils::filters,
@@ -27,20 +31,29 @@ struct RepoHomeTemplate<'a> {
 }
 
 #[tracing::instrument(skip_all)]
-pub(crate) async fn get(state: State<BileState>, Path(repo_name): Path<RepoName>) -> Response {
-    state.spawn(move |state| inner(&state, &repo_name)).await
+pub(crate) async fn get(
+    state: State<BileState>,
+    format: Format,
+    Path(repo_name): Path<RepoName>,
+) -> Response {
+    if format == Format::Json {
+        return api::repo::get(state, Path(repo_name)).await;
+    }
+
+    state
+        .spawn(Cost::Light, move |state| inner(&state, &repo_name))
+        .await
 }
 
 #[tracing::instrument(skip_all)]
 fn inner(state: &BileState, repo_name: &RepoName) -> Result<Response> {
-    let Some(repo) = Repository::open(&state.config, repo_name).context("opening repository")?
-    else {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
         return Ok(ErrorPage::from(state)
             .with_status(StatusCode::NOT_FOUND)
             .into_response());
     };
 
-    let readme_text = repo.readme(&state.syntax);
+    let readme_text = repo.readme(&state.syntax, &state.config.markdown);
 
     // TODO: let r = req.param("ref").unwrap_or("HEAD");
     let r = "HEAD";
@@ -50,11 +63,19 @@ fn inner(state: &BileState, repo_name: &RepoName) -> Result<Response> {
             .into_response());
     };
 
-    Ok(Html(RepoHomeTemplate {
-        config: &state.config,
-        repo: &repo,
-        commits,
-        readme_text,
-    })
-    .into_response())
+    // the readme is read from the same commit
+    let revision = commits
+        .first()
+        .map(|head| Revision::of_commit(head).on_page(&repo));
+
+    Ok((
+        revision,
+        Html(RepoHomeTemplate {
+            config: &state.config,
+            repo: &repo,
+            commits,
+            readme_text,
+        }),
+    )
+        .into_response())
 }
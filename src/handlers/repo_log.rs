@@ -1,20 +1,29 @@
 use axum::{
-    extract::State,
+    extract::{Query, State},
     http::StatusCode,
     response::{IntoResponse as _, Response},
 };
+use git2::Oid;
+use serde::Deserialize as _;
 
 use crate::{
     BileState,
     config::Config,
     error::{Context as _, Result},
-    git::Repository,
+    git::{Cursor, Order, Page, Repository, Tips, graph::Row},
+    handlers::{
+        api,
+        repo_log_feed::{self, Feed},
+    },
     http::{
+        conditional::Revision,
         extractor::{ObjectName, Ref, RepoName},
+        negotiate::Format,
         path::Path,
+        pool::Cost,
         response::{ErrorPage, Html, Redirect},
     },
-    utils::filters,
+    utils::{filters, switcher::RefSwitcher},
 };
 
 #[derive(askama::Template)]
@@ -23,46 +32,269 @@ struct RepoLogTemplate<'a> {
     config: &'a Config,
     repo: &'a Repository,
     commits: Vec<git2::Commit<'a>>,
+    /// Drawn next to the commits when asked for
+    graph: Option<Vec<Row>>,
     branch: String,
-    // the spec the user should be linked to to see the next page of commits
+    switcher: RefSwitcher,
+    // the links to the pages of newer and older commits
+    prev_page: Option<String>,
     next_page: Option<String>,
+    // the links to switch between the ways to show the log
+    graph_toggle: Option<String>,
+    order_toggle: String,
+    order: Order,
+    // the feeds of the shown commits
+    rss_url: String,
+    atom_url: String,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct Params {
+    /// Continue the log after a page, as linked from the previous page
+    cursor: Option<String>,
+    /// Show the page of commits right before this commit
+    before: Option<String>,
+    /// Walk every branch and tag instead of a single ref
+    #[serde(default, deserialize_with = "flag")]
+    all: bool,
+    /// Draw the commit graph
+    #[serde(default, deserialize_with = "flag")]
+    graph: bool,
+    #[serde(default)]
+    order: Order,
+    /// Serve a feed of the log of a path instead, the same as asking for
+    /// `{path}/feed.xml` or `{path}/feed.atom`
+    feed: Option<Feed>,
+}
+
+impl Params {
+    /// The commit to show the page before and the cursor to continue from, `None` if
+    /// either is invalid or the cursor continues from commits `repo` doesn't have.
+    fn cursors(&self, repo: &Repository) -> Option<(Option<Oid>, Option<Cursor>)> {
+        match (
+            self.before.as_deref().map(Cursor::parse),
+            self.cursor.as_deref().map(Cursor::parse),
+        ) {
+            (Some(None), _) | (_, Some(None)) => None,
+            (_, Some(Some(cursor))) if !cursor.exists_in(repo) => None,
+            (before, cursor) => Some((before.flatten().and_then(|c| c.first()), cursor.flatten())),
+        }
+    }
+}
+
+/// Flags in the query are set with `1` or `true`, like `?all=1`.
+fn flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+
+    Ok(matches!(value.as_str(), "1" | "true"))
+}
+
+/// How the log is shown, kept when following links.
+#[derive(Debug, Clone, Copy)]
+struct View {
+    all: bool,
+    graph: bool,
+    order: Order,
+}
+
+impl View {
+    /// The order to walk the history in.
+    const fn walk_order(self) -> Order {
+        if self.graph {
+            self.order.drawn()
+        } else {
+            self.order
+        }
+    }
+
+    /// Links to the log at `base`, at the page given by a cursor.
+    fn url(self, base: &str, page: Option<(&str, &Cursor)>) -> String {
+        format!("{base}{}", self.query(page))
+    }
+
+    fn query(self, page: Option<(&str, &Cursor)>) -> String {
+        let mut query = Vec::new();
+
+        if let Some((name, cursor)) = page {
+            query.push(format!("{name}={cursor}"));
+        }
+
+        if self.all {
+            query.push("all=1".to_string());
+        }
+
+        if self.graph {
+            query.push("graph=1".to_string());
+        }
+
+        if self.order == Order::Topo {
+            query.push("order=topo".to_string());
+        }
+
+        if query.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", query.join("&"))
+        }
+    }
 }
 
 #[tracing::instrument(skip_all)]
-pub(crate) async fn get_1(state: State<BileState>, Path(repo_name): Path<RepoName>) -> Response {
+pub(crate) async fn get_1(
+    state: State<BileState>,
+    format: Format,
+    Path(repo_name): Path<RepoName>,
+    Query(params): Query<Params>,
+) -> Response {
+    if format == Format::Json {
+        return commits(state, repo_name, None, None, params).await;
+    }
+
     state
-        .spawn(move |state| inner(&state, &repo_name, None, None))
+        .spawn(Cost::Heavy, move |state| {
+            inner(&state, &repo_name, None, None, &params)
+        })
         .await
 }
 
 #[tracing::instrument(skip_all)]
 pub(crate) async fn get_2(
     state: State<BileState>,
+    format: Format,
     Path((repo_name, r#ref)): Path<(RepoName, Ref)>,
+    Query(params): Query<Params>,
 ) -> Response {
+    if format == Format::Json {
+        return commits(state, repo_name, Some(r#ref), None, params).await;
+    }
+
     state
-        .spawn(move |state| inner(&state, &repo_name, Some(&r#ref), None))
+        .spawn(Cost::Heavy, move |state| {
+            inner(&state, &repo_name, Some(&r#ref), None, &params)
+        })
         .await
 }
 
 #[tracing::instrument(skip_all)]
 pub(crate) async fn get_3(
     state: State<BileState>,
+    format: Format,
     Path((repo_name, r#ref, object_name)): Path<(RepoName, Ref, ObjectName)>,
+    Query(params): Query<Params>,
 ) -> Response {
+    // the path is the last part of the route, so its feeds are found by their suffix
+    if let Some((feed, path)) = Feed::split(&object_name) {
+        return repo_log_feed::get_3(state, feed, repo_name, r#ref, path).await;
+    }
+
+    if let Some(feed) = params.feed {
+        return repo_log_feed::get_3(state, feed, repo_name, r#ref, object_name).await;
+    }
+
+    if format == Format::Json {
+        return commits(state, repo_name, Some(r#ref), Some(object_name), params).await;
+    }
+
     state
-        .spawn(move |state| inner(&state, &repo_name, Some(&r#ref), Some(&object_name)))
+        .spawn(Cost::Heavy, move |state| {
+            inner(
+                &state,
+                &repo_name,
+                Some(&r#ref),
+                Some(&object_name),
+                &params,
+            )
+        })
         .await
 }
 
+/// A page of the log as returned by the JSON API.
+async fn commits(
+    state: State<BileState>,
+    repo_name: RepoName,
+    r#ref: Option<Ref>,
+    path: Option<ObjectName>,
+    params: Params,
+) -> Response {
+    // the API only lists a single ref by date, so a page of anything else can't be given
+    if params.all || params.before.is_some() || params.order != Order::Time {
+        return ErrorPage::from(&*state)
+            .with_status(StatusCode::BAD_REQUEST)
+            .into_response();
+    }
+
+    let params = api::commits::Params {
+        r#ref,
+        path,
+        cursor: params.cursor,
+    };
+
+    api::commits::get(state, Path(repo_name), Query(params)).await
+}
+
+/// The log of a ref and path, and its RSS and Atom feeds.
+fn urls(
+    repo_name: &RepoName,
+    r#ref: Option<&Ref>,
+    object_name: Option<&ObjectName>,
+) -> (String, String, String) {
+    match (r#ref, object_name) {
+        (Some(r#ref), Some(path)) => {
+            let log = format!("/{repo_name}/log/{ref}/{}", path.0.trim_matches('/'));
+            let (rss, atom) = (format!("{log}/feed.xml"), format!("{log}/feed.atom"));
+            (log, rss, atom)
+        }
+        (Some(r#ref), None) => {
+            let log = format!("/{repo_name}/log/{ref}");
+            let (rss, atom) = (format!("{log}/feed.xml"), format!("{log}/feed.atom"));
+            (log, rss, atom)
+        }
+        (None, _) => {
+            let log = format!("/{repo_name}/log");
+            let (rss, atom) = (format!("{log}.xml"), format!("{log}.atom"));
+            (log, rss, atom)
+        }
+    }
+}
+
+/// The page of the log to show, the first one if the requested page is gone.
+fn page<'r>(
+    repo: &'r Repository,
+    tips: Tips<'_>,
+    per_page: usize,
+    object_name: Option<&ObjectName>,
+    before: Option<Oid>,
+    cursor: Option<&Cursor>,
+    order: Order,
+) -> Result<Option<Page<'r>>> {
+    let page = match before {
+        Some(before) => repo
+            .commits_before_obj(tips, per_page, object_name, before, order)
+            .context("failed to get commits before commit")?,
+        None => None,
+    };
+
+    // a commit that is not part of the history anymore starts over at the newest commits
+    page.filter(|page| !page.commits.is_empty()).map_or_else(
+        || {
+            repo.commits_for_obj(tips, per_page, object_name, cursor, order)
+                .context("failed to get commits for object")
+        },
+        |page| Ok(Some(page)),
+    )
+}
+
 fn inner(
     state: &BileState,
     repo_name: &RepoName,
     r#ref: Option<&Ref>,
     object_name: Option<&ObjectName>,
+    params: &Params,
 ) -> Result<Response> {
-    let Some(repo) = Repository::open(&state.config, repo_name).context("opening repository")?
-    else {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
         return Ok(ErrorPage::from(state)
             .with_status(StatusCode::NOT_FOUND)
             .into_response());
@@ -75,44 +307,104 @@ fn inner(
     }
 
     let r = r#ref.map_or("HEAD", |r| r.0.as_str());
+    let tips = if params.all { Tips::All } else { Tips::Spec(r) };
 
-    let next_page_spec = if repo.is_shallow() {
-        String::new()
-    } else if let Some(i) = r.rfind('~') {
-        // there is a tilde, try to find a number too
-        let n = r[i + 1..].parse::<usize>().ok().unwrap_or(1);
-        format!("{}~{}", &r[..i], n + state.config.log_per_page)
-    } else {
-        // there was no tilde
-        format!("{}~{}", r, state.config.log_per_page)
+    let Some((before, cursor)) = params.cursors(&repo) else {
+        return Ok(ErrorPage::from(state)
+            .with_status(StatusCode::BAD_REQUEST)
+            .into_response());
+    };
+
+    let view = View {
+        all: params.all,
+        // the graph only makes sense for the whole history
+        graph: params.graph && object_name.is_none(),
+        order: params.order,
     };
 
-    let Some(mut commits) = repo
-        .commits_for_obj(r, state.config.log_per_page + 1, object_name)
-        .context("failed to get commits for object")?
+    let per_page = state.config.log_per_page;
+    let Some(page) = page(
+        &repo,
+        tips,
+        per_page,
+        object_name,
+        before,
+        cursor.as_ref(),
+        view.walk_order(),
+    )?
     else {
         return Ok(ErrorPage::from(state)
             .with_status(StatusCode::NOT_FOUND)
             .into_response());
     };
 
-    // check if there even is a next page
-    let next_page = if commits.len() < state.config.log_per_page + 1 {
-        None
+    let (base_url, rss_url, atom_url) = urls(repo_name, r#ref, object_name);
+
+    let prev_page = page
+        .commits
+        .first()
+        .filter(|_| page.has_prev)
+        .map(|commit| view.url(&base_url, Some(("before", &Cursor::at(commit.id())))));
+    let next_page = page
+        .next
+        .as_ref()
+        .map(|next| view.url(&base_url, Some(("cursor", next))));
+
+    let graph_toggle = object_name.is_none().then(|| {
+        View {
+            graph: !view.graph,
+            ..view
+        }
+        .url(&base_url, None)
+    });
+    let order_toggle = View {
+        order: view.order.other(),
+        ..view
+    }
+    .url(&base_url, None);
+
+    let branch = if view.all {
+        "all branches and tags".to_string()
     } else {
-        // remove additional commit from next page check
-        commits.pop();
-        Some(next_page_spec)
+        repo.ref_or_head_shorthand(r#ref)?
     };
 
-    let branch = repo.ref_or_head_shorthand(r#ref)?;
+    // switching to another ref keeps showing the same path
+    let item =
+        object_name.map_or_else(String::new, |path| format!("/{}", path.0.trim_matches('/')));
+    let switcher = RefSwitcher::new(
+        &repo,
+        branch.clone(),
+        format!("/{repo_name}/log/"),
+        format!("{item}{}", View { all: false, ..view }.query(None)),
+    )?
+    .with_all(View { all: true, ..view }.url(&base_url, None));
+
+    let revision = page
+        .tips
+        .iter()
+        .copied()
+        .collect::<Revision>()
+        .named_by([r])
+        .on_page(&repo);
 
-    Ok(Html(RepoLogTemplate {
-        config: &state.config,
-        repo: &repo,
-        commits,
-        branch,
-        next_page,
-    })
-    .into_response())
+    Ok((
+        revision,
+        Html(RepoLogTemplate {
+            config: &state.config,
+            repo: &repo,
+            commits: page.commits,
+            graph: view.graph.then_some(page.graph),
+            branch,
+            switcher,
+            prev_page,
+            next_page,
+            graph_toggle,
+            order_toggle,
+            order: view.order,
+            rss_url,
+            atom_url,
+        }),
+    )
+        .into_response())
 }
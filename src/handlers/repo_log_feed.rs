@@ -3,32 +3,85 @@ use axum::{
     http::StatusCode,
     response::{IntoResponse as _, Response},
 };
+use git2::DiffStatsFormat;
 
 use crate::{
     BileState,
     error::{Context as _, Result},
-    git::Repository,
+    git::{Order, Repository, Tips},
     http::{
-        extractor::{Ref, RepoName},
+        extractor::{ObjectName, Ref, RepoName},
         path::Path,
-        response::{ErrorPage, Xml},
+        pool::Cost,
+        response::{Atom, ErrorPage, Xml},
     },
     utils::filters,
 };
 
+/// Width the diffstat of feed entries is wrapped to.
+const DIFFSTAT_WIDTH: usize = 72;
+
+/// The formats feeds are offered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Feed {
+    Rss,
+    Atom,
+}
+
+impl Feed {
+    /// Splits the feed off a path ending in `/feed.xml` or `/feed.atom`, which
+    /// asks for the feed of the path before it.
+    pub(crate) fn split(object_name: &ObjectName) -> Option<(Self, ObjectName)> {
+        let path = object_name.0.trim_end_matches('/');
+
+        let (feed, path) = match (
+            path.strip_suffix("/feed.xml"),
+            path.strip_suffix("/feed.atom"),
+        ) {
+            (Some(path), _) => (Self::Rss, path),
+            (_, Some(path)) => (Self::Atom, path),
+            (None, None) => return None,
+        };
+
+        let path = path.trim_matches('/');
+
+        (!path.is_empty()).then(|| (feed, ObjectName(path.to_string())))
+    }
+}
+
 #[derive(askama::Template)]
 #[template(path = "log.xml")]
 struct RepoLogFeedTemplate<'a> {
     repo: &'a Repository,
     commits: Vec<git2::Commit<'a>>,
     branch: String,
+    path: Option<&'a str>,
+    base_url: &'a str,
+}
+
+#[derive(askama::Template)]
+#[template(path = "log.atom", escape = "html")]
+struct RepoLogAtomTemplate<'a> {
+    repo: &'a Repository,
+    entries: Vec<Entry<'a>>,
+    branch: String,
+    path: Option<&'a str>,
     base_url: &'a str,
+    feed_url: &'a str,
+}
+
+struct Entry<'a> {
+    commit: git2::Commit<'a>,
+    diffstat: String,
 }
 
 #[tracing::instrument(skip_all)]
 pub(crate) async fn get_1(state: State<BileState>, Path(repo_name): Path<RepoName>) -> Response {
     state
-        .spawn(move |state| inner(&state, &repo_name, None))
+        .spawn(Cost::Heavy, move |state| {
+            inner(&state, Feed::Rss, &repo_name, None, None)
+        })
         .await
 }
 
@@ -38,13 +91,61 @@ pub(crate) async fn get_2(
     Path((repo_name, r#ref)): Path<(RepoName, Ref)>,
 ) -> Response {
     state
-        .spawn(move |state| inner(&state, &repo_name, Some(&r#ref)))
+        .spawn(Cost::Heavy, move |state| {
+            inner(&state, Feed::Rss, &repo_name, Some(&r#ref), None)
+        })
         .await
 }
 
-fn inner(state: &BileState, repo_name: &RepoName, r#ref: Option<&Ref>) -> Result<Response> {
-    let Some(repo) = Repository::open(&state.config, repo_name).context("opening repository")?
-    else {
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get_atom_1(
+    state: State<BileState>,
+    Path(repo_name): Path<RepoName>,
+) -> Response {
+    state
+        .spawn(Cost::Heavy, move |state| {
+            inner(&state, Feed::Atom, &repo_name, None, None)
+        })
+        .await
+}
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get_atom_2(
+    state: State<BileState>,
+    Path((repo_name, r#ref)): Path<(RepoName, Ref)>,
+) -> Response {
+    state
+        .spawn(Cost::Heavy, move |state| {
+            inner(&state, Feed::Atom, &repo_name, Some(&r#ref), None)
+        })
+        .await
+}
+
+/// Feed of the commits changing a path, served from behind `repo_log::get_3`
+/// as `log/{ref}/{path}/feed.xml` and `log/{ref}/{path}/feed.atom`.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get_3(
+    state: State<BileState>,
+    feed: Feed,
+    repo_name: RepoName,
+    r#ref: Ref,
+    object_name: ObjectName,
+) -> Response {
+    state
+        .spawn(Cost::Heavy, move |state| {
+            inner(&state, feed, &repo_name, Some(&r#ref), Some(&object_name))
+        })
+        .await
+}
+
+fn inner(
+    state: &BileState,
+    feed: Feed,
+    repo_name: &RepoName,
+    r#ref: Option<&Ref>,
+    object_name: Option<&ObjectName>,
+) -> Result<Response> {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
         return Ok(ErrorPage::from(state)
             .with_status(StatusCode::NOT_FOUND)
             .into_response());
@@ -59,19 +160,65 @@ fn inner(state: &BileState, repo_name: &RepoName, r#ref: Option<&Ref>) -> Result
 
     let r = r#ref.map_or("HEAD", |r| r.0.as_str());
 
-    let Some(commits) = repo.commits(r, state.config.log_per_page)? else {
+    let Some(page) = repo
+        .commits_for_obj(
+            Tips::Spec(r),
+            state.config.log_per_page,
+            object_name,
+            None,
+            Order::Time,
+        )
+        .context("failed to get commits for object")?
+    else {
         return Ok(ErrorPage::from(state)
             .with_status(StatusCode::NOT_FOUND)
             .into_response());
     };
 
     let branch = repo.ref_or_head_shorthand(r#ref)?;
+    let base_url = format!("{}/{repo_name}", state.config.base_url);
+    let path = object_name.map(|name| name.0.trim_matches('/'));
+
+    if feed == Feed::Rss {
+        return Ok(Xml(RepoLogFeedTemplate {
+            repo: &repo,
+            commits: page.commits,
+            branch,
+            path,
+            base_url: &base_url,
+        })
+        .into_response());
+    }
+
+    let feed_url = match (r#ref, path) {
+        (Some(r#ref), Some(path)) => format!("{base_url}/log/{ref}/{path}/feed.atom"),
+        (Some(r#ref), None) => format!("{base_url}/log/{ref}/feed.atom"),
+        (None, _) => format!("{base_url}/log.atom"),
+    };
 
-    Ok(Xml(RepoLogFeedTemplate {
+    Ok(Atom(RepoLogAtomTemplate {
+        entries: entries(&repo, page.commits)?,
         repo: &repo,
-        commits,
         branch,
-        base_url: &format!("/{repo_name}"),
+        path,
+        base_url: &base_url,
+        feed_url: &feed_url,
     })
     .into_response())
 }
+
+/// Pairs every commit with its diffstat, as shown by `git show --stat`.
+fn entries<'a>(repo: &Repository, commits: Vec<git2::Commit<'a>>) -> Result<Vec<Entry<'a>>> {
+    commits
+        .into_iter()
+        .map(|commit| {
+            let stats = repo.commit_stats(&commit)?;
+            let diffstat = stats.to_buf(DiffStatsFormat::FULL, DIFFSTAT_WIDTH)?;
+
+            Ok(Entry {
+                diffstat: String::from_utf8_lossy(&diffstat).into_owned(),
+                commit,
+            })
+        })
+        .collect()
+}
@@ -9,9 +9,12 @@ use crate::{
     config::Config,
     error::{Context as _, Result},
     git::{Repository, TagEntry},
+    handlers::api,
     http::{
         extractor::RepoName,
+        negotiate::Format,
         path::Path,
+        pool::Cost,
         response::{ErrorPage, Html, Redirect},
     },
     utils::filters,
@@ -27,14 +30,23 @@ struct RepoRefTemplate<'a> {
 }
 
 #[tracing::instrument(skip_all)]
-pub(crate) async fn get(state: State<BileState>, Path(repo_name): Path<RepoName>) -> Response {
-    state.spawn(move |state| inner(&state, &repo_name)).await
+pub(crate) async fn get(
+    state: State<BileState>,
+    format: Format,
+    Path(repo_name): Path<RepoName>,
+) -> Response {
+    if format == Format::Json {
+        return api::refs::get(state, Path(repo_name)).await;
+    }
+
+    state
+        .spawn(Cost::Light, move |state| inner(&state, &repo_name))
+        .await
 }
 
 #[tracing::instrument(skip_all)]
 fn inner(state: &BileState, repo_name: &RepoName) -> Result<Response> {
-    let Some(repo) = Repository::open(&state.config, repo_name).context("opening repository")?
-    else {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
         return Ok(ErrorPage::from(state)
             .with_status(StatusCode::NOT_FOUND)
             .into_response());
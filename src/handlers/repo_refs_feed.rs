@@ -7,11 +7,13 @@ use axum::{
 use crate::{
     BileState,
     error::{Context as _, Result},
-    git::{Repository, TagEntry},
+    git::{Release, Repository},
+    handlers::{repo_log_feed::Feed, repo_tag},
     http::{
         extractor::RepoName,
         path::Path,
-        response::{ErrorPage, Xml},
+        pool::Cost,
+        response::{Atom, ErrorPage, Xml},
     },
     utils::filters,
 };
@@ -20,19 +22,45 @@ use crate::{
 #[template(path = "refs.xml")]
 struct RepoRefFeedTemplate<'a> {
     repo: &'a Repository,
-    tags: Vec<TagEntry>,
+    entries: Vec<Entry>,
     base_url: &'a str,
 }
 
+#[derive(askama::Template)]
+#[template(path = "refs.atom", escape = "html")]
+struct RepoRefAtomTemplate<'a> {
+    repo: &'a Repository,
+    entries: Vec<Entry>,
+    base_url: &'a str,
+}
+
+struct Entry {
+    release: Release,
+    /// Rendered notes and links of the release
+    html: String,
+}
+
 #[tracing::instrument(skip_all)]
 pub(crate) async fn get(state: State<BileState>, Path(repo_name): Path<RepoName>) -> Response {
-    state.spawn(move |state| inner(&state, &repo_name)).await
+    state
+        .spawn(Cost::Light, move |state| {
+            inner(&state, Feed::Rss, &repo_name)
+        })
+        .await
+}
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get_atom(state: State<BileState>, Path(repo_name): Path<RepoName>) -> Response {
+    state
+        .spawn(Cost::Light, move |state| {
+            inner(&state, Feed::Atom, &repo_name)
+        })
+        .await
 }
 
 #[tracing::instrument(skip_all)]
-fn inner(state: &BileState, repo_name: &RepoName) -> Result<Response> {
-    let Some(repo) = Repository::open(&state.config, repo_name).context("opening repository")?
-    else {
+fn inner(state: &BileState, feed: Feed, repo_name: &RepoName) -> Result<Response> {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
         return Ok(ErrorPage::from(state)
             .with_status(StatusCode::NOT_FOUND)
             .into_response());
@@ -45,15 +73,31 @@ fn inner(state: &BileState, repo_name: &RepoName) -> Result<Response> {
             .into_response());
     }
 
-    let mut tags = repo.tag_entries()?;
+    let base_url = format!("{}/{repo_name}", state.config.base_url);
 
-    // sort so that newest tags are at the top
-    tags.sort_unstable_by(|a, b| a.signature.when().cmp(&b.signature.when()).reverse());
+    let entries = repo
+        .releases()?
+        .into_iter()
+        .map(|release| {
+            Ok(Entry {
+                html: repo_tag::release_html(state, &base_url, &release)?,
+                release,
+            })
+        })
+        .collect::<Result<_>>()?;
 
-    Ok(Xml(RepoRefFeedTemplate {
-        repo: &repo,
-        tags,
-        base_url: &format!("/{repo_name}"),
+    Ok(match feed {
+        Feed::Rss => Xml(RepoRefFeedTemplate {
+            repo: &repo,
+            entries,
+            base_url: &base_url,
+        })
+        .into_response(),
+        Feed::Atom => Atom(RepoRefAtomTemplate {
+            repo: &repo,
+            entries,
+            base_url: &base_url,
+        })
+        .into_response(),
     })
-    .into_response())
 }
@@ -1,3 +1,4 @@
+use askama::Template as _;
 use axum::{
     extract::State,
     http::StatusCode,
@@ -8,13 +9,14 @@ use crate::{
     BileState,
     config::Config,
     error::{Context as _, Result},
-    git::Repository,
+    git::{Release, Repository},
     http::{
         extractor::{RepoName, Tag},
         path::Path,
+        pool::Cost,
         response::{ErrorPage, Html, Redirect},
     },
-    utils::filters,
+    utils::{filters, markdown},
 };
 
 #[derive(askama::Template)]
@@ -23,6 +25,16 @@ struct Template<'a> {
     config: &'a Config,
     repo: &'a Repository,
     tag: git2::Tag<'a>,
+    release: String,
+}
+
+#[derive(askama::Template)]
+#[template(path = "release.html")]
+struct ReleaseTemplate<'a> {
+    notes: &'a str,
+    tag: &'a str,
+    previous: Option<&'a str>,
+    base_url: &'a str,
 }
 
 #[tracing::instrument(skip_all)]
@@ -31,14 +43,13 @@ pub(crate) async fn get(
     Path((repo_name, tag)): Path<(RepoName, Tag)>,
 ) -> Response {
     state
-        .spawn(move |state| inner(&state, &repo_name, &tag))
+        .spawn(Cost::Light, move |state| inner(&state, &repo_name, &tag))
         .await
 }
 
 #[tracing::instrument(skip_all)]
 fn inner(state: &BileState, repo_name: &RepoName, tag: &Tag) -> Result<Response> {
-    let Some(repo) = Repository::open(&state.config, repo_name).context("opening repository")?
-    else {
+    let Some(repo) = Repository::open(state, repo_name).context("opening repository")? else {
         return Ok(ErrorPage::from(state)
             .with_status(StatusCode::NOT_FOUND)
             .into_response());
@@ -50,10 +61,48 @@ fn inner(state: &BileState, repo_name: &RepoName, tag: &Tag) -> Result<Response>
             .into_response());
     };
 
+    let release = match repo
+        .releases()?
+        .iter()
+        .find(|release| release.tag.tag == tag.0)
+    {
+        Some(release) => release_html(state, &format!("/{repo_name}"), release)?,
+        None => String::new(),
+    };
+
     Ok(Html(Template {
         config: &state.config,
         repo: &repo,
         tag: repo_tag,
+        release,
     })
     .into_response())
 }
+
+/// Renders the notes of a release with links to its archive and changes.
+pub(crate) fn release_html(state: &BileState, base_url: &str, release: &Release) -> Result<String> {
+    let html = ReleaseTemplate {
+        notes: &markdown::render(
+            &state.syntax,
+            &state.config.markdown,
+            strip_signature(&release.tag.message),
+        ),
+        tag: &release.tag.tag,
+        previous: release.previous.as_deref(),
+        base_url,
+    }
+    .render()?;
+
+    Ok(html)
+}
+
+/// Cuts the signature off the message of a signed tag.
+fn strip_signature(message: &str) -> &str {
+    [
+        "-----BEGIN PGP SIGNATURE-----",
+        "-----BEGIN SSH SIGNATURE-----",
+    ]
+    .iter()
+    .find_map(|marker| message.find(marker))
+    .map_or(message, |idx| &message[..idx])
+}
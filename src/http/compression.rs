@@ -0,0 +1,194 @@
+//! Compressed responses, negotiated with `Accept-Encoding`.
+
+use std::{cmp::Ordering, convert::Infallible};
+
+use axum::{
+    extract::{FromRequestParts, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{Extensions, HeaderMap, HeaderValue, StatusCode, Version, header, request::Parts};
+use tower_http::compression::{CompressionLayer, DefaultPredicate, Predicate};
+
+/// Types that are compressed already, or that do not get any smaller.
+const COMPRESSED: [&str; 11] = [
+    "application/gzip",
+    "application/pdf",
+    "application/x-7z-compressed",
+    "application/x-bzip2",
+    "application/x-rar-compressed",
+    "application/x-xz",
+    "application/zip",
+    "application/zstd",
+    "audio/",
+    "font/woff",
+    "video/",
+];
+
+/// Compresses pages and blobs, except for images and the types in
+/// [`COMPRESSED`].
+pub(crate) fn layer() -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new().compress_when(DefaultPredicate::new().and(compressible))
+}
+
+fn compressible(_: StatusCode, _: Version, headers: &HeaderMap, _: &Extensions) -> bool {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    !COMPRESSED
+        .iter()
+        .any(|compressed| content_type.starts_with(compressed))
+}
+
+/// The `ETag` of a response is the same for every encoding, so it has to be
+/// weak once the response is compressed.
+///
+/// A `304 Not Modified` keeps the form of the tag the client sent.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn weaken_etags(request: Request, next: Next) -> Response {
+    let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+
+    let mut response = next.run(request).await;
+
+    let not_modified = response.status() == StatusCode::NOT_MODIFIED;
+    let compressed = response.headers().contains_key(header::CONTENT_ENCODING);
+
+    let Some(etag) = response
+        .headers()
+        .get(header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"))
+    else {
+        return response;
+    };
+
+    let weak = if not_modified {
+        if_none_match
+            .as_ref()
+            .and_then(|tags| tags.to_str().ok())
+            .is_some_and(|tags| {
+                tags.split(',')
+                    .map(str::trim)
+                    .any(|tag| tag.strip_prefix("W/") == Some(etag))
+            })
+    } else {
+        compressed
+    };
+
+    if weak && let Ok(etag) = HeaderValue::from_str(&format!("W/{etag}")) {
+        response.headers_mut().insert(header::ETAG, etag);
+    }
+
+    response
+}
+
+/// The coding to answer with, mostly decided by the client's `Accept-Encoding`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Encoding {
+    Brotli,
+    Zstd,
+    Gzip,
+    Identity,
+}
+
+impl Encoding {
+    /// Picks the coding with the highest quality, the one compressing the
+    /// assets the most wins ties.
+    fn from_accept(accept: &str) -> Self {
+        let mut best = (Self::Identity, 0.0);
+
+        for coding in accept.split(',') {
+            let mut params = coding.split(';').map(str::trim);
+
+            let encodings: &[Self] = match params.next().unwrap_or_default() {
+                "br" => &[Self::Brotli],
+                "zstd" => &[Self::Zstd],
+                "gzip" | "x-gzip" => &[Self::Gzip],
+                "*" => &[Self::Brotli, Self::Zstd, Self::Gzip],
+                _ => continue,
+            };
+
+            let quality = params
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            for encoding in encodings {
+                let better = match quality.total_cmp(&best.1) {
+                    Ordering::Greater => true,
+                    Ordering::Equal => quality > 0.0 && *encoding < best.0,
+                    Ordering::Less => false,
+                };
+
+                if better {
+                    best = (*encoding, quality);
+                }
+            }
+        }
+
+        best.0
+    }
+
+    const fn header_value(self) -> Option<HeaderValue> {
+        match self {
+            Self::Brotli => Some(HeaderValue::from_static("br")),
+            Self::Zstd => Some(HeaderValue::from_static("zstd")),
+            Self::Gzip => Some(HeaderValue::from_static("gzip")),
+            Self::Identity => None,
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Encoding {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .headers
+            .get(header::ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .map_or(Self::Identity, Self::from_accept))
+    }
+}
+
+/// A static asset with its copies compressed by `build.rs`.
+pub(crate) struct Precompressed {
+    pub identity: &'static [u8],
+    pub brotli: &'static [u8],
+    pub zstd: &'static [u8],
+    pub gzip: &'static [u8],
+}
+
+impl Precompressed {
+    pub(crate) const fn encoded(&self, encoding: Encoding) -> Encoded {
+        let body = match encoding {
+            Encoding::Brotli => self.brotli,
+            Encoding::Zstd => self.zstd,
+            Encoding::Gzip => self.gzip,
+            Encoding::Identity => self.identity,
+        };
+
+        Encoded(encoding, body)
+    }
+}
+
+/// One of the copies of a [`Precompressed`] asset.
+pub(crate) struct Encoded(Encoding, &'static [u8]);
+
+impl IntoResponse for Encoded {
+    fn into_response(self) -> Response {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::VARY,
+            HeaderValue::from_static(header::ACCEPT_ENCODING.as_str()),
+        );
+
+        if let Some(encoding) = self.0.header_value() {
+            headers.insert(header::CONTENT_ENCODING, encoding);
+        }
+
+        (headers, self.1).into_response()
+    }
+}
@@ -0,0 +1,243 @@
+//! Caching headers for pages showing git objects, and `304 Not Modified` for
+//! clients that already have them.
+
+use std::{
+    convert::Infallible,
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+};
+
+use axum::{
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse as _, IntoResponseParts, Response, ResponseParts},
+};
+use git2::{Oid, Time};
+use http::{HeaderMap, HeaderValue, StatusCode, Uri, header, uri::PathAndQuery};
+use jiff::{Timestamp, fmt::rfc2822};
+
+use crate::{META_PACKAGE_VERSION, git::Repository, http::negotiate::Format};
+
+/// Responses to URLs naming an object by its full id, and showing nothing but
+/// the object, never change.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// Anything else can change with a push, but can be checked with the `ETag`.
+const REVALIDATE: &str = "no-cache";
+
+/// The git objects a response shows, it only changes when they do.
+///
+/// Handlers add it to their responses, [`validate`] turns it into an `ETag`
+/// and answers conditional requests.
+#[derive(Debug, Clone)]
+pub(crate) struct Revision {
+    /// The first one is the main object, the rest are hashed into the `ETag`
+    ids: Vec<Oid>,
+    /// Commit time, for `Last-Modified`
+    time: Option<Time>,
+    /// Hash of what a page shows about the repository besides the objects,
+    /// which can change with a push, so the page never is immutable
+    page: Option<u64>,
+    /// The URL named every object by its full id
+    full_ids: bool,
+}
+
+impl Revision {
+    pub(crate) fn new(id: Oid) -> Self {
+        Self {
+            ids: vec![id],
+            time: None,
+            page: None,
+            full_ids: false,
+        }
+    }
+
+    pub(crate) fn of_commit(commit: &git2::Commit<'_>) -> Self {
+        Self {
+            time: Some(commit.committer().when()),
+            ..Self::new(commit.id())
+        }
+    }
+
+    /// A blob looked up in a commit, which is only immutable when the URL named
+    /// the commit by its full id.
+    pub(crate) fn of_blob(id: Oid, commit: &git2::Commit<'_>, name: &str) -> Self {
+        Self {
+            full_ids: name == commit.id().to_string(),
+            ..Self::new(id)
+        }
+    }
+
+    /// The response shows another object as well.
+    pub(crate) fn and(mut self, id: Oid) -> Self {
+        self.ids.push(id);
+        self
+    }
+
+    /// The response is a page of the repository, which also shows its
+    /// description, and its branches and tags in badges and ref switchers.
+    pub(crate) fn on_page(mut self, repo: &Repository) -> Self {
+        let mut hasher = DefaultHasher::new();
+        repo.description().hash(&mut hasher);
+        repo.ref_tips().hash(&mut hasher);
+
+        self.page = Some(hasher.finish());
+        self
+    }
+
+    /// Marks the response as immutable if the URL named every object by its full
+    /// id, instead of a branch or an abbreviated id that could become ambiguous,
+    /// unless it is a page of the repository.
+    pub(crate) fn named_by<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut names = names.into_iter();
+
+        self.full_ids = self
+            .ids
+            .iter()
+            .all(|id| names.next().is_some_and(|name| name == id.to_string()))
+            && names.next().is_none();
+
+        self
+    }
+
+    /// Pages of the repository showing an object named by its full id can leave
+    /// out what else they show of it, and skip [`Revision::on_page`] to be
+    /// immutable too.
+    pub(crate) const fn immutable(&self) -> bool {
+        self.full_ids && self.page.is_none()
+    }
+
+    /// A strong `ETag`, the same object in another format or on another page of
+    /// a listing is a different response.
+    fn etag(&self, format: Format, uri: &Uri) -> Option<HeaderValue> {
+        let mut hasher = DefaultHasher::new();
+        META_PACKAGE_VERSION.hash(&mut hasher);
+        self.ids.hash(&mut hasher);
+        self.page.hash(&mut hasher);
+        format.hash(&mut hasher);
+        uri.path_and_query()
+            .map(PathAndQuery::as_str)
+            .hash(&mut hasher);
+
+        let id = self.ids.first()?;
+
+        HeaderValue::from_str(&format!("\"{id}-{:016x}\"", hasher.finish())).ok()
+    }
+
+    fn last_modified(&self) -> Option<HeaderValue> {
+        let time = Timestamp::from_second(self.time?.seconds()).ok()?;
+
+        let date = rfc2822::DateTimePrinter::new()
+            .timestamp_to_rfc9110_string(&time)
+            .ok()?;
+
+        HeaderValue::from_str(&date).ok()
+    }
+}
+
+/// A listing walked from several commits, e.g. every branch.
+impl FromIterator<Oid> for Revision {
+    fn from_iter<I: IntoIterator<Item = Oid>>(ids: I) -> Self {
+        Self {
+            ids: ids.into_iter().collect(),
+            time: None,
+            page: None,
+            full_ids: false,
+        }
+    }
+}
+
+impl IntoResponseParts for Revision {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.extensions_mut().insert(self);
+
+        Ok(res)
+    }
+}
+
+/// Adds `ETag`, `Last-Modified` and `Cache-Control` to responses with a
+/// [`Revision`], answering with `304 Not Modified` when the client has it.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn validate(request: Request, next: Next) -> Response {
+    let format = Format::from_parts(request.uri(), request.headers());
+    let uri = request.uri().clone();
+    let conditions = request.headers().clone();
+
+    let mut response = next.run(request).await;
+
+    let Some(revision) = response.extensions_mut().remove::<Revision>() else {
+        return response;
+    };
+
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let mut headers = HeaderMap::new();
+
+    if let Some(etag) = revision.etag(format, &uri) {
+        headers.insert(header::ETAG, etag);
+    }
+
+    if let Some(last_modified) = revision.last_modified() {
+        headers.insert(header::LAST_MODIFIED, last_modified);
+    }
+
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(if revision.immutable() {
+            IMMUTABLE
+        } else {
+            REVALIDATE
+        }),
+    );
+
+    if is_fresh(&conditions, &headers, revision.immutable()) {
+        if let Some(vary) = response.headers().get(header::VARY) {
+            headers.insert(header::VARY, vary.clone());
+        }
+
+        return (StatusCode::NOT_MODIFIED, headers).into_response();
+    }
+
+    response.headers_mut().extend(headers);
+
+    response
+}
+
+/// Whether the client's copy is still the current one, `If-None-Match` wins
+/// over `If-Modified-Since` when both are sent.
+///
+/// A branch can be reset to an older commit, so the date is only trusted for
+/// immutable responses.
+fn is_fresh(conditions: &HeaderMap, headers: &HeaderMap, immutable: bool) -> bool {
+    if let Some(if_none_match) = conditions.get(header::IF_NONE_MATCH) {
+        let Some(etag) = headers.get(header::ETAG) else {
+            return false;
+        };
+
+        return if_none_match.to_str().is_ok_and(|tags| {
+            tags.split(',').map(str::trim).any(|tag| {
+                // weak comparison, as RFC 9110 asks for with `If-None-Match`
+                tag == "*" || tag.trim_start_matches("W/").as_bytes() == etag.as_bytes()
+            })
+        });
+    }
+
+    if !immutable {
+        return false;
+    }
+
+    let parse = |value: &HeaderValue| {
+        rfc2822::DateTimeParser::new()
+            .parse_timestamp(value.as_bytes())
+            .ok()
+    };
+
+    let since = conditions.get(header::IF_MODIFIED_SINCE).and_then(parse);
+    let modified = headers.get(header::LAST_MODIFIED).and_then(parse);
+
+    since
+        .zip(modified)
+        .is_some_and(|(since, modified)| modified <= since)
+}
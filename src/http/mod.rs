@@ -1,38 +1,80 @@
+pub(crate) mod compression;
+pub(crate) mod conditional;
 pub(crate) mod extractor;
+pub(crate) mod negotiate;
 pub(crate) mod path;
+pub(crate) mod pool;
+pub(crate) mod range;
 pub(crate) mod response;
+pub(crate) mod stream;
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use axum::response::{IntoResponse as _, Response};
-use http::StatusCode;
+use http::{HeaderValue, StatusCode, header};
 use syntect::parsing::SyntaxSet;
+use tower_helmet::{
+    HelmetLayer,
+    header::{ContentSecurityPolicy, CrossOriginResourcePolicy},
+};
+use tower_http::set_header::SetResponseHeaderLayer;
 
-use crate::{config::Config, error::Result, http::response::ErrorPage};
+use crate::{
+    config::Config,
+    error::Result,
+    git::{
+        activity::Activity, cache::LastCommitCache, commit_graph::CommitGraphCache,
+        metadata::RepoCache,
+    },
+    http::{
+        pool::{Cost, Pool},
+        response::ErrorPage,
+    },
+};
 
 #[derive(Clone)]
 pub(crate) struct BileState {
     pub(crate) config: Arc<Config>,
     pub(crate) syntax: Arc<SyntaxSet>,
+    pub(crate) activity: Arc<Activity>,
+    pub(crate) last_commits: Arc<LastCommitCache>,
+    pub(crate) repos: Arc<RepoCache>,
+    pub(crate) commit_graphs: Arc<CommitGraphCache>,
+    pub(crate) pool: Arc<Pool>,
 }
 
 impl BileState {
     pub(crate) fn new(config: Config, syntax: SyntaxSet) -> Self {
         Self {
+            pool: Arc::new(Pool::new(&config)),
             config: Arc::new(config),
             syntax: Arc::new(syntax),
+            activity: Arc::default(),
+            last_commits: Arc::default(),
+            repos: Arc::default(),
+            commit_graphs: Arc::default(),
         }
     }
 
-    pub(crate) async fn spawn<F>(&self, f: F) -> Response
+    /// Runs git work on a worker of the [`Pool`], answering with 503 Service
+    /// Unavailable when none becomes free in time.
+    pub(crate) async fn spawn<F>(&self, cost: Cost, f: F) -> Response
     where
         F: FnOnce(Self) -> Result<Response> + Send + 'static,
     {
         let span = tracing::Span::current();
 
+        // held until the work and the body it streams are done, even if the
+        // client stops waiting for them
+        let worker = match self.pool.acquire(cost).await {
+            Ok(worker) => worker,
+            Err(busy) => return busy.response(ErrorPage::from(self)),
+        };
+
         let this = self.clone();
 
-        spawn_blocking(move || span.in_scope(|| wrap_err(this.clone(), f(this)))).await
+        spawn_blocking(move || worker.run(|| span.in_scope(|| wrap_err(this.clone(), f(this)))))
+            .await
     }
 }
 
@@ -61,6 +103,50 @@ fn wrap_err(state: BileState, res: Result<Response>) -> Response {
     }
 }
 
+/// Security headers for the pages.
+pub(crate) fn helmet(config: &Config) -> HelmetLayer {
+    let mut layer = HelmetLayer::with_defaults();
+    layer.enable(PermissionsPolicy);
+
+    // images and media are embedded from the raw origin
+    if !config.raw_base.is_empty() {
+        let mut directives = HashMap::new();
+        directives.insert("img-src", vec!["'self'", "data:", config.raw_base.as_str()]);
+        directives.insert("media-src", vec!["'self'", config.raw_base.as_str()]);
+
+        layer.enable(ContentSecurityPolicy {
+            directives,
+            ..Default::default()
+        });
+    }
+
+    layer
+}
+
+/// Policy of the responses for raw files that don't set their own, like error pages.
+const RAW_POLICY: &str = "default-src 'none'; img-src 'self' data:; style-src 'self'; sandbox";
+
+/// Security headers for raw files, the policy of files is set by the handler.
+pub(crate) fn raw_helmet(config: &Config) -> (HelmetLayer, SetResponseHeaderLayer<HeaderValue>) {
+    let mut layer = HelmetLayer::with_defaults();
+    layer
+        .enable(PermissionsPolicy)
+        .remove(header::CONTENT_SECURITY_POLICY);
+
+    // the pages embedding them are on another origin
+    if !config.raw_base.is_empty() {
+        layer.enable(CrossOriginResourcePolicy::CrossOrigin);
+    }
+
+    (
+        layer,
+        SetResponseHeaderLayer::if_not_present(
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static(RAW_POLICY),
+        ),
+    )
+}
+
 pub(crate) struct PermissionsPolicy;
 
 impl tower_helmet::IntoHeader for PermissionsPolicy {
@@ -68,10 +154,8 @@ impl tower_helmet::IntoHeader for PermissionsPolicy {
         http::HeaderName::from_static("permissions-policy")
     }
 
-    fn header_value(
-        &self,
-    ) -> std::result::Result<http::HeaderValue, http::header::InvalidHeaderValue> {
-        http::HeaderValue::from_str(
+    fn header_value(&self) -> std::result::Result<HeaderValue, header::InvalidHeaderValue> {
+        HeaderValue::from_str(
             "accelerometer=(),ambient-light-sensor=(),attribution-reporting=(),autoplay=(),bluetooth=(),browsing-topics=(),camera=(),captured-surface-control=(),clipboard-read=(),clipboard-write=(),compute-pressure=(),display-capture=(),document-domain=(),encrypted-media=(),fullscreen=(),gamepad=(),geolocation=(),gyroscope=(),hid=(),identity-credentials-get=(),idle-detection=(),interest-cohort=(),local-fonts=(),magnetometer=(),microphone=(),midi=(),on-device-speech-recognition=(),otp-credentials=(),payment=(),picture-in-picture=(),private-state-token-issuance=(),private-state-token-redemption=(),publickey-credentials-create=(),publickey-credentials-get=(),screen-wake-lock=(),serial=(),speaker-selection=(),storage-access=(),sync-xhr=(),usb=(),web-share=(),window-management=(),xr-spatial-tracking=()",
         )
     }
@@ -0,0 +1,169 @@
+use std::{convert::Infallible, fmt::Write as _};
+
+use axum::{
+    extract::{FromRequestParts, Request},
+    middleware::Next,
+    response::{IntoResponse as _, Response},
+};
+use http::{HeaderMap, HeaderValue, StatusCode, Uri, header, request::Parts};
+
+use crate::{
+    handlers::api::schema,
+    http::{BileState, response::ApiJson},
+};
+
+/// What a client wants responses to be in, mostly decided by its `Accept` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Format {
+    Html,
+    Json,
+    Text,
+}
+
+impl Format {
+    pub(crate) fn from_parts(uri: &Uri, headers: &HeaderMap) -> Self {
+        // git shows the body of failed requests as is, it is told apart by the path
+        // rather than its user agent so responses only vary by `Accept`
+        if is_git(uri.path()) {
+            return Self::Text;
+        }
+
+        if uri.path().starts_with("/api/") {
+            return Self::Json;
+        }
+
+        headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .map_or(Self::Html, Self::from_accept)
+    }
+
+    /// Picks the format with the highest quality, the earliest one wins ties.
+    fn from_accept(accept: &str) -> Self {
+        let mut best = (Self::Html, 0.0);
+
+        for range in accept.split(',') {
+            let mut params = range.split(';').map(str::trim);
+
+            let format = match params.next().unwrap_or_default() {
+                "text/html" | "application/xhtml+xml" | "text/*" | "*/*" => Self::Html,
+                "application/json" => Self::Json,
+                "text/plain" => Self::Text,
+                _ => continue,
+            };
+
+            let quality = params
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if quality > best.1 {
+                best = (format, quality);
+            }
+        }
+
+        best.0
+    }
+}
+
+/// Whether a path is one git clients fetch a repository from, like `/{repo}/info/refs`.
+fn is_git(path: &str) -> bool {
+    let mut segments = path.trim_start_matches('/').splitn(3, '/').skip(1);
+
+    matches!(
+        (segments.next(), segments.next()),
+        (Some("info"), Some("refs")) | (Some("HEAD"), None) | (Some("objects"), Some(_))
+    )
+}
+
+impl FromRequestParts<BileState> for Format {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &BileState) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_parts(&parts.uri, &parts.headers))
+    }
+}
+
+/// Marks a response as an error page so it can be rendered in another format,
+/// with the message it shows if it has one.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IsError(pub Option<&'static str>);
+
+/// Renders error pages as JSON or plain text for clients that do not want HTML.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn errors(request: Request, next: Next) -> Response {
+    let format = Format::from_parts(request.uri(), request.headers());
+
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let response = next.run(request).await;
+
+    let mut response = if let Some(&IsError(message)) = response.extensions().get::<IsError>() {
+        render_error(format, response.status(), message, request_id, response)
+    } else {
+        response
+    };
+
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept"));
+
+    response
+}
+
+fn render_error(
+    format: Format,
+    status: StatusCode,
+    message: Option<&str>,
+    request_id: Option<String>,
+    response: Response,
+) -> Response {
+    let message = message
+        .or_else(|| status.canonical_reason())
+        .unwrap_or_default()
+        .to_string();
+    // tells clients turned away when busy when to come back
+    let retry_after = response.headers().get(header::RETRY_AFTER).cloned();
+
+    let mut rendered = match format {
+        Format::Html => response,
+        Format::Json => (
+            status,
+            ApiJson(schema::Error {
+                status: status.as_u16(),
+                message,
+                request_id,
+            }),
+        )
+            .into_response(),
+        Format::Text => {
+            let mut text = format!("{} {message}\n", status.as_u16());
+
+            if let Some(request_id) = request_id {
+                let _ = writeln!(text, "request id: {request_id}");
+            }
+
+            (
+                status,
+                [(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static(mime::TEXT_PLAIN_UTF_8.as_ref()),
+                )],
+                text,
+            )
+                .into_response()
+        }
+    };
+
+    if let Some(retry_after) = retry_after {
+        rendered
+            .headers_mut()
+            .insert(header::RETRY_AFTER, retry_after);
+    }
+
+    rendered
+}
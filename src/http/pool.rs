@@ -0,0 +1,183 @@
+//! Limits how many requests do git work at once, so a burst of requests
+//! queues instead of starting a thread for each of them.
+
+use std::{
+    cell::RefCell,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::response::{IntoResponse, Response};
+use http::{HeaderValue, StatusCode, header};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{config::Config, http::response::ErrorPage};
+
+/// How much of the pool a route takes up while it runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Cost {
+    /// Looking up a few objects, like a tree, a blob or the refs.
+    Light,
+    /// Walking history, diffing trees or going through every repository,
+    /// which can take seconds on large repositories.
+    Heavy,
+}
+
+impl Cost {
+    const fn permits(self) -> u32 {
+        match self {
+            Self::Light => 1,
+            Self::Heavy => 4,
+        }
+    }
+}
+
+pub(crate) struct Pool {
+    workers: Arc<Semaphore>,
+    /// Permits of all the workers, a request never needs more than that
+    size: u32,
+    queued: AtomicUsize,
+    max_queued: usize,
+    queue_timeout: Duration,
+    send_timeout: Duration,
+}
+
+impl Pool {
+    pub(crate) fn new(config: &Config) -> Self {
+        let workers = config.max_workers.clamp(1, Semaphore::MAX_PERMITS);
+
+        Self {
+            workers: Arc::new(Semaphore::new(workers)),
+            size: u32::try_from(workers).unwrap_or(u32::MAX),
+            queued: AtomicUsize::new(0),
+            max_queued: config.max_queued,
+            queue_timeout: Duration::from_secs(config.queue_timeout),
+            send_timeout: Duration::from_secs(config.send_timeout),
+        }
+    }
+
+    /// Waits for a worker, unless the queue is full or the wait takes too
+    /// long.
+    #[tracing::instrument(skip(self))]
+    pub(crate) async fn acquire(&self, cost: Cost) -> Result<Worker, Busy> {
+        let permits = cost.permits().min(self.size);
+
+        if let Ok(permit) = Arc::clone(&self.workers).try_acquire_many_owned(permits) {
+            return Ok(Worker {
+                _permit: Arc::new(permit),
+                send_timeout: self.send_timeout,
+            });
+        }
+
+        let queued = Queued::join(&self.queued);
+
+        if queued.ahead >= self.max_queued {
+            tracing::warn!("queue is full, turning request away");
+
+            return Err(Busy(self.queue_timeout));
+        }
+
+        // the semaphore is never closed, so only the wait can fail
+        if let Ok(Ok(permit)) = tokio::time::timeout(
+            self.queue_timeout,
+            Arc::clone(&self.workers).acquire_many_owned(permits),
+        )
+        .await
+        {
+            return Ok(Worker {
+                _permit: Arc::new(permit),
+                send_timeout: self.send_timeout,
+            });
+        }
+
+        tracing::warn!("no worker became free in time, turning request away");
+
+        Err(Busy(self.queue_timeout))
+    }
+}
+
+thread_local! {
+    /// The worker the work on this thread is done for.
+    static CURRENT: RefCell<Option<Worker>> = const { RefCell::new(None) };
+}
+
+/// Permits taken from the pool, given back once the work and every body it
+/// sends are done.
+#[derive(Clone)]
+pub(crate) struct Worker {
+    _permit: Arc<OwnedSemaphorePermit>,
+    send_timeout: Duration,
+}
+
+impl Worker {
+    /// Runs the work on this thread, bodies created by it take the worker
+    /// along with [`Worker::current`].
+    pub(crate) fn run<R>(self, f: impl FnOnce() -> R) -> R {
+        /// Leaves the worker when the work is done or panics.
+        struct Reset;
+
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                CURRENT.with_borrow_mut(Option::take);
+            }
+        }
+
+        CURRENT.with_borrow_mut(|current| *current = Some(self));
+        let _reset = Reset;
+
+        f()
+    }
+
+    /// The worker of the work running on this thread.
+    pub(crate) fn current() -> Option<Self> {
+        CURRENT.with_borrow(Clone::clone)
+    }
+
+    /// How long a body waits for a client to take more of it before the
+    /// body is cut off, so the client can't keep the worker.
+    pub(crate) const fn send_timeout(&self) -> Duration {
+        self.send_timeout
+    }
+}
+
+/// A place in the queue, left when the request gets a worker, gives up or is
+/// dropped as the client went away.
+struct Queued<'p> {
+    queued: &'p AtomicUsize,
+    /// Requests that were queued before this one
+    ahead: usize,
+}
+
+impl<'p> Queued<'p> {
+    fn join(queued: &'p AtomicUsize) -> Self {
+        Self {
+            queued,
+            ahead: queued.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// All workers are busy, the client should come back after the queue moved on.
+#[derive(Debug)]
+pub(crate) struct Busy(Duration);
+
+impl Busy {
+    pub(crate) fn response(self, page: ErrorPage) -> Response {
+        let retry_after = HeaderValue::from(self.0.as_secs().max(1));
+
+        (
+            [(header::RETRY_AFTER, retry_after)],
+            page.with_status(StatusCode::SERVICE_UNAVAILABLE),
+        )
+            .into_response()
+    }
+}
@@ -0,0 +1,215 @@
+//! Blobs and files sent while they are read, and the part of them asked for
+//! with `Range`.
+
+use std::{
+    fs::File,
+    io::{Read as _, Seek as _, SeekFrom},
+    ops::Range,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use axum::{
+    body::Body,
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{HeaderMap, HeaderValue, Method, StatusCode, header};
+
+use crate::{
+    error::{Context as _, Result},
+    http::{pool::Worker, stream},
+};
+
+/// Bytes read from a file at once.
+const CHUNK: usize = 64 * 1024;
+
+/// Reads the bytes in a range, handing them to the callback until it returns
+/// `false` as the client went away.
+type Reader = dyn Fn(Range<u64>, &mut dyn FnMut(&[u8]) -> bool) -> Result<()> + Send + Sync;
+
+/// A body read on a blocking thread while it is sent, which can start
+/// anywhere to answer `Range` requests.
+#[derive(Clone)]
+pub(crate) struct Streamed {
+    len: u64,
+    reader: Arc<Reader>,
+    /// The worker of the request, the range of the body is read on it too
+    worker: Option<Worker>,
+}
+
+impl Streamed {
+    pub(crate) fn new(
+        len: u64,
+        reader: impl Fn(Range<u64>, &mut dyn FnMut(&[u8]) -> bool) -> Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            len,
+            reader: Arc::new(reader),
+            worker: Worker::current(),
+        }
+    }
+
+    /// A file, which is expected to keep its length while it is sent.
+    pub(crate) fn file(path: PathBuf, len: u64) -> Self {
+        Self::new(len, move |range, write| {
+            let mut file = File::open(&path).context("failed to open file")?;
+            file.seek(SeekFrom::Start(range.start))?;
+
+            let mut file = file.take(range.end - range.start);
+            let mut buf = vec![0; CHUNK];
+
+            loop {
+                let read = file.read(&mut buf)?;
+
+                if read == 0 || !write(&buf[..read]) {
+                    return Ok(());
+                }
+            }
+        })
+    }
+
+    /// Starts reading once the body is first polled, so a body that is
+    /// replaced by a range of it never reads anything.
+    fn body(&self, range: Range<u64>) -> Body {
+        let reader = Arc::clone(&self.reader);
+
+        stream::body(
+            self.worker.clone(),
+            Box::new(move |write| reader(range, write)),
+        )
+    }
+}
+
+impl IntoResponse for Streamed {
+    fn into_response(self) -> Response {
+        let mut response = self.body(0..self.len).into_response();
+
+        let headers = response.headers_mut();
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(self.len));
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+        response.extensions_mut().insert(self);
+
+        response
+    }
+}
+
+/// Answers `Range` requests for [`Streamed`] responses with the part asked for.
+///
+/// Runs outside of [`super::conditional::validate`], so `If-Range` can be
+/// checked against the `ETag` it adds.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn ranges(request: Request, next: Next) -> Response {
+    let conditions = (request.method() == Method::GET).then(|| request.headers().clone());
+
+    let mut response = next.run(request).await;
+
+    let Some(streamed) = response.extensions_mut().remove::<Streamed>() else {
+        return response;
+    };
+
+    let Some(conditions) = conditions else {
+        return response;
+    };
+
+    if response.status() != StatusCode::OK || !if_range(&conditions, response.headers()) {
+        return response;
+    }
+
+    let Some(range) = conditions
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return response;
+    };
+
+    let range = match parse(range, streamed.len) {
+        Some(Ok(range)) => range,
+        Some(Err(Unsatisfiable)) => {
+            return (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", streamed.len))],
+            )
+                .into_response();
+        }
+        // a range that can not be understood is ignored
+        None => return response,
+    };
+
+    let content_range = format!("bytes {}-{}/{}", range.start, range.end - 1, streamed.len);
+    let len = range.end - range.start;
+
+    *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+    *response.body_mut() = streamed.body(range);
+
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+    if let Ok(content_range) = HeaderValue::from_str(&content_range) {
+        headers.insert(header::CONTENT_RANGE, content_range);
+    }
+
+    response
+}
+
+/// Whether the client's copy is the one being sent, so a part of it can be
+/// sent instead of all of it.
+///
+/// `If-Range` needs a strong match, either the exact `ETag` or the exact
+/// `Last-Modified` date.
+fn if_range(conditions: &HeaderMap, headers: &HeaderMap) -> bool {
+    let Some(if_range) = conditions.get(header::IF_RANGE) else {
+        return true;
+    };
+
+    let validator = if if_range.as_bytes().starts_with(b"\"") {
+        headers.get(header::ETAG)
+    } else {
+        headers.get(header::LAST_MODIFIED)
+    };
+
+    validator.is_some_and(|validator| validator == if_range)
+}
+
+/// The range starts after the end of the body.
+#[derive(Debug)]
+struct Unsatisfiable;
+
+/// A single range of `bytes`, `None` for anything else, which is then served
+/// whole.
+fn parse(range: &str, len: u64) -> Option<Result<Range<u64>, Unsatisfiable>> {
+    let (first, last) = range.trim().strip_prefix("bytes=")?.split_once('-')?;
+
+    let (first, last) = (first.trim(), last.trim());
+
+    if first.is_empty() {
+        // the last bytes of the body
+        let suffix: u64 = last.parse().ok()?;
+
+        if suffix == 0 || len == 0 {
+            return Some(Err(Unsatisfiable));
+        }
+
+        return Some(Ok(len.saturating_sub(suffix)..len));
+    }
+
+    let first: u64 = first.parse().ok()?;
+
+    if first >= len {
+        return Some(Err(Unsatisfiable));
+    }
+
+    let last = if last.is_empty() {
+        len - 1
+    } else {
+        last.parse::<u64>().ok()?.min(len - 1)
+    };
+
+    // not a valid range
+    if last < first {
+        return None;
+    }
+
+    Some(Ok(first..last + 1))
+}
@@ -1,11 +1,16 @@
 use std::sync::Arc;
 
 use axum::{
+    Extension,
     http::{HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
 
-use crate::{config::Config, http::BileState};
+use crate::{
+    config::Config,
+    error::Result,
+    http::{BileState, negotiate::IsError, pool::Worker, stream},
+};
 
 pub(crate) struct Css<T>(pub T);
 
@@ -70,6 +75,43 @@ impl<T: IntoResponse> IntoResponse for Json<T> {
     }
 }
 
+/// Serializes `T` as the body of an API response.
+pub(crate) struct ApiJson<T: serde::Serialize>(pub T);
+
+impl<T: serde::Serialize> IntoResponse for ApiJson<T> {
+    fn into_response(self) -> Response {
+        match serde_json::to_string(&self.0) {
+            Ok(serialized) => (
+                [
+                    (
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
+                    ),
+                    (
+                        header::CACHE_CONTROL,
+                        HeaderValue::from_static("max-age=300, private"),
+                    ),
+                ],
+                serialized,
+            )
+                .into_response(),
+            Err(err) => {
+                tracing::error!(err=?err, "failed to serialize json response");
+
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    [(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
+                    )],
+                    r#"{"status":500,"message":"Internal Server Error"}"#,
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
 pub(crate) struct Png<T>(pub T);
 
 impl<T: IntoResponse> IntoResponse for Png<T> {
@@ -148,6 +190,59 @@ impl<T: askama::Template> IntoResponse for Html<T> {
     }
 }
 
+/// Marks where the [`Content`] of a [`StreamedHtml`] goes in its template.
+pub(crate) const STREAMED: &str = "<!-- bile:streamed -->";
+
+/// Writes HTML, handing it to the callback until it returns `false` as the
+/// client went away.
+pub(crate) type Content = Box<dyn FnOnce(&mut dyn FnMut(&str) -> bool) -> Result<()> + Send>;
+
+/// A page with content too large to render in memory, the content is
+/// written in place of [`STREAMED`] while the page is sent.
+///
+/// The page is rendered before, so the content can take over what its
+/// template borrowed.
+pub(crate) struct StreamedHtml(pub String, pub Content);
+
+impl IntoResponse for StreamedHtml {
+    fn into_response(self) -> Response {
+        let rendered = self.0;
+        let content = self.1;
+
+        let body = stream::body(
+            Worker::current(),
+            Box::new(move |write| {
+                let (head, tail) = rendered.split_once(STREAMED).unwrap_or((&rendered, ""));
+
+                if !write(head.as_bytes()) {
+                    return Ok(());
+                }
+
+                content(&mut |html| write(html.as_bytes()))?;
+
+                write(tail.as_bytes());
+
+                Ok(())
+            }),
+        );
+
+        (
+            [
+                (
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static(mime::TEXT_HTML_UTF_8.as_ref()),
+                ),
+                (
+                    header::CACHE_CONTROL,
+                    HeaderValue::from_static("max-age=300, private"),
+                ),
+            ],
+            body,
+        )
+            .into_response()
+    }
+}
+
 pub(crate) struct Xml<T: askama::Template>(pub T);
 
 impl<T: askama::Template> IntoResponse for Xml<T> {
@@ -184,6 +279,42 @@ impl<T: askama::Template> IntoResponse for Xml<T> {
     }
 }
 
+pub(crate) struct Atom<T: askama::Template>(pub T);
+
+impl<T: askama::Template> IntoResponse for Atom<T> {
+    fn into_response(self) -> Response {
+        match self.0.render() {
+            Ok(rendered) => (
+                [
+                    (
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/atom+xml"),
+                    ),
+                    (
+                        header::CACHE_CONTROL,
+                        HeaderValue::from_static("max-age=300, private"),
+                    ),
+                ],
+                rendered,
+            )
+                .into_response(),
+            Err(err) => {
+                tracing::error!(err=?err, "failed to render atom response");
+
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    [(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static(mime::TEXT_HTML_UTF_8.as_ref()),
+                    )],
+                    "a serious error has occured",
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
 #[must_use = "needs to be returned from a handler or otherwise turned into a Response to be useful"]
 #[derive(Debug, Clone)]
 pub(crate) struct Redirect {
@@ -250,13 +381,19 @@ impl IntoResponse for Redirect {
 pub(crate) struct ErrorPage {
     config: Arc<Config>,
     status: StatusCode,
+    message: Option<&'static str>,
 }
 
 impl ErrorPage {
     pub(crate) fn with_status(self, status: StatusCode) -> Self {
+        Self { status, ..self }
+    }
+
+    /// Tells what went wrong when the status alone does not.
+    pub(crate) fn with_message(self, message: &'static str) -> Self {
         Self {
-            config: self.config,
-            status,
+            message: Some(message),
+            ..self
         }
     }
 }
@@ -266,6 +403,7 @@ impl From<BileState> for ErrorPage {
         Self {
             config: value.config,
             status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: None,
         }
     }
 }
@@ -275,12 +413,14 @@ impl<'s> From<&'s BileState> for ErrorPage {
         Self {
             config: value.config.clone(),
             status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: None,
         }
     }
 }
 
 impl IntoResponse for ErrorPage {
     fn into_response(self) -> Response {
-        (self.status, Html(self)).into_response()
+        // rendered again by `negotiate::errors` for clients that want another format
+        (self.status, Extension(IsError(self.message)), Html(self)).into_response()
     }
 }
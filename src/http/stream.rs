@@ -0,0 +1,127 @@
+//! Bodies written on a blocking thread while they are sent, so they never
+//! have to be in memory as a whole.
+
+use std::{cell::Cell, io};
+
+use axum::body::{Body, Bytes};
+use futures_util::{StreamExt as _, stream};
+use tokio::{
+    runtime::Handle,
+    sync::mpsc::{self, error::SendTimeoutError},
+};
+
+use crate::{error::Result, http::pool::Worker};
+
+/// Bytes handed to the client at once.
+const CHUNK: usize = 64 * 1024;
+/// Chunks written ahead of the client.
+const WRITE_AHEAD: usize = 4;
+
+/// Writes the body, handing it to the callback in pieces of any size until
+/// the callback returns `false` as the client went away.
+pub(crate) type Produce = Box<dyn FnOnce(&mut dyn FnMut(&[u8]) -> bool) -> Result<()> + Send>;
+
+/// Starts writing once the body is first polled, so a body that is dropped
+/// before it is sent never does any work.
+///
+/// The body keeps the `worker` of the request that created it until it is
+/// written, so the pool bounds the work done while sending it too.
+pub(crate) fn body(worker: Option<Worker>, produce: Produce) -> Body {
+    enum State {
+        Pending(Option<Worker>, Produce),
+        Writing(mpsc::Receiver<io::Result<Bytes>>),
+    }
+
+    let chunks = stream::unfold(State::Pending(worker, produce), |state| async move {
+        let mut chunks = match state {
+            State::Pending(held, pending) => write(held, pending),
+            State::Writing(chunks) => chunks,
+        };
+
+        let chunk = chunks.recv().await?;
+
+        Some((chunk, State::Writing(chunks)))
+    });
+
+    // compression polls the body again once it ended
+    Body::from_stream(chunks.fuse())
+}
+
+/// Writes on a blocking thread, as far ahead of the client as the channel holds.
+///
+/// A client that stops reading is cut off after the send timeout of the
+/// worker, so it gives the worker back instead of keeping it while it stalls.
+fn write(worker: Option<Worker>, produce: Produce) -> mpsc::Receiver<io::Result<Bytes>> {
+    let (sender, receiver) = mpsc::channel(WRITE_AHEAD);
+    let span = tracing::Span::current();
+    let runtime = Handle::current();
+
+    tokio::task::spawn_blocking(move || {
+        let timeout = worker.as_ref().map(Worker::send_timeout);
+        let _worker = worker;
+
+        span.in_scope(|| {
+            let mut buf = Vec::with_capacity(CHUNK);
+            let stalled = Cell::new(false);
+
+            let send = |chunk: &[u8]| {
+                let chunk = Ok(Bytes::copy_from_slice(chunk));
+
+                // without a worker there is nothing for the client to keep
+                let Some(timeout) = timeout else {
+                    return sender.blocking_send(chunk).is_ok();
+                };
+
+                match runtime.block_on(sender.send_timeout(chunk, timeout)) {
+                    Ok(()) => true,
+                    Err(SendTimeoutError::Timeout(_)) => {
+                        stalled.set(true);
+                        false
+                    }
+                    Err(SendTimeoutError::Closed(_)) => false,
+                }
+            };
+
+            // small pieces, like the lines of a page, are sent together
+            let result = produce(&mut |mut data| {
+                while buf.len() + data.len() >= CHUNK {
+                    let (chunk, rest) = data.split_at(CHUNK - buf.len());
+                    buf.extend_from_slice(chunk);
+                    data = rest;
+
+                    if !send(&buf) {
+                        return false;
+                    }
+
+                    buf.clear();
+                }
+
+                buf.extend_from_slice(data);
+
+                true
+            });
+
+            if result.is_ok() && !stalled.get() && !buf.is_empty() {
+                send(&buf);
+            }
+
+            // the client sees the body end early instead of a truncated one
+            let cut = if stalled.get() {
+                tracing::warn!("client stopped reading, cutting the body off");
+                io::Error::new(io::ErrorKind::TimedOut, "client stopped reading")
+            } else if let Err(err) = result {
+                tracing::error!(err=?err, "failed to write body");
+                io::Error::other(err.to_string())
+            } else {
+                return;
+            };
+
+            // sent once the client reads on, the worker is given back meanwhile
+            runtime.spawn(async move {
+                let _ = sender.send(Err(cut)).await;
+            });
+        });
+    });
+
+    receiver
+}
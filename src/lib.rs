@@ -235,17 +235,23 @@ pub(crate) mod utils;
 pub mod config;
 pub mod error;
 
+pub use crate::git::{backend, commit_graph};
+
 use std::{str, time::Duration};
 
-use axum::{Router, http::StatusCode, routing::get};
-use axum_response_cache::CacheLayer;
-use tower_helmet::HelmetLayer;
-use tower_http::{timeout::TimeoutLayer, trace::TraceLayer};
+use axum::{Router, http::StatusCode, middleware, routing::get};
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    timeout::TimeoutLayer,
+    trace::TraceLayer,
+};
 
 use crate::{
     config::Config,
     http::{
         BileState,
+        compression::{self, Encoding, Precompressed},
+        conditional, negotiate, range,
         response::{Css, Ico, Json, Png, Text},
     },
 };
@@ -253,15 +259,27 @@ use crate::{
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
+/// An asset with the copies `build.rs` compressed.
+macro_rules! precompressed {
+    ($name:literal) => {
+        Precompressed {
+            identity: include_bytes!(concat!("../assets/", $name)),
+            brotli: include_bytes!(concat!(env!("OUT_DIR"), "/", $name, ".br")),
+            zstd: include_bytes!(concat!(env!("OUT_DIR"), "/", $name, ".zst")),
+            gzip: include_bytes!(concat!(env!("OUT_DIR"), "/", $name, ".gz")),
+        }
+    };
+}
+
 static APPLE_TOUCH_ICON_PNG: &[u8] = include_bytes!("../assets/apple-touch-icon.png");
-static FAVICON_ICO: &[u8] = include_bytes!("../assets/favicon.ico");
+static FAVICON_ICO: Precompressed = precompressed!("favicon.ico");
 static ICON_192_MASKABLE: &[u8] = include_bytes!("../assets/icon-192-maskable.png");
 static ICON_192: &[u8] = include_bytes!("../assets/icon-192.png");
 static ICON_512_MASKABLE: &[u8] = include_bytes!("../assets/icon-512-maskable.png");
 static ICON_512: &[u8] = include_bytes!("../assets/icon-512.png");
-static MANIFEST_JSON: &str = include_str!("../assets/manifest.json");
+static MANIFEST_JSON: Precompressed = precompressed!("manifest.json");
 static ROBOTS_TXT: &str = include_str!("../assets/robots.txt");
-static STYLE_CSS: &str = include_str!("../assets/style.css");
+static STYLE_CSS: Precompressed = precompressed!("style.css");
 
 static META_PACKAGE_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -285,20 +303,38 @@ impl Bile {
         }
     }
 
+    /// Writes a commit-graph for every repository, going on with the rest when
+    /// one fails.
+    pub fn write_commit_graphs(&self) -> error::Result<()> {
+        for repo in git::Repository::all(&self.state.config)? {
+            match repo.write_commit_graph() {
+                Ok(()) => tracing::info!(repo=?repo.path().display(), "wrote commit-graph"),
+                Err(err) => {
+                    tracing::error!(repo=?repo.path().display(), err=?err, "failed to write commit-graph");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     #[rustfmt::skip]
     pub fn routes(&self) -> Router {
-        Router::new()
+        let pages = Router::new()
             .route("/", get(handlers::index::get))
+            .route("/activity", get(handlers::activity::get))
+            .route("/activity.xml", get(handlers::activity::get_feed))
+            .route("/activity.atom", get(handlers::activity::get_atom))
             // assets
             .route("/apple-touch-icon.png", get(async || Png(APPLE_TOUCH_ICON_PNG)))
-            .route("/favicon.ico", get(async || Ico(FAVICON_ICO)))
+            .route("/favicon.ico", get(async |encoding: Encoding| Ico(FAVICON_ICO.encoded(encoding))))
             .route("/icon-192-maskable.png", get(async || Png(ICON_192_MASKABLE)))
             .route("/icon-192.png", get(async || Png(ICON_192)))
             .route("/icon-512-maskable.png", get(async || Png(ICON_512_MASKABLE)))
             .route("/icon-512.png", get(async || Png(ICON_512)))
-            .route("/manifest.json", get(async || Json(MANIFEST_JSON)))
+            .route("/manifest.json", get(async |encoding: Encoding| Json(MANIFEST_JSON.encoded(encoding))))
             .route("/robots.txt", get(async || Text(ROBOTS_TXT)))
-            .route("/style.css", get(async || Css(STYLE_CSS)))
+            .route("/style.css", get(async |encoding: Encoding| Css(STYLE_CSS.encoded(encoding))))
             //
             .route("/{repo_name}", get(handlers::repo_home::get))
             .route("/{repo_name}/", get(handlers::repo_home::get))
@@ -307,18 +343,23 @@ impl Bile {
             .route("/{repo_name}/HEAD", get(handlers::git::get_1))
             .route("/{repo_name}/objects/{*obj}", get(handlers::git::get_2))
             // web pages
+            .route("/{repo_name}/archive/{*object_name}", get(handlers::repo_archive::get))
             .route("/{repo_name}/commit/{commit}", get(handlers::repo_commit::get))
+            .route("/{repo_name}/compare/{*range}", get(handlers::repo_compare::get))
             .route("/{repo_name}/refs", get(handlers::repo_refs::get))
             .route("/{repo_name}/refs/", get(handlers::repo_refs::get))
             .route("/{repo_name}/refs.xml", get(handlers::repo_refs_feed::get))
+            .route("/{repo_name}/refs.atom", get(handlers::repo_refs_feed::get_atom))
             .route("/{repo_name}/refs/{tag}", get(handlers::repo_tag::get))
             //
             .route("/{repo_name}/log", get(handlers::repo_log::get_1))
             .route("/{repo_name}/log/", get(handlers::repo_log::get_1))
             .route("/{repo_name}/log.xml", get(handlers::repo_log_feed::get_1))
+            .route("/{repo_name}/log.atom", get(handlers::repo_log_feed::get_atom_1))
             .route("/{repo_name}/log/{ref}", get(handlers::repo_log::get_2))
             .route("/{repo_name}/log/{ref}/", get(handlers::repo_log::get_2))
             .route("/{repo_name}/log/{ref}/feed.xml", get(handlers::repo_log_feed::get_2))
+            .route("/{repo_name}/log/{ref}/feed.atom", get(handlers::repo_log_feed::get_atom_2))
             .route("/{repo_name}/log/{ref}/{*object_name}", get(handlers::repo_log::get_3))
             //
             .route("/{repo_name}/tree", get(handlers::repo_file::get_1))
@@ -326,19 +367,37 @@ impl Bile {
             .route("/{repo_name}/tree/{ref}", get(handlers::repo_file::get_2))
             .route("/{repo_name}/tree/{ref}/", get(handlers::repo_file::get_2))
             .route("/{repo_name}/tree/{ref}/item/{*object_name}", get(handlers::repo_file::get_3))
+            // json api
+            .route("/api/v1/openapi.json", get(handlers::api::openapi))
+            .route("/api/v1/repos", get(handlers::api::repos::get))
+            .route("/api/v1/repos/{repo_name}", get(handlers::api::repo::get))
+            .route("/api/v1/repos/{repo_name}/refs", get(handlers::api::refs::get))
+            .route("/api/v1/repos/{repo_name}/commits", get(handlers::api::commits::get))
+            .route("/api/v1/repos/{repo_name}/commits/{commit}", get(handlers::api::commit::get))
+            .route("/api/v1/repos/{repo_name}/tree/{ref}", get(handlers::api::tree::get_1))
+            .route("/api/v1/repos/{repo_name}/tree/{ref}/{*object_name}", get(handlers::api::tree::get_2))
+            .route("/api/v1/repos/{repo_name}/blob/{ref}/{*object_name}", get(handlers::api::blob::get))
+            .layer(http::helmet(&self.state.config));
+
+        // raw files are kept apart as they get a stricter policy
+        let raw = Router::new()
             .route("/{repo_name}/tree/{ref}/raw/{*object_name}", get(handlers::repo_file_raw::get))
-            //
+            .layer(http::raw_helmet(&self.state.config));
+
+        pages
+            .merge(raw)
             .with_state(self.state.clone())
             //
             .layer((
+                SetRequestIdLayer::x_request_id(MakeRequestUuid),
                 TraceLayer::new_for_http(),
+                PropagateRequestIdLayer::x_request_id(),
                 TimeoutLayer::with_status_code(StatusCode::REQUEST_TIMEOUT, Duration::from_secs(10)),
-                CacheLayer::with_lifespan(Duration::from_secs(60)).use_stale_on_failure(),
-                {
-                    let mut layer = HelmetLayer::with_defaults();
-                    layer.enable(http::PermissionsPolicy);
-                    layer
-                },
+                middleware::from_fn(compression::weaken_etags),
+                compression::layer(),
+                middleware::from_fn(range::ranges),
+                middleware::from_fn(conditional::validate),
+                middleware::from_fn(negotiate::errors),
             ))
     }
 }
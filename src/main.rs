@@ -1,6 +1,9 @@
 #![deny(rust_2018_idioms, unsafe_code)]
 
-use bile::{Bile, config::Config};
+use bile::{
+    Bile,
+    config::{Command, Config},
+};
 use tracing_subscriber::{layer::SubscriberExt as _, util::SubscriberInitExt as _};
 
 #[tokio::main]
@@ -31,9 +34,14 @@ async fn main() -> bile::error::Result<()> {
     }
 
     let addr = format!("[::]:{}", config.port);
+    let command = config.command;
 
     let bile = Bile::init(config.finalize()?);
 
+    if let Some(Command::WriteCommitGraphs) = command {
+        return bile.write_commit_graphs();
+    }
+
     let app = bile.routes();
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
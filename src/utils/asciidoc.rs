@@ -0,0 +1,278 @@
+use std::fmt::Write as _;
+
+use syntect::parsing::SyntaxSet;
+
+use crate::utils::markup::{self, Delimiter, Link, List};
+
+const DELIMITERS: &[Delimiter] = &[
+    Delimiter {
+        marker: "`",
+        tag: "code",
+        verbatim: true,
+    },
+    Delimiter {
+        marker: "**",
+        tag: "strong",
+        verbatim: false,
+    },
+    Delimiter {
+        marker: "*",
+        tag: "strong",
+        verbatim: false,
+    },
+    Delimiter {
+        marker: "__",
+        tag: "em",
+        verbatim: false,
+    },
+    Delimiter {
+        marker: "_",
+        tag: "em",
+        verbatim: false,
+    },
+];
+
+const ADMONITIONS: &[&str] = &["NOTE", "TIP", "IMPORTANT", "WARNING", "CAUTION"];
+
+/// Renders the commonly used subset of `AsciiDoc` to HTML.
+#[tracing::instrument(skip_all)]
+pub(crate) fn render(syntaxes: &SyntaxSet, input: &str) -> String {
+    let mut output = String::new();
+    let mut paragraph = Vec::new();
+    let mut list = List::default();
+    // set by a `[source,<language>]` block attribute line
+    let mut language: Option<String> = None;
+
+    let mut lines = input.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let line = line.trim_end();
+
+        if line.is_empty() || line == "+" || line == "--" {
+            // lists may have blank lines between their items
+            if lines.peek().is_some_and(|next| list_item(next).is_some()) {
+                markup::paragraph(&mut output, &mut paragraph, inline);
+            } else {
+                flush(&mut output, &mut paragraph, &mut list);
+            }
+            continue;
+        }
+
+        // comments
+        if line == "////" {
+            lines
+                .by_ref()
+                .take_while(|l| l.trim_end() != "////")
+                .for_each(drop);
+            continue;
+        }
+        if line.starts_with("//") {
+            continue;
+        }
+
+        // document attributes, e.g. `:toc: left`
+        if is_attribute_entry(line) {
+            continue;
+        }
+
+        // block attributes, only the source language is of interest
+        if paragraph.is_empty()
+            && let Some(attributes) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']'))
+        {
+            let mut attributes = attributes.split(',').map(str::trim);
+            if attributes.next() == Some("source") {
+                language = attributes.next().map(str::to_string);
+            }
+            continue;
+        }
+
+        // delimited blocks
+        if matches!(line, "----" | "...." | "____" | "====" | "****") {
+            flush(&mut output, &mut paragraph, &mut list);
+
+            let content = lines
+                .by_ref()
+                .take_while(|l| l.trim_end() != line)
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            delimited_block(
+                syntaxes,
+                &mut output,
+                line,
+                language.take().as_deref(),
+                &content,
+            );
+
+            continue;
+        }
+
+        if line == "'''" {
+            flush(&mut output, &mut paragraph, &mut list);
+            output.push_str("<hr>\n");
+            continue;
+        }
+
+        if let Some((level, title)) = heading(line) {
+            flush(&mut output, &mut paragraph, &mut list);
+            let _ = writeln!(output, "<h{level}>{}</h{level}>", inline(title));
+            continue;
+        }
+
+        // block titles, e.g. `.Example`
+        if paragraph.is_empty()
+            && let Some(title) = line.strip_prefix('.')
+            && title.starts_with(|c: char| !c.is_whitespace() && c != '.')
+        {
+            flush(&mut output, &mut paragraph, &mut list);
+            let _ = writeln!(output, "<div class=\"title\">{}</div>", inline(title));
+            continue;
+        }
+
+        if let Some((depth, ordered, text)) = list_item(line) {
+            markup::paragraph(&mut output, &mut paragraph, inline);
+            list.push(depth, ordered, text);
+            continue;
+        }
+
+        if paragraph.is_empty() && list.extend(line.trim_start()) {
+            continue;
+        }
+
+        paragraph.push(line);
+    }
+
+    flush(&mut output, &mut paragraph, &mut list);
+
+    output
+}
+
+fn flush(output: &mut String, paragraph: &mut Vec<&str>, list: &mut List) {
+    list.flush(output, inline);
+
+    let admonition = paragraph.first().copied().and_then(|first| {
+        ADMONITIONS
+            .iter()
+            .find_map(|label| Some((*label, first.strip_prefix(label)?.strip_prefix(": ")?)))
+    });
+
+    if let Some((label, first)) = admonition {
+        paragraph[0] = first;
+
+        let _ = writeln!(
+            output,
+            "<div class=\"admonition {}\"><strong>{label}:</strong>",
+            label.to_ascii_lowercase(),
+        );
+        markup::paragraph(output, paragraph, inline);
+        output.push_str("</div>\n");
+    } else {
+        markup::paragraph(output, paragraph, inline);
+    }
+}
+
+fn delimited_block(
+    syntaxes: &SyntaxSet,
+    output: &mut String,
+    delimiter: &str,
+    language: Option<&str>,
+    content: &str,
+) {
+    match delimiter {
+        "----" => output.push_str(&markup::code_block(syntaxes, language, content)),
+        "...." => {
+            let _ = writeln!(output, "<pre>{}</pre>", markup::escape(content));
+        }
+        "____" => {
+            let _ = writeln!(
+                output,
+                "<blockquote>\n{}</blockquote>",
+                render(syntaxes, content)
+            );
+        }
+        _ => {
+            let _ = writeln!(
+                output,
+                "<div class=\"example\">\n{}</div>",
+                render(syntaxes, content)
+            );
+        }
+    }
+}
+
+fn inline(text: &str) -> String {
+    // a trailing ` +` forces a line break
+    markup::inline(text, DELIMITERS, &link).replace(" +\n", "<br>\n")
+}
+
+fn link(text: &str) -> Option<Link> {
+    if let Some(target) = text.strip_prefix("link:") {
+        let end = target.find(['[', ' '])?;
+        let (label, len) = label(&target[end..])?;
+
+        return Some(Link {
+            href: target[..end].to_string(),
+            label,
+            len: "link:".len() + end + len,
+        });
+    }
+
+    let url = markup::bare_url(text)?;
+
+    Some(match label(&text[url.len..]) {
+        Some((label, len)) => Link {
+            label,
+            len: url.len + len,
+            ..url
+        },
+        None => url,
+    })
+}
+
+/// Parses the `[label]` following a link target.
+fn label(text: &str) -> Option<(Option<String>, usize)> {
+    let inner = text.strip_prefix('[')?;
+    let end = inner.find(']')?;
+
+    let label = Some(&inner[..end])
+        .filter(|label| !label.is_empty())
+        .map(str::to_string);
+
+    Some((label, end + 2))
+}
+
+fn is_attribute_entry(line: &str) -> bool {
+    line.strip_prefix(':')
+        .and_then(|rest| rest.split_once(':'))
+        .is_some_and(|(name, _)| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '!'))
+        })
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.bytes().take_while(|&b| b == b'=').count();
+    let title = line[level..].strip_prefix(' ')?.trim();
+
+    ((1..=6).contains(&level) && !title.is_empty()).then_some((level, title))
+}
+
+fn list_item(line: &str) -> Option<(usize, bool, &str)> {
+    let line = line.trim_start();
+
+    for (marker, ordered) in [(b'*', false), (b'-', false), (b'.', true)] {
+        let depth = line.bytes().take_while(|&b| b == marker).count();
+
+        if depth == 0 || (marker == b'-' && depth > 1) {
+            continue;
+        }
+
+        if let Some(text) = line[depth..].strip_prefix(' ') {
+            return Some((depth, ordered, text.trim()));
+        }
+    }
+
+    None
+}
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+
+use git2::{Diff, DiffFindOptions, DiffFormat, DiffStats, Oid};
+use syntect::{parsing::SyntaxSet, util::LinesWithEndings};
+use yoke::{Yoke, Yokeable};
+
+use crate::{
+    error::{Context as _, Result},
+    git::Repository,
+    http::response::Content,
+    utils::highlight::Highlighter,
+};
+
+/// The changes shown on a page.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Changes {
+    /// The changes of a commit to its first parent.
+    Commit(Oid),
+    /// The changes from one commit to another.
+    Between(Oid, Oid),
+}
+
+impl Changes {
+    /// Diffs the changes, marking moved and renamed files.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn diff(self, repo: &Repository) -> Result<Diff<'_>> {
+        let find = |id: Oid| {
+            repo.find_commit(id)
+                .context("commit of the diff is missing")
+        };
+
+        let mut diff = match self {
+            Self::Commit(id) => repo
+                .commit_diff(&find(id)?)
+                .context("failed to get commits diff")?,
+            Self::Between(from, to) => repo
+                .compare_diff(&find(from)?, &find(to)?)
+                .context("failed to diff refs")?,
+        };
+
+        let mut find_options = DiffFindOptions::new();
+        // try to find moved/renamed files
+        find_options.all(true);
+        if let Err(err) = diff.find_similar(Some(&mut find_options)) {
+            tracing::error!(err=?err, "failed to mark similar files in diff");
+        }
+
+        Ok(diff)
+    }
+
+    /// Diffs the changes, keeping the repository along with the diff so the
+    /// page can show its stats and then send it without diffing again.
+    pub(crate) fn diff_owned(self, repo: Repository) -> Result<OwnedDiff> {
+        let diff = Yoke::try_attach_to_cart(Box::new(repo), |repo| self.diff(repo).map(RepoDiff))?;
+
+        Ok(OwnedDiff(diff))
+    }
+}
+
+#[derive(Yokeable)]
+struct RepoDiff<'repo>(Diff<'repo>);
+
+/// A diff and the repository it was made in.
+pub(crate) struct OwnedDiff(Yoke<RepoDiff<'static>, Box<Repository>>);
+
+impl OwnedDiff {
+    pub(crate) fn repo(&self) -> &Repository {
+        self.0.backing_cart()
+    }
+
+    pub(crate) fn stats(&self) -> Result<DiffStats> {
+        Ok(self.0.get().0.stats()?)
+    }
+
+    /// Writes the diff as a highlighted patch while the page is sent.
+    pub(crate) fn stream(self, syntaxes: &Arc<SyntaxSet>) -> Content {
+        let syntaxes = Arc::clone(syntaxes);
+
+        Box::new(move |write| {
+            highlight(&syntaxes, &self.0.get().0, write);
+
+            Ok(())
+        })
+    }
+}
+
+/// Highlights a diff as a patch, one line at a time.
+#[tracing::instrument(skip_all)]
+fn highlight(syntaxes: &SyntaxSet, diff: &Diff<'_>, write: &mut dyn FnMut(&str) -> bool) {
+    let syntax = syntaxes
+        .find_syntax_by_name("Diff")
+        .expect("diff syntax missing");
+
+    let mut highlighter = Highlighter::new(syntax, syntaxes);
+    // file headers come in one piece, the end of a file without a newline
+    // comes after its last line
+    let mut pending = String::new();
+    let mut open = true;
+
+    let _ = diff.print(DiffFormat::Patch, |_, _, diff_line| {
+        let Ok(content) = str::from_utf8(diff_line.content()) else {
+            pending.push_str("Cannot display diff for binary file.");
+
+            return false;
+        };
+
+        match diff_line.origin() {
+            'F' | 'H' | 'B' => {}
+            c if matches!(c, ' ' | '+' | '-' | '=' | '<' | '>') => pending.push(c),
+            // anything libgit2 might add later is shown as context
+            _ => pending.push(' '),
+        }
+
+        pending.push_str(content);
+
+        let complete = pending.rfind('\n').map_or(0, |end| end + 1);
+
+        for line in LinesWithEndings::from(&pending[..complete]) {
+            if !write(&highlighter.line(line)) {
+                open = false;
+                return false;
+            }
+        }
+
+        pending.drain(..complete);
+
+        true
+    });
+
+    if !open {
+        return;
+    }
+
+    if !pending.is_empty() && !write(&highlighter.line(&pending)) {
+        return;
+    }
+
+    write(&highlighter.finish());
+}
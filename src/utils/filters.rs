@@ -37,6 +37,33 @@ pub(crate) fn format_datetime<'f>(
     Ok(format)
 }
 
+/// Units for [`relative_time`], largest first.
+const TIME_UNITS: [(i64, &str); 6] = [
+    (365 * 24 * 60 * 60, "year"),
+    (30 * 24 * 60 * 60, "month"),
+    (7 * 24 * 60 * 60, "week"),
+    (24 * 60 * 60, "day"),
+    (60 * 60, "hour"),
+    (60, "minute"),
+];
+
+/// How long ago a time was, like "3 days ago".
+#[askama::filter_fn]
+pub(crate) fn relative_time(time: Time, _: &dyn askama::Values) -> askama::Result<String> {
+    let seconds = Timestamp::now().as_second().saturating_sub(time.seconds());
+
+    for (size, unit) in TIME_UNITS {
+        let amount = seconds / size;
+
+        if amount > 0 {
+            let plural = if amount == 1 { "" } else { "s" };
+            return Ok(format!("{amount} {unit}{plural} ago"));
+        }
+    }
+
+    Ok("just now".to_string())
+}
+
 #[askama::filter_fn]
 pub(crate) fn unix_perms(m: i32, _: &dyn askama::Values) -> askama::Result<String> {
     // https://unix.stackexchange.com/questions/450480/file-permission-with-six-bytes-in-git-what-does-it-mean
@@ -0,0 +1,52 @@
+use syntect::{
+    html::{ClassStyle, line_tokens_to_classed_spans},
+    parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet},
+};
+
+/// Highlights a text one line at a time, so it can be sent while it is
+/// highlighted, with CSS classes so we can use prefers-color-scheme.
+pub(crate) struct Highlighter<'s> {
+    syntaxes: &'s SyntaxSet,
+    parse_state: ParseState,
+    scope_stack: ScopeStack,
+    open_spans: isize,
+}
+
+impl<'s> Highlighter<'s> {
+    pub(crate) fn new(syntax: &SyntaxReference, syntaxes: &'s SyntaxSet) -> Self {
+        Self {
+            syntaxes,
+            parse_state: ParseState::new(syntax),
+            scope_stack: ScopeStack::new(),
+            open_spans: 0,
+        }
+    }
+
+    /// The HTML of a line including its newline, spans of scopes going on
+    /// in the next line are left open.
+    pub(crate) fn line(&mut self, line: &str) -> String {
+        let highlighted = self
+            .parse_state
+            .parse_line(line, self.syntaxes)
+            .map_err(syntect::Error::from)
+            .and_then(|ops| {
+                line_tokens_to_classed_spans(line, &ops, ClassStyle::Spaced, &mut self.scope_stack)
+            });
+
+        match highlighted {
+            Ok((html, delta)) => {
+                self.open_spans += delta;
+                html
+            }
+            Err(err) => {
+                tracing::error!(err=?err, "failed to highlight code");
+                String::new()
+            }
+        }
+    }
+
+    /// Closes the spans left open.
+    pub(crate) fn finish(self) -> String {
+        "</span>".repeat(self.open_spans.max(0).cast_unsigned())
+    }
+}
@@ -15,15 +15,24 @@ use syntect::{
     util::LinesWithEndings,
 };
 
+use crate::config::Markdown;
+
 #[tracing::instrument(skip_all)]
-pub(crate) fn render(syntaxes: &SyntaxSet, input: &str) -> String {
+pub(crate) fn render(syntaxes: &SyntaxSet, config: &Markdown, input: &str) -> String {
     let adaptor = SyntectAdapter {
         syntax_set: syntaxes,
     };
 
     let mut options = Options::default();
 
-    options.extension.tasklist = true;
+    options.extension.table = config.tables;
+    options.extension.strikethrough = config.strikethrough;
+    options.extension.autolink = config.autolinks;
+    options.extension.footnotes = config.footnotes;
+    options.extension.tasklist = config.tasklists;
+    options.extension.alerts = config.alerts;
+    // an empty prefix keeps the ids in line with the anchors linking to them
+    options.extension.header_ids = config.heading_anchors.then(String::new);
 
     let mut plugins = Plugins::default();
 
@@ -0,0 +1,406 @@
+use std::{ffi::OsStr, path::Path};
+
+use askama::filters::Escaper as _;
+use syntect::{
+    html::{ClassStyle, ClassedHTMLGenerator},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+use crate::{
+    config::Markdown,
+    utils::{asciidoc, markdown, org, rst},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Markup {
+    Plaintext,
+    Markdown,
+    Html,
+    AsciiDoc,
+    ReStructuredText,
+    Org,
+}
+
+/// Every known markup format along with the file extensions it is recognized by.
+///
+/// When a repository has multiple readme files the one whose format comes first wins.
+static REGISTRY: &[(Markup, &[&str])] = &[
+    (Markup::Plaintext, &["", "txt"]),
+    (Markup::Markdown, &["md", "mdown", "markdown"]),
+    (Markup::Html, &["html", "htm"]),
+    (Markup::AsciiDoc, &["adoc", "asciidoc"]),
+    (Markup::ReStructuredText, &["rst", "rest"]),
+    (Markup::Org, &["org"]),
+];
+
+impl Markup {
+    #[must_use]
+    pub(crate) fn from_extension(extension: &str) -> Option<Self> {
+        REGISTRY
+            .iter()
+            .find(|(_, extensions)| {
+                extensions
+                    .iter()
+                    .any(|candidate| candidate.eq_ignore_ascii_case(extension))
+            })
+            .map(|(markup, _)| *markup)
+    }
+
+    #[must_use]
+    pub(crate) fn from_path(path: &Path) -> Option<Self> {
+        Self::from_extension(path.extension().and_then(OsStr::to_str).unwrap_or_default())
+    }
+
+    #[must_use]
+    pub(crate) fn priority(self) -> usize {
+        REGISTRY
+            .iter()
+            .position(|(markup, _)| *markup == self)
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Whether the file view should show the rendered document rather than its source.
+    ///
+    /// Plaintext has nothing to render and HTML is only trusted for readmes.
+    #[must_use]
+    pub(crate) const fn is_document(self) -> bool {
+        matches!(
+            self,
+            Self::Markdown | Self::AsciiDoc | Self::ReStructuredText | Self::Org
+        )
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn render(self, syntaxes: &SyntaxSet, markdown: &Markdown, text: &str) -> String {
+        match self {
+            // render plaintext as preformatted text
+            Self::Plaintext => format!("<pre>{}</pre>", escape(text)),
+            // already is HTML
+            Self::Html => text.to_string(),
+            Self::Markdown => markdown::render(syntaxes, markdown, text),
+            Self::AsciiDoc => asciidoc::render(syntaxes, text),
+            Self::ReStructuredText => rst::render(syntaxes, text),
+            Self::Org => org::render(syntaxes, text),
+        }
+    }
+}
+
+/// How deeply blocks like quotes may be nested in each other.
+const MAX_DEPTH: usize = 16;
+
+/// Renders a block nested `nesting` levels deep with `render`, deeper blocks are
+/// shown as plain text instead so that untrusted input can't overflow the stack.
+pub(crate) fn nested(text: &str, nesting: usize, render: impl FnOnce(usize) -> String) -> String {
+    if nesting < MAX_DEPTH {
+        render(nesting + 1)
+    } else {
+        format!("<pre>{}</pre>\n", escape(text))
+    }
+}
+
+#[must_use]
+pub(crate) fn escape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+
+    if let Err(err) = askama::filters::Html.write_escaped_str(&mut output, text) {
+        tracing::error!(err=?err, "failed to write escaped text");
+    }
+
+    output
+}
+
+/// Highlights a code block, falling back to plain text for unknown languages.
+#[must_use]
+pub(crate) fn code_block(syntaxes: &SyntaxSet, language: Option<&str>, code: &str) -> String {
+    let syntax = language
+        .filter(|language| !language.is_empty())
+        .and_then(|language| syntaxes.find_syntax_by_token(language))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+
+    let mut highlighter =
+        ClassedHTMLGenerator::new_with_class_style(syntax, syntaxes, ClassStyle::Spaced);
+
+    for line in LinesWithEndings::from(code) {
+        if let Err(err) = highlighter.parse_html_for_line_which_includes_newline(line) {
+            tracing::error!(err=?err, "failed to highlight code");
+        }
+    }
+
+    format!(
+        "<pre class=\"syntax-highlighting\"><code>{}</code></pre>\n",
+        highlighter.finalize()
+    )
+}
+
+/// A pair of markers that wraps inline text, e.g. `*bold*`.
+pub(crate) struct Delimiter {
+    pub(crate) marker: &'static str,
+    pub(crate) tag: &'static str,
+    /// the wrapped text is shown as is instead of being parsed for more markup
+    pub(crate) verbatim: bool,
+}
+
+pub(crate) struct Link {
+    pub(crate) href: String,
+    pub(crate) label: Option<String>,
+    /// how many bytes of the input the link took up
+    pub(crate) len: usize,
+}
+
+/// Renders inline markup shared by the lightweight markup formats.
+///
+/// Delimiters only open and close on word boundaries so that things like
+/// `snake_case` are left alone.
+#[must_use]
+pub(crate) fn inline(
+    text: &str,
+    delimiters: &[Delimiter],
+    link: &dyn Fn(&str) -> Option<Link>,
+) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut prev: Option<char> = None;
+
+    // found up front so that looking for the end of a span doesn't search the rest of the text
+    let mut closers: Vec<Closers> = delimiters
+        .iter()
+        .map(|delimiter| Closers::new(text, delimiter.marker))
+        .collect();
+
+    'outer: while let Some(c) = rest.chars().next() {
+        if prev.is_none_or(|prev| !prev.is_alphanumeric()) {
+            if let Some(found) = link(rest) {
+                write_link(&mut output, &found);
+                prev = rest[..found.len].chars().next_back();
+                rest = &rest[found.len..];
+                continue;
+            }
+
+            let at = text.len() - rest.len();
+
+            for (delimiter, closing) in delimiters.iter().zip(&mut closers) {
+                let Some(len) = delimited(text, at, delimiter.marker, closing) else {
+                    continue;
+                };
+
+                let inner = &rest[delimiter.marker.len()..len - delimiter.marker.len()];
+
+                output.push('<');
+                output.push_str(delimiter.tag);
+                output.push('>');
+                if delimiter.verbatim {
+                    output.push_str(&escape(inner));
+                } else {
+                    output.push_str(&inline(inner, delimiters, link));
+                }
+                output.push_str("</");
+                output.push_str(delimiter.tag);
+                output.push('>');
+
+                prev = delimiter.marker.chars().next_back();
+                rest = &rest[len..];
+                continue 'outer;
+            }
+        }
+
+        let len = c.len_utf8();
+        output.push_str(&escape(&rest[..len]));
+        prev = Some(c);
+        rest = &rest[len..];
+    }
+
+    output
+}
+
+/// The positions in a text where a marker can close a span, i.e. after a
+/// non-whitespace character and before a word boundary.
+struct Closers {
+    positions: Vec<usize>,
+    /// spans are looked for front to back, so closers before this are never needed again
+    next: usize,
+}
+
+impl Closers {
+    fn new(text: &str, marker: &str) -> Self {
+        let positions = text
+            .char_indices()
+            .filter(|&(at, _)| {
+                text[at..].starts_with(marker)
+                    && text[..at]
+                        .chars()
+                        .next_back()
+                        .is_some_and(|c| !c.is_whitespace())
+                    && text[at + marker.len()..]
+                        .chars()
+                        .next()
+                        .is_none_or(|c| !c.is_alphanumeric())
+            })
+            .map(|(at, _)| at)
+            .collect();
+
+        Self { positions, next: 0 }
+    }
+
+    /// The first closer after `start`, which must not be before the one of the previous call.
+    fn after(&mut self, start: usize) -> Option<usize> {
+        while self.positions.get(self.next).is_some_and(|&at| at <= start) {
+            self.next += 1;
+        }
+
+        self.positions.get(self.next).copied()
+    }
+}
+
+/// Returns the length of the delimited span at `at` in `text`, if there is one.
+fn delimited(text: &str, at: usize, marker: &str, closers: &mut Closers) -> Option<usize> {
+    let inner = text[at..].strip_prefix(marker)?;
+
+    if inner.starts_with(char::is_whitespace) || inner.starts_with(marker) {
+        return None;
+    }
+
+    // spans can't be empty
+    let end = closers.after(at + marker.len())?;
+
+    Some(end + marker.len() - at)
+}
+
+fn write_link(output: &mut String, link: &Link) {
+    let label = escape(link.label.as_deref().unwrap_or(&link.href));
+
+    if is_safe_href(&link.href) {
+        output.push_str("<a href=\"");
+        output.push_str(&escape(&link.href));
+        output.push_str("\">");
+        output.push_str(&label);
+        output.push_str("</a>");
+    } else {
+        output.push_str(&label);
+    }
+}
+
+/// Only allow relative links and a handful of well known schemes.
+#[must_use]
+pub(crate) fn is_safe_href(href: &str) -> bool {
+    match href.split_once(':') {
+        Some((scheme, _)) if !scheme.contains(['/', '?', '#']) => matches!(
+            scheme.to_ascii_lowercase().as_str(),
+            "http" | "https" | "mailto" | "ftp"
+        ),
+        _ => true,
+    }
+}
+
+/// Finds a plain `http(s)://` URL at the start of `text`.
+#[must_use]
+pub(crate) fn bare_url(text: &str) -> Option<Link> {
+    if !text.starts_with("https://") && !text.starts_with("http://") {
+        return None;
+    }
+
+    let end = text
+        .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '[' | ']'))
+        .unwrap_or(text.len());
+
+    // trailing punctuation most likely belongs to the sentence
+    let url = text[..end].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '\'']);
+
+    Some(Link {
+        href: url.to_string(),
+        label: None,
+        len: url.len(),
+    })
+}
+
+/// Writes the buffered lines of a paragraph.
+pub(crate) fn paragraph(
+    output: &mut String,
+    lines: &mut Vec<&str>,
+    render: impl Fn(&str) -> String,
+) {
+    if lines.is_empty() {
+        return;
+    }
+
+    output.push_str("<p>");
+    output.push_str(&render(&lines.join("\n")));
+    output.push_str("</p>\n");
+
+    lines.clear();
+}
+
+struct ListItem {
+    depth: usize,
+    ordered: bool,
+    text: String,
+}
+
+/// Collects list items so that nested lists can be written in one go.
+#[derive(Default)]
+pub(crate) struct List {
+    items: Vec<ListItem>,
+}
+
+impl List {
+    pub(crate) fn push(&mut self, depth: usize, ordered: bool, text: &str) {
+        self.items.push(ListItem {
+            depth,
+            ordered,
+            text: text.to_string(),
+        });
+    }
+
+    /// Appends a continuation line to the last item, returns false if there is none.
+    pub(crate) fn extend(&mut self, text: &str) -> bool {
+        let Some(item) = self.items.last_mut() else {
+            return false;
+        };
+
+        item.text.push('\n');
+        item.text.push_str(text);
+
+        true
+    }
+
+    pub(crate) fn flush(&mut self, output: &mut String, render: impl Fn(&str) -> String) {
+        let tag = |ordered| if ordered { "ol" } else { "ul" };
+
+        // the depth and kind of every list that is still open
+        let mut open: Vec<(usize, bool)> = Vec::new();
+
+        for item in self.items.drain(..) {
+            while let Some(&(depth, ordered)) = open.last() {
+                if depth < item.depth {
+                    break;
+                }
+
+                if depth == item.depth && ordered == item.ordered {
+                    output.push_str("</li>\n");
+                    break;
+                }
+
+                output.push_str("</li>\n</");
+                output.push_str(tag(ordered));
+                output.push_str(">\n");
+                open.pop();
+            }
+
+            if open.last().is_none_or(|&(depth, _)| depth < item.depth) {
+                output.push('<');
+                output.push_str(tag(item.ordered));
+                output.push_str(">\n");
+                open.push((item.depth, item.ordered));
+            }
+
+            output.push_str("<li>");
+            output.push_str(&render(&item.text));
+        }
+
+        while let Some((_, ordered)) = open.pop() {
+            output.push_str("</li>\n</");
+            output.push_str(tag(ordered));
+            output.push_str(">\n");
+        }
+    }
+}
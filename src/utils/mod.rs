@@ -1,5 +1,14 @@
+pub(crate) mod asciidoc;
+pub(crate) mod diff;
 pub(crate) mod filters;
+pub(crate) mod highlight;
 pub(crate) mod markdown;
+pub(crate) mod markup;
+pub(crate) mod notebook;
+pub(crate) mod org;
+pub(crate) mod rst;
+pub(crate) mod switcher;
+pub(crate) mod table;
 
 #[must_use]
 pub(crate) fn blob_mime(blob: &git2::Blob<'_>, extension: &str) -> mime::Mime {
@@ -0,0 +1,266 @@
+use std::{collections::HashMap, fmt::Write as _};
+
+use base64::{Engine as _, prelude::BASE64_STANDARD};
+use serde::Deserialize;
+use syntect::parsing::SyntaxSet;
+
+use crate::{
+    config::Markdown,
+    error::Result,
+    utils::{markdown, markup},
+};
+
+/// Image types that can be shown as is, SVGs are fine too as scripts do not
+/// run in an `<img>`.
+const IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/svg+xml"];
+
+#[derive(Deserialize)]
+struct Notebook {
+    #[serde(default)]
+    metadata: Metadata,
+    #[serde(default)]
+    cells: Vec<Cell>,
+}
+
+#[derive(Default, Deserialize)]
+struct Metadata {
+    kernelspec: Option<KernelSpec>,
+    language_info: Option<LanguageInfo>,
+}
+
+#[derive(Deserialize)]
+struct KernelSpec {
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LanguageInfo {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "cell_type", rename_all = "lowercase")]
+enum Cell {
+    Code {
+        source: Text,
+        execution_count: Option<u64>,
+        #[serde(default)]
+        outputs: Vec<Output>,
+    },
+    Markdown {
+        source: Text,
+    },
+    Raw {
+        source: Text,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "output_type", rename_all = "snake_case")]
+enum Output {
+    Stream {
+        name: String,
+        text: Text,
+    },
+    ExecuteResult {
+        #[serde(default)]
+        data: HashMap<String, serde_json::Value>,
+    },
+    DisplayData {
+        #[serde(default)]
+        data: HashMap<String, serde_json::Value>,
+    },
+    Error {
+        ename: String,
+        evalue: String,
+        #[serde(default)]
+        traceback: Vec<String>,
+    },
+}
+
+/// Multiline strings are stored either as a single string or as a list of lines.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Text {
+    Single(String),
+    Lines(Vec<String>),
+}
+
+impl Text {
+    fn join(&self) -> String {
+        match self {
+            Self::Single(text) => text.clone(),
+            Self::Lines(lines) => lines.concat(),
+        }
+    }
+}
+
+/// Renders a Jupyter notebook as a list of cells with their stored outputs.
+#[tracing::instrument(skip_all)]
+pub(crate) fn render(syntaxes: &SyntaxSet, config: &Markdown, input: &str) -> Result<String> {
+    let notebook: Notebook = serde_json::from_str(input)?;
+
+    let language = notebook
+        .metadata
+        .kernelspec
+        .and_then(|kernel| kernel.language)
+        .or_else(|| notebook.metadata.language_info.and_then(|info| info.name))
+        .unwrap_or_else(|| "python".to_string());
+
+    let mut output = String::from("<div class=\"notebook\">\n");
+
+    for cell in &notebook.cells {
+        match cell {
+            Cell::Code {
+                source,
+                execution_count,
+                outputs,
+            } => {
+                let count = execution_count.map(|n| n.to_string()).unwrap_or_default();
+
+                let _ = write!(
+                    output,
+                    "<div class=\"cell code\">\n<div class=\"prompt\">In [{count}]:</div>\n{}",
+                    markup::code_block(syntaxes, Some(&language), &source.join()),
+                );
+
+                if !outputs.is_empty() {
+                    output.push_str("<div class=\"outputs\">\n");
+                    for cell_output in outputs {
+                        write_output(&mut output, cell_output);
+                    }
+                    output.push_str("</div>\n");
+                }
+
+                output.push_str("</div>\n");
+            }
+            Cell::Markdown { source } => {
+                let _ = writeln!(
+                    output,
+                    "<div class=\"cell markdown readme\">\n{}</div>",
+                    markdown::render(syntaxes, config, &source.join()),
+                );
+            }
+            Cell::Raw { source } => {
+                let _ = writeln!(
+                    output,
+                    "<div class=\"cell raw\"><pre>{}</pre></div>",
+                    markup::escape(&source.join()),
+                );
+            }
+        }
+    }
+
+    output.push_str("</div>\n");
+
+    Ok(output)
+}
+
+fn write_output(output: &mut String, cell_output: &Output) {
+    match cell_output {
+        Output::Stream { name, text } => {
+            let _ = writeln!(
+                output,
+                "<pre class=\"output {}\">{}</pre>",
+                markup::escape(name),
+                markup::escape(&text.join()),
+            );
+        }
+        Output::ExecuteResult { data } | Output::DisplayData { data } => {
+            write_data(output, data);
+        }
+        Output::Error {
+            ename,
+            evalue,
+            traceback,
+        } => {
+            let traceback = if traceback.is_empty() {
+                format!("{ename}: {evalue}")
+            } else {
+                strip_ansi(&traceback.join("\n"))
+            };
+
+            let _ = writeln!(
+                output,
+                "<pre class=\"output error\">{}</pre>",
+                markup::escape(&traceback),
+            );
+        }
+    }
+}
+
+/// Shows the richest representation that is safe to embed.
+///
+/// HTML and JavaScript outputs could run arbitrary code on the page so only
+/// their plain text fallback is shown.
+fn write_data(output: &mut String, data: &HashMap<String, serde_json::Value>) {
+    let image = IMAGE_TYPES.iter().find_map(|mime| {
+        let text = as_text(data.get(*mime)?)?;
+
+        Some((*mime, text))
+    });
+
+    if let Some((mime, text)) = image {
+        // SVGs are stored as markup, every other image as base64
+        let encoded = if mime == "image/svg+xml" {
+            BASE64_STANDARD.encode(text)
+        } else {
+            text.split_whitespace().collect()
+        };
+
+        if is_base64(&encoded) {
+            let _ = writeln!(
+                output,
+                "<img class=\"output\" src=\"data:{mime};base64,{encoded}\" />"
+            );
+            return;
+        }
+    }
+
+    if let Some(text) = data.get("text/plain").and_then(as_text) {
+        let _ = writeln!(
+            output,
+            "<pre class=\"output\">{}</pre>",
+            markup::escape(&text)
+        );
+    }
+}
+
+fn as_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Array(lines) => lines
+            .iter()
+            .map(|line| line.as_str())
+            .collect::<Option<Vec<_>>>()
+            .map(|lines| lines.concat()),
+        _ => None,
+    }
+}
+
+fn is_base64(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'='))
+}
+
+/// Tracebacks are stored with the terminal colors of the kernel.
+fn strip_ansi(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // skip the control sequence up to and including its final byte
+            if chars.next() == Some('[') {
+                chars.by_ref().find(|next| ('@'..='~').contains(next));
+            }
+            continue;
+        }
+
+        output.push(c);
+    }
+
+    output
+}
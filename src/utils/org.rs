@@ -0,0 +1,300 @@
+use std::fmt::Write as _;
+
+use syntect::parsing::SyntaxSet;
+
+use crate::utils::markup::{self, Delimiter, Link, List};
+
+const DELIMITERS: &[Delimiter] = &[
+    Delimiter {
+        marker: "=",
+        tag: "code",
+        verbatim: true,
+    },
+    Delimiter {
+        marker: "~",
+        tag: "code",
+        verbatim: true,
+    },
+    Delimiter {
+        marker: "*",
+        tag: "strong",
+        verbatim: false,
+    },
+    Delimiter {
+        marker: "/",
+        tag: "em",
+        verbatim: false,
+    },
+    Delimiter {
+        marker: "_",
+        tag: "u",
+        verbatim: false,
+    },
+    Delimiter {
+        marker: "+",
+        tag: "del",
+        verbatim: false,
+    },
+];
+
+/// Renders the commonly used subset of Org mode to HTML.
+#[tracing::instrument(skip_all)]
+pub(crate) fn render(syntaxes: &SyntaxSet, input: &str) -> String {
+    render_nested(syntaxes, input, 0)
+}
+
+/// Renders a document or a block `nesting` levels deep in one.
+fn render_nested(syntaxes: &SyntaxSet, input: &str, nesting: usize) -> String {
+    let mut output = String::new();
+    let mut paragraph = Vec::new();
+    let mut list = List::default();
+
+    let mut lines = input.lines().map(str::trim_end).peekable();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+
+        if trimmed.is_empty() {
+            // lists may have blank lines between their items
+            if lines.peek().is_some_and(|next| list_item(next).is_some()) {
+                markup::paragraph(&mut output, &mut paragraph, inline);
+            } else {
+                flush(&mut output, &mut paragraph, &mut list);
+            }
+            continue;
+        }
+
+        // blocks, e.g. `#+BEGIN_SRC rust`
+        if let Some(block) = keyword(trimmed, "#+begin_") {
+            flush(&mut output, &mut paragraph, &mut list);
+
+            let (kind, argument) = block.split_once(' ').unwrap_or((block, ""));
+            let kind = kind.to_ascii_lowercase();
+            let end = format!("#+end_{kind}");
+
+            let content = lines
+                .by_ref()
+                .take_while(|l| !l.trim().eq_ignore_ascii_case(&end))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            self::block(syntaxes, &mut output, &kind, argument, &content, nesting);
+
+            continue;
+        }
+
+        if let Some(title) = keyword(trimmed, "#+title:") {
+            flush(&mut output, &mut paragraph, &mut list);
+            let _ = writeln!(output, "<h1 class=\"title\">{}</h1>", inline(title.trim()));
+            continue;
+        }
+
+        // other keywords and comments
+        if trimmed.starts_with("#+") || trimmed == "#" || trimmed.starts_with("# ") {
+            continue;
+        }
+
+        // drawers, e.g. `:PROPERTIES:`
+        if is_drawer(trimmed) {
+            lines
+                .by_ref()
+                .take_while(|l| !l.trim().eq_ignore_ascii_case(":end:"))
+                .for_each(drop);
+            continue;
+        }
+
+        if let Some((level, title)) = heading(line) {
+            flush(&mut output, &mut paragraph, &mut list);
+            let _ = writeln!(output, "<h{level}>{}</h{level}>", inline(title));
+            continue;
+        }
+
+        if trimmed.len() >= 5 && trimmed.bytes().all(|b| b == b'-') {
+            flush(&mut output, &mut paragraph, &mut list);
+            output.push_str("<hr>\n");
+            continue;
+        }
+
+        // fixed width lines
+        if trimmed == ":" || trimmed.starts_with(": ") {
+            flush(&mut output, &mut paragraph, &mut list);
+
+            let mut content = vec![trimmed.get(2..).unwrap_or_default()];
+            while let Some(next) = lines.next_if(|l| {
+                let l = l.trim_start();
+                l == ":" || l.starts_with(": ")
+            }) {
+                content.push(next.trim_start().get(2..).unwrap_or_default());
+            }
+
+            let _ = writeln!(output, "<pre>{}</pre>", markup::escape(&content.join("\n")));
+            continue;
+        }
+
+        if trimmed.starts_with('|') {
+            flush(&mut output, &mut paragraph, &mut list);
+
+            let mut rows = vec![trimmed];
+            while let Some(next) = lines.next_if(|l| l.trim_start().starts_with('|')) {
+                rows.push(next.trim_start());
+            }
+
+            table(&mut output, &rows);
+            continue;
+        }
+
+        if let Some((depth, ordered, text)) = list_item(line) {
+            markup::paragraph(&mut output, &mut paragraph, inline);
+            list.push(depth, ordered, text);
+            continue;
+        }
+
+        if paragraph.is_empty() && line.starts_with(char::is_whitespace) && list.extend(trimmed) {
+            continue;
+        }
+
+        paragraph.push(trimmed);
+    }
+
+    flush(&mut output, &mut paragraph, &mut list);
+
+    output
+}
+
+fn flush(output: &mut String, paragraph: &mut Vec<&str>, list: &mut List) {
+    list.flush(output, inline);
+    markup::paragraph(output, paragraph, inline);
+}
+
+fn block(
+    syntaxes: &SyntaxSet,
+    output: &mut String,
+    kind: &str,
+    argument: &str,
+    content: &str,
+    nesting: usize,
+) {
+    let nested = || {
+        markup::nested(content, nesting, |nesting| {
+            render_nested(syntaxes, content, nesting)
+        })
+    };
+
+    match kind {
+        "src" => output.push_str(&markup::code_block(
+            syntaxes,
+            argument.split_whitespace().next(),
+            content,
+        )),
+        "example" | "verse" => {
+            let _ = writeln!(output, "<pre>{}</pre>", markup::escape(content));
+        }
+        "quote" => {
+            let _ = writeln!(output, "<blockquote>\n{}</blockquote>", nested());
+        }
+        // the contents of comment and export blocks are not shown
+        "comment" | "export" => {}
+        _ => {
+            let _ = writeln!(
+                output,
+                "<div class=\"{}\">\n{}</div>",
+                markup::escape(kind),
+                nested()
+            );
+        }
+    }
+}
+
+fn inline(text: &str) -> String {
+    markup::inline(text, DELIMITERS, &link)
+}
+
+fn link(text: &str) -> Option<Link> {
+    let Some(inner) = text.strip_prefix("[[") else {
+        return markup::bare_url(text);
+    };
+
+    let end = inner.find("]]")?;
+    let (href, label) = match inner[..end].split_once("][") {
+        Some((href, label)) => (href, Some(label.to_string())),
+        None => (&inner[..end], None),
+    };
+
+    Some(Link {
+        href: href.strip_prefix("file:").unwrap_or(href).to_string(),
+        label,
+        len: 2 + end + 2,
+    })
+}
+
+/// Strips a case insensitive `#+` keyword from the start of `line`.
+fn keyword<'l>(line: &'l str, keyword: &str) -> Option<&'l str> {
+    line.get(..keyword.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(keyword))
+        .map(|_| &line[keyword.len()..])
+}
+
+fn is_drawer(line: &str) -> bool {
+    line.len() > 2
+        && line.starts_with(':')
+        && line.ends_with(':')
+        && line[1..line.len() - 1]
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.bytes().take_while(|&b| b == b'*').count();
+    let title = line[level..].strip_prefix(' ')?.trim();
+
+    (level > 0).then_some((level.min(6), title))
+}
+
+fn list_item(line: &str) -> Option<(usize, bool, &str)> {
+    let text = line.trim_start();
+    let depth = line.len() - text.len();
+
+    // unindented stars are headings
+    let bullets: &[&str] = if depth == 0 {
+        &["- ", "+ "]
+    } else {
+        &["- ", "+ ", "* "]
+    };
+
+    for bullet in bullets {
+        if let Some(item) = text.strip_prefix(bullet) {
+            return Some((depth, false, item.trim()));
+        }
+    }
+
+    let number = text.bytes().take_while(u8::is_ascii_digit).count();
+
+    if number == 0 {
+        return None;
+    }
+
+    let item = text[number..]
+        .strip_prefix(". ")
+        .or_else(|| text[number..].strip_prefix(") "))?;
+
+    Some((depth, true, item.trim()))
+}
+
+fn table(output: &mut String, rows: &[&str]) {
+    // a rule after the first row turns it into the header
+    let has_header = rows.get(1).is_some_and(|row| row.starts_with("|-"));
+
+    output.push_str("<table>\n");
+
+    for (n, row) in rows.iter().filter(|row| !row.starts_with("|-")).enumerate() {
+        let cell = if n == 0 && has_header { "th" } else { "td" };
+
+        output.push_str("<tr>");
+        for column in row.trim_matches('|').split('|') {
+            let _ = write!(output, "<{cell}>{}</{cell}>", inline(column.trim()));
+        }
+        output.push_str("</tr>\n");
+    }
+
+    output.push_str("</table>\n");
+}
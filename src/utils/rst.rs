@@ -0,0 +1,382 @@
+use std::{collections::HashMap, fmt::Write as _};
+
+use syntect::parsing::SyntaxSet;
+
+use crate::utils::markup::{self, Delimiter, Link, List};
+
+const DELIMITERS: &[Delimiter] = &[
+    Delimiter {
+        marker: "``",
+        tag: "code",
+        verbatim: true,
+    },
+    Delimiter {
+        marker: "**",
+        tag: "strong",
+        verbatim: false,
+    },
+    Delimiter {
+        marker: "*",
+        tag: "em",
+        verbatim: false,
+    },
+    Delimiter {
+        marker: "`",
+        tag: "cite",
+        verbatim: false,
+    },
+];
+
+const ADMONITIONS: &[&str] = &[
+    "attention",
+    "caution",
+    "danger",
+    "error",
+    "hint",
+    "important",
+    "note",
+    "tip",
+    "warning",
+];
+
+/// Renders the commonly used subset of reStructuredText to HTML.
+#[tracing::instrument(skip_all)]
+pub(crate) fn render(syntaxes: &SyntaxSet, input: &str) -> String {
+    render_nested(syntaxes, input, 0)
+}
+
+/// Renders a document or a block `nesting` levels deep in one.
+fn render_nested(syntaxes: &SyntaxSet, input: &str, nesting: usize) -> String {
+    let lines: Vec<&str> = input.lines().map(str::trim_end).collect();
+
+    let targets = targets(&lines);
+    let inline = |text: &str| markup::inline(text, DELIMITERS, &|rest| link(&targets, rest));
+
+    let mut output = String::new();
+    let mut paragraph = Vec::new();
+    let mut list = List::default();
+    // section adornment styles in the order they first appeared, which decides the heading level
+    let mut styles: Vec<(u8, bool)> = Vec::new();
+
+    let mut i = 0;
+
+    while let Some(&line) = lines.get(i) {
+        if line.is_empty() {
+            if !continues_list(&lines[i..]) {
+                list.flush(&mut output, inline);
+            }
+            markup::paragraph(&mut output, &mut paragraph, inline);
+            i += 1;
+            continue;
+        }
+
+        if paragraph.is_empty()
+            && let Some((style, title, len)) = section_title(&lines[i..])
+        {
+            list.flush(&mut output, inline);
+            let level = level(&mut styles, style);
+            let _ = writeln!(output, "<h{level}>{}</h{level}>", inline(title));
+            i += len;
+            continue;
+        }
+
+        // transition
+        if paragraph.is_empty()
+            && is_adornment(line)
+            && line.len() >= 4
+            && lines.get(i + 1).is_none_or(|next| next.is_empty())
+        {
+            list.flush(&mut output, inline);
+            output.push_str("<hr>\n");
+            i += 1;
+            continue;
+        }
+
+        // explicit markup, i.e. directives, comments and link targets
+        if let Some(explicit) = line.strip_prefix("..")
+            && (explicit.is_empty() || explicit.starts_with(' '))
+        {
+            list.flush(&mut output, inline);
+            markup::paragraph(&mut output, &mut paragraph, inline);
+
+            let (body, next) = indented_block(&lines, i + 1);
+            i = next;
+
+            if let Some((name, argument)) = explicit.trim().split_once("::") {
+                directive(
+                    syntaxes,
+                    &mut output,
+                    name.trim(),
+                    argument.trim(),
+                    &body,
+                    nesting,
+                    inline,
+                );
+            }
+
+            continue;
+        }
+
+        if let Some((depth, ordered, text)) = list_item(line) {
+            markup::paragraph(&mut output, &mut paragraph, inline);
+            list.push(depth, ordered, text);
+            i += 1;
+            continue;
+        }
+
+        if paragraph.is_empty() && line.starts_with(' ') {
+            // continuation of a list item
+            if list.extend(line.trim_start()) {
+                i += 1;
+                continue;
+            }
+
+            // indented text on its own is a block quote
+            let (body, next) = indented_block(&lines, i);
+            let _ = writeln!(
+                output,
+                "<blockquote>\n{}</blockquote>",
+                markup::nested(&body, nesting, |nesting| render_nested(
+                    syntaxes, &body, nesting
+                ))
+            );
+            i = next;
+            continue;
+        }
+
+        i += 1;
+
+        // a paragraph ending with `::` introduces a literal block
+        if let Some(text) = literal_block_intro(line) {
+            if !text.is_empty() {
+                paragraph.push(text);
+            }
+
+            list.flush(&mut output, inline);
+            markup::paragraph(&mut output, &mut paragraph, inline);
+
+            let (body, next) = indented_block(&lines, i);
+            if !body.is_empty() {
+                let _ = writeln!(output, "<pre>{}</pre>", markup::escape(&body));
+            }
+            i = next;
+            continue;
+        }
+
+        paragraph.push(line.trim_start());
+    }
+
+    list.flush(&mut output, inline);
+    markup::paragraph(&mut output, &mut paragraph, inline);
+
+    output
+}
+
+/// Writes the supported directives, everything else is treated as a comment.
+fn directive(
+    syntaxes: &SyntaxSet,
+    output: &mut String,
+    name: &str,
+    argument: &str,
+    body: &str,
+    nesting: usize,
+    inline: impl Fn(&str) -> String,
+) {
+    match name {
+        "code" | "code-block" | "sourcecode" => {
+            // skip directive options like `:linenos:`
+            let code = body
+                .lines()
+                .skip_while(|l| l.starts_with(':'))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let code = code.trim_start_matches('\n');
+
+            output.push_str(&markup::code_block(syntaxes, Some(argument), code));
+        }
+        "image" | "figure" if markup::is_safe_href(argument) => {
+            let _ = writeln!(
+                output,
+                "<p><img src=\"{0}\" alt=\"{0}\"></p>",
+                markup::escape(argument),
+            );
+        }
+        name if ADMONITIONS.contains(&name) => {
+            let _ = writeln!(
+                output,
+                "<div class=\"admonition {name}\"><strong>{}:</strong>",
+                name.to_ascii_uppercase(),
+            );
+            if !argument.is_empty() {
+                let _ = writeln!(output, "<p>{}</p>", inline(argument));
+            }
+            output.push_str(&markup::nested(body, nesting, |nesting| {
+                render_nested(syntaxes, body, nesting)
+            }));
+            output.push_str("</div>\n");
+        }
+        _ => {}
+    }
+}
+
+/// Collects the external hyperlink targets, e.g. `.. _name: https://example.com`.
+fn targets(lines: &[&str]) -> HashMap<String, String> {
+    lines
+        .iter()
+        .filter_map(|line| line.trim_start().strip_prefix(".. _"))
+        .filter_map(|target| {
+            let (name, url) = target.split_once(": ")?;
+            let name = name.trim_matches('`').to_lowercase();
+
+            Some((name, url.trim().to_string()))
+        })
+        .collect()
+}
+
+fn link(targets: &HashMap<String, String>, text: &str) -> Option<Link> {
+    let Some(inner) = text.strip_prefix('`') else {
+        return markup::bare_url(text);
+    };
+
+    let end = inner.find('`')?;
+    let after = inner[end..].strip_prefix("`_")?;
+
+    // anonymous references end with two underscores
+    let len = 1 + end + 2 + usize::from(after.starts_with('_'));
+    let reference = &inner[..end];
+
+    // embedded URL, e.g. `label <https://example.com>`_
+    if let Some((label, href)) = reference
+        .strip_suffix('>')
+        .and_then(|reference| reference.rsplit_once('<'))
+    {
+        let label = label.trim();
+
+        return Some(Link {
+            href: href.trim().to_string(),
+            label: (!label.is_empty()).then(|| label.to_string()),
+            len,
+        });
+    }
+
+    let href = targets.get(&reference.to_lowercase())?;
+
+    Some(Link {
+        href: href.clone(),
+        label: Some(reference.to_string()),
+        len,
+    })
+}
+
+/// Lists may have blank lines between their items.
+fn continues_list(lines: &[&str]) -> bool {
+    lines
+        .iter()
+        .find(|line| !line.is_empty())
+        .is_some_and(|next| next.starts_with(' ') || list_item(next).is_some())
+}
+
+/// Strips the `::` that introduces a literal block.
+///
+/// `text::` keeps a single colon while `text ::` loses both.
+fn literal_block_intro(line: &str) -> Option<&str> {
+    let text = line.strip_suffix("::")?.trim();
+
+    if text.is_empty() || line.ends_with(" ::") {
+        return Some(text);
+    }
+
+    Some(line[..line.len() - 1].trim_start())
+}
+
+/// Finds a section title at the start of `lines`.
+///
+/// Returns the adornment style, the title and how many lines the title takes up.
+fn section_title<'l>(lines: &[&'l str]) -> Option<((u8, bool), &'l str, usize)> {
+    match *lines {
+        // with an overline
+        [overline, title, underline, ..]
+            if is_adornment(overline) && overline == underline && !title.trim().is_empty() =>
+        {
+            Some(((overline.as_bytes()[0], true), title.trim(), 3))
+        }
+        // with only an underline
+        [title, underline, ..]
+            if !title.starts_with(' ')
+                && is_adornment(underline)
+                && underline.len() >= title.chars().count().min(3) =>
+        {
+            Some(((underline.as_bytes()[0], false), title, 2))
+        }
+        _ => None,
+    }
+}
+
+fn level(styles: &mut Vec<(u8, bool)>, style: (u8, bool)) -> usize {
+    let position = styles.iter().position(|s| *s == style).unwrap_or_else(|| {
+        styles.push(style);
+        styles.len() - 1
+    });
+
+    (position + 1).min(6)
+}
+
+fn is_adornment(line: &str) -> bool {
+    let bytes = line.as_bytes();
+
+    bytes.len() >= 2
+        && b"=-~^\"'`#*+:._".contains(&bytes[0])
+        && bytes.iter().all(|b| *b == bytes[0])
+}
+
+/// Returns the dedented block of lines starting at `start` and the index of the line after it.
+fn indented_block(lines: &[&str], start: usize) -> (String, usize) {
+    let end = lines[start.min(lines.len())..]
+        .iter()
+        .position(|line| !line.is_empty() && !line.starts_with(char::is_whitespace))
+        .map_or(lines.len(), |len| start + len);
+
+    let block = &lines[start.min(end)..end];
+
+    let indent = block
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or_default();
+
+    let text = block
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    (text.trim_matches('\n').to_string(), end)
+}
+
+fn list_item(line: &str) -> Option<(usize, bool, &str)> {
+    let text = line.trim_start();
+    let depth = line.len() - text.len();
+
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = text.strip_prefix(bullet) {
+            return Some((depth, false, item.trim()));
+        }
+    }
+
+    let number = if text.starts_with('#') {
+        1
+    } else {
+        text.bytes().take_while(u8::is_ascii_digit).count()
+    };
+
+    if number == 0 {
+        return None;
+    }
+
+    let item = text[number..]
+        .strip_prefix(". ")
+        .or_else(|| text[number..].strip_prefix(") "))?;
+
+    Some((depth, true, item.trim()))
+}
@@ -0,0 +1,62 @@
+use crate::{error::Result, git::Repository};
+
+/// The branches and tags a page can be switched to, keeping the rest of the page.
+pub(crate) struct RefSwitcher {
+    /// What the page shows now
+    pub current: String,
+    pub branches: Vec<String>,
+    /// Newest first
+    pub tags: Vec<String>,
+    /// The page for a ref is at `{prefix}{ref}{suffix}`
+    pub prefix: String,
+    pub suffix: String,
+    /// The page for every branch and tag at once, if there is one
+    pub all: Option<String>,
+}
+
+impl RefSwitcher {
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn new(
+        repo: &Repository,
+        current: String,
+        prefix: String,
+        suffix: String,
+    ) -> Result<Self> {
+        let mut branches: Vec<String> = repo
+            .branches()?
+            .iter()
+            .filter_map(|branch| branch.shorthand().map(ToString::to_string))
+            .collect();
+        branches.sort_unstable();
+
+        let mut tags = repo.tag_entries()?;
+        tags.sort_by_key(|tag| std::cmp::Reverse(tag.signature.when()));
+
+        Ok(Self {
+            current,
+            branches,
+            tags: tags.into_iter().map(|tag| tag.tag).collect(),
+            prefix,
+            suffix,
+            all: None,
+        })
+    }
+
+    /// Only links the page for every branch and tag, for pages that must not
+    /// change when they do.
+    pub(crate) const fn pinned(current: String, all: String) -> Self {
+        Self {
+            current,
+            branches: Vec::new(),
+            tags: Vec::new(),
+            prefix: String::new(),
+            suffix: String::new(),
+            all: Some(all),
+        }
+    }
+
+    pub(crate) fn with_all(mut self, url: String) -> Self {
+        self.all = Some(url);
+        self
+    }
+}
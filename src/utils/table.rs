@@ -0,0 +1,81 @@
+use std::fmt::Write as _;
+
+use crate::{error::Result, utils::markup::escape};
+
+/// Rows shown before linking to the raw file instead.
+const MAX_ROWS: usize = 1000;
+
+/// Renders delimiter separated values as a table, using the first row as the header.
+///
+/// Every row keeps the `L<n>` anchor of the line it starts on so links to the
+/// source view keep working.
+#[tracing::instrument(skip_all)]
+pub(crate) fn render(input: &str, delimiter: u8, prefix: &str, raw: &str) -> Result<String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(input.as_bytes());
+
+    let mut output = String::from("<table class=\"csv\">\n");
+    let mut truncated = false;
+    let mut records = reader.records().enumerate().peekable();
+
+    if records.peek().is_none() {
+        output.push_str("<tbody>\n");
+    }
+
+    for (n, record) in records {
+        // a row past the limit is not shown, so it being invalid does not matter
+        if n > MAX_ROWS {
+            truncated = true;
+            break;
+        }
+
+        let record = record?;
+
+        let line = record.position().map_or(n + 1, |position| {
+            usize::try_from(position.line()).unwrap_or(usize::MAX)
+        });
+
+        if n == 0 {
+            output.push_str("<thead>\n");
+        }
+
+        // the header is made of header cells only
+        let cell = if n == 0 { "th" } else { "td" };
+        let _ = write!(
+            output,
+            "<tr id=\"L{line}\"><{cell}><a href=\"{prefix}#L{line}\" class=\"line\">{line}</a></{cell}>"
+        );
+
+        for field in &record {
+            let field = field.trim();
+
+            if n == 0 {
+                let _ = write!(output, "<th scope=\"col\">{}</th>", escape(field));
+            } else if field.parse::<f64>().is_ok() {
+                let _ = write!(output, "<td class=\"number\">{}</td>", escape(field));
+            } else {
+                let _ = write!(output, "<td>{}</td>", escape(field));
+            }
+        }
+
+        output.push_str("</tr>\n");
+
+        if n == 0 {
+            output.push_str("</thead>\n<tbody>\n");
+        }
+    }
+
+    output.push_str("</tbody>\n</table>\n");
+
+    if truncated {
+        let _ = writeln!(
+            output,
+            "<p>Only the first {MAX_ROWS} rows are shown, <a href=\"{raw}\">view the raw file</a> for the rest.</p>"
+        );
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,170 @@
+//! Streams tarballs of a tree, and refuses ones larger than configured before
+//! any of it is sent.
+
+use std::{fs, io::Read as _, path::PathBuf};
+
+use axum::{
+    body::{self, Body},
+    http::{Request, StatusCode, header},
+    response::Response,
+};
+use bile::{Bile, config::Config};
+use flate2::read::GzDecoder;
+use git2::{Repository, Signature, Time};
+use tower::util::ServiceExt as _;
+
+/// A repository with two files in a directory, served with `config`.
+fn fixture(name: &str, config: Config) -> Bile {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("archive-{name}"));
+    let _ = fs::remove_dir_all(&root);
+
+    let dir = root.join("archive.git");
+    let repo = Repository::init_bare(&dir).expect("failed to create repository");
+    fs::write(dir.join("git-daemon-export-ok"), "").expect("failed to export repository");
+
+    let mut src = repo.treebuilder(None).expect("failed to build tree");
+    for (name, content) in [
+        ("lib.rs", "pub fn one() {}\n"),
+        ("main.rs", "fn main() {}\n"),
+    ] {
+        let blob = repo.blob(content.as_bytes()).expect("failed to write blob");
+        src.insert(name, blob, 0o100_644)
+            .expect("failed to insert blob");
+    }
+    let src = src.write().expect("failed to write tree");
+
+    let mut builder = repo.treebuilder(None).expect("failed to build tree");
+    builder
+        .insert("src", src, 0o040_000)
+        .expect("failed to insert tree");
+    let tree = builder.write().expect("failed to write tree");
+    let tree = repo.find_tree(tree).expect("tree is missing");
+
+    let signature = Signature::new(
+        "Fixture",
+        "fixture@example.com",
+        &Time::new(1_700_000_000, 0),
+    )
+    .expect("failed to create signature");
+    repo.commit(
+        Some("refs/heads/main"),
+        &signature,
+        &signature,
+        "files\n",
+        &tree,
+        &[],
+    )
+    .expect("failed to commit");
+    repo.set_head("refs/heads/main")
+        .expect("failed to set HEAD");
+
+    Bile::init(Config {
+        project_root: root.canonicalize().expect("failed to canonicalize root"),
+        ..config
+    })
+}
+
+async fn get(bile: &Bile, uri: &str, accept: &str) -> Response {
+    let request = Request::builder()
+        .uri(uri)
+        .header(header::ACCEPT, accept)
+        .body(Body::empty())
+        .expect("failed to build request");
+
+    bile.routes()
+        .oneshot(request)
+        .await
+        .expect("failed to send request")
+}
+
+async fn bytes(response: Response) -> Vec<u8> {
+    body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("failed to read body")
+        .to_vec()
+}
+
+#[tokio::test]
+async fn streams_the_tree() {
+    let bile = fixture("streams", Config::default());
+
+    let response = get(&bile, "/archive.git/archive/main.tar.gz", "*/*").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/gzip");
+    assert_eq!(
+        response.headers()[header::CONTENT_DISPOSITION],
+        "attachment; filename=\"archive-main.tar.gz\""
+    );
+
+    let archive = bytes(response).await;
+    let mut archive = tar::Archive::new(GzDecoder::new(&archive[..]));
+
+    let mut files = Vec::new();
+    for entry in archive.entries().expect("failed to read archive") {
+        let mut entry = entry.expect("failed to read entry");
+        let path = entry
+            .path()
+            .expect("path is not valid")
+            .to_string_lossy()
+            .into_owned();
+
+        let mut content = String::new();
+        entry
+            .read_to_string(&mut content)
+            .expect("failed to read file");
+
+        files.push((path, content));
+    }
+
+    assert_eq!(
+        files,
+        [
+            ("archive-main/src".to_string(), String::new()),
+            (
+                "archive-main/src/lib.rs".to_string(),
+                "pub fn one() {}\n".to_string()
+            ),
+            (
+                "archive-main/src/main.rs".to_string(),
+                "fn main() {}\n".to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn refuses_large_trees() {
+    for (name, config) in [
+        (
+            "entries",
+            Config {
+                max_archive_entries: 2,
+                ..Config::default()
+            },
+        ),
+        (
+            "size",
+            Config {
+                max_archive_size: 20,
+                ..Config::default()
+            },
+        ),
+    ] {
+        let bile = fixture(name, config);
+
+        let response = get(&bile, "/archive.git/archive/main.tar.gz", "text/html").await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{name}");
+        let html = String::from_utf8(bytes(response).await).expect("body is not UTF-8");
+        assert!(html.contains("too large to download"), "{name}: {html}");
+
+        let response = get(
+            &bile,
+            "/archive.git/archive/main.tar.gz",
+            "application/json",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{name}");
+        let json = String::from_utf8(bytes(response).await).expect("body is not UTF-8");
+        assert!(json.contains("too large to download"), "{name}: {json}");
+    }
+}
@@ -0,0 +1,641 @@
+//! Runs the same queries against every repository backend, on fixture
+//! repositories built here so the expected results are known.
+//!
+//! Run with `--features gix` to include gitoxide. Set
+//! `BILE_CONFORMANCE_REPOS` to repositories, separated like `PATH`, to also
+//! compare the backends on them, with `--nocapture` to see how long each took.
+
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{self, Body},
+    http::{Request, StatusCode},
+};
+#[cfg(feature = "gix")]
+use bile::backend::Gitoxide;
+use bile::{
+    Bile,
+    backend::{Backend, Commit, Entry, Id, Kind, Libgit2, Signature, Sorting, Tag},
+    config::Config,
+};
+use git2::{FileMode, Oid, Repository};
+use tower::util::ServiceExt as _;
+
+const TIME: i64 = 1_700_000_000;
+const LOGO: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+/// A file in a fixture commit, with its path, content and mode.
+type File = (&'static str, &'static [u8], FileMode);
+
+struct Fixture {
+    dir: PathBuf,
+    /// A repository without commits
+    empty: PathBuf,
+    /// A repository with `HEAD` pointing at a commit instead of a branch
+    detached: PathBuf,
+    commits: Vec<Id>,
+    logo: Id,
+}
+
+/// Builds the fixture repositories once for all tests.
+fn fixture() -> &'static Fixture {
+    static FIXTURE: OnceLock<Fixture> = OnceLock::new();
+
+    FIXTURE.get_or_init(|| {
+        let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("backend");
+        let _ = fs::remove_dir_all(&root);
+
+        let dir = root.join("history.git");
+        let repo = Repository::init_bare(&dir).expect("failed to create repository");
+        fs::write(dir.join("git-daemon-export-ok"), "").expect("failed to export repository");
+        repo.set_head("refs/heads/main")
+            .expect("failed to set HEAD");
+
+        let readme: File = ("README.md", b"# fixture\n", FileMode::Blob);
+        let readme_2: File = ("README.md", b"# fixture\n\nnow with more\n", FileMode::Blob);
+        let main: File = ("src/main.rs", b"fn main() {}\n", FileMode::Blob);
+        let main_2: File = (
+            "src/main.rs",
+            b"fn main() {\n    run();\n}\n",
+            FileMode::Blob,
+        );
+        let guide: File = ("docs/guide.md", b"# guide\n", FileMode::Blob);
+        let guide_2: File = ("docs/guide.md", b"# guide\n\nstep one\n", FileMode::Blob);
+        let logo: File = ("logo.png", LOGO, FileMode::Blob);
+        let run: File = ("run.sh", b"#!/bin/sh\n", FileMode::BlobExecutable);
+
+        let c1 = commit(&repo, 1, &[], &[readme, main], "root\n");
+        // made with a clock ahead, it still comes after its children
+        let c2 = commit(&repo, 9, &[c1], &[readme, main_2], "change main\n");
+        let c3 = commit(
+            &repo,
+            3,
+            &[c2],
+            &[readme, main_2, guide, logo, run],
+            "add docs\n\nwith a body\n",
+        );
+        let c4 = commit(
+            &repo,
+            4,
+            &[c3],
+            &[readme, main_2, guide_2, logo, run],
+            "extend guide\n",
+        );
+        let c5 = commit(
+            &repo,
+            5,
+            &[c3],
+            &[readme_2, main_2, guide, logo, run],
+            "extend readme\n",
+        );
+        let c6 = commit(
+            &repo,
+            6,
+            &[c5, c4],
+            &[readme_2, main_2, guide_2, logo, run],
+            "Merge branch 'feature'\n",
+        );
+
+        repo.reference("refs/heads/main", c6, true, "fixture")
+            .expect("failed to create branch");
+        repo.reference("refs/heads/feature", c4, true, "fixture")
+            .expect("failed to create branch");
+
+        // lightweight, annotated and a tag of something else than a commit
+        repo.reference("refs/tags/v0.1", c1, true, "fixture")
+            .expect("failed to create tag");
+        let object = repo.find_object(c3, None).expect("commit is missing");
+        repo.tag("v1.0", &object, &signature(7, 60), "Release 1.0\n", false)
+            .expect("failed to create tag");
+        let logo_id = repo.blob(LOGO).expect("failed to write blob");
+        let object = repo.find_object(logo_id, None).expect("blob is missing");
+        repo.tag("logo", &object, &signature(8, 0), "The logo\n", false)
+            .expect("failed to create tag");
+
+        let empty = root.join("empty.git");
+        Repository::init_bare(&empty).expect("failed to create repository");
+
+        let detached = root.join("detached.git");
+        let repo = Repository::init_bare(&detached).expect("failed to create repository");
+        let id = commit(&repo, 1, &[], &[readme], "root\n");
+        repo.set_head_detached(id).expect("failed to detach HEAD");
+
+        Fixture {
+            dir,
+            empty,
+            detached,
+            commits: [c1, c2, c3, c4, c5, c6].map(Id::from).to_vec(),
+            logo: logo_id.into(),
+        }
+    })
+}
+
+/// Signatures a minute apart, so the history has one order.
+fn signature(minute: i64, offset: i32) -> git2::Signature<'static> {
+    git2::Signature::new(
+        "Fixture",
+        "fixture@example.com",
+        &git2::Time::new(TIME + minute * 60, offset),
+    )
+    .expect("failed to create signature")
+}
+
+fn commit(repo: &Repository, minute: i64, parents: &[Oid], files: &[File], message: &str) -> Oid {
+    let tree = tree(repo, files, "");
+    let tree = repo.find_tree(tree).expect("tree is missing");
+    let parents: Vec<_> = parents
+        .iter()
+        .map(|id| repo.find_commit(*id).expect("parent is missing"))
+        .collect();
+
+    repo.commit(
+        None,
+        &signature(minute, 120),
+        &signature(minute, -300),
+        message,
+        &tree,
+        &parents.iter().collect::<Vec<_>>(),
+    )
+    .expect("failed to commit")
+}
+
+/// Writes the files below `prefix` as a tree.
+fn tree(repo: &Repository, files: &[File], prefix: &str) -> Oid {
+    let mut builder = repo.treebuilder(None).expect("failed to build tree");
+    let mut dirs = BTreeMap::new();
+
+    for (path, content, mode) in files {
+        let Some(path) = path.strip_prefix(prefix) else {
+            continue;
+        };
+
+        match path.split_once('/') {
+            Some((dir, _)) => {
+                dirs.insert(dir, format!("{prefix}{dir}/"));
+            }
+            None => {
+                let blob = repo.blob(content).expect("failed to write blob");
+                builder
+                    .insert(path, blob, (*mode).into())
+                    .expect("failed to insert blob");
+            }
+        }
+    }
+
+    for (dir, prefix) in dirs {
+        let tree = tree(repo, files, &prefix);
+        builder
+            .insert(dir, tree, FileMode::Tree.into())
+            .expect("failed to insert tree");
+    }
+
+    builder.write().expect("failed to write tree")
+}
+
+fn entry<B: Backend>(backend: &B, tree: Id, path: &str) -> Option<Entry> {
+    backend
+        .entry(tree, Path::new(path))
+        .expect("failed to look up entry")
+}
+
+fn conforms<B: Backend>() {
+    let fixture = fixture();
+    let [c1, c2, c3, c4, c5, c6] = fixture.commits[..] else {
+        unreachable!();
+    };
+
+    let backend = B::open(&fixture.dir).expect("failed to open repository");
+
+    assert_eq!(backend.head_name().expect("failed to get HEAD"), "main");
+
+    // commits by branch, tag, id and revision
+    let commit = |spec: &str| {
+        let id = backend.resolve(spec).expect("failed to resolve commit");
+        let commit = backend
+            .commit(spec)
+            .expect("failed to look up commit")
+            .map(|commit| commit.id);
+        assert_eq!(id, commit, "{spec} resolves to another commit");
+
+        id
+    };
+    assert_eq!(commit("main"), Some(c6));
+    assert_eq!(commit("HEAD"), Some(c6));
+    assert_eq!(commit("feature"), Some(c4));
+    assert_eq!(commit("v0.1"), Some(c1));
+    assert_eq!(commit("v1.0"), Some(c3));
+    assert_eq!(commit("main~1"), Some(c5));
+    assert_eq!(commit(&c2.to_string()), Some(c2));
+    assert_eq!(commit("missing"), None);
+    assert_eq!(commit("logo"), None);
+
+    let merge = backend
+        .commit("main")
+        .expect("failed to look up commit")
+        .expect("commit is missing");
+    assert_eq!(merge.parents, [c5, c4]);
+    assert_eq!(merge.message, "Merge branch 'feature'\n");
+    assert_eq!(
+        merge.author,
+        Signature {
+            name: "Fixture".to_string(),
+            email: "fixture@example.com".to_string(),
+            time: TIME + 6 * 60,
+            offset: 120,
+        }
+    );
+    assert_eq!(merge.committer.offset, -300);
+
+    let docs = backend
+        .commit(&c3.to_string())
+        .expect("failed to look up commit")
+        .expect("commit is missing");
+    assert_eq!(docs.message, "add docs\n\nwith a body\n");
+    assert_eq!(docs.parents, [c2]);
+
+    // history, newest first
+    let history = |path: Option<&str>, amount| {
+        backend
+            .history(c6, path.map(Path::new), amount)
+            .expect("failed to walk history")
+    };
+    assert_eq!(history(None, 100), [c6, c5, c4, c3, c2, c1]);
+    assert_eq!(history(None, 2), [c6, c5]);
+    assert_eq!(history(Some("README.md"), 100), [c6, c5, c1]);
+    assert_eq!(history(Some("docs/guide.md"), 100), [c6, c4, c3]);
+    assert_eq!(history(Some("src"), 100), [c2, c1]);
+    assert_eq!(history(Some("missing"), 100), []);
+    assert_eq!(
+        backend
+            .history(c4, Some(Path::new("README.md")), 100)
+            .expect("failed to walk history"),
+        [c1]
+    );
+
+    // walks in each order, and of what is not reachable from another commit
+    let walk = |tips: &[Id], hidden: &[Id], sorting| {
+        backend
+            .walk(tips, hidden, sorting)
+            .expect("failed to walk history")
+            .collect::<Result<Vec<_>, _>>()
+            .expect("failed to walk history")
+    };
+    assert_eq!(walk(&[c6], &[], Sorting::Time), [c6, c5, c4, c3, c2, c1]);
+    assert_eq!(walk(&[c6], &[], Sorting::Date), [c6, c5, c4, c3, c2, c1]);
+    assert_eq!(walk(&[c6], &[], Sorting::Topo), [c6, c4, c5, c3, c2, c1]);
+    assert_eq!(walk(&[c4, c5], &[], Sorting::Date), [c5, c4, c3, c2, c1]);
+    assert_eq!(walk(&[c6], &[c3], Sorting::Time), [c6, c5, c4]);
+    assert_eq!(walk(&[c4], &[c5], Sorting::Time), [c4]);
+
+    let changes = |commit, path: &str| {
+        backend
+            .changes(commit, Path::new(path))
+            .expect("failed to check for changes")
+    };
+    assert!(changes(c1, "README.md"));
+    assert!(!changes(c2, "README.md"));
+    assert!(changes(c2, "src"));
+    // the merge has the guide of one parent and the readme of the other
+    assert!(changes(c6, "README.md"));
+    assert!(changes(c6, "docs/guide.md"));
+    assert!(!changes(c6, "src/main.rs"));
+    assert!(!changes(c6, "missing"));
+
+    // trees and blobs
+    let tree = merge.tree;
+    let names: Vec<_> = backend
+        .tree(tree)
+        .expect("failed to read tree")
+        .into_iter()
+        .map(|entry| (entry.name, entry.kind, entry.mode))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("README.md".to_string(), Kind::Blob, 0o100_644),
+            ("docs".to_string(), Kind::Tree, 0o40_000),
+            ("logo.png".to_string(), Kind::Blob, 0o100_644),
+            ("run.sh".to_string(), Kind::Blob, 0o100_755),
+            ("src".to_string(), Kind::Tree, 0o40_000),
+        ]
+    );
+
+    let main = entry(&backend, tree, "src/main.rs").expect("entry is missing");
+    assert_eq!(main.name, "main.rs");
+    assert_eq!(main.kind, Kind::Blob);
+    assert_eq!(
+        backend.blob(main.id).expect("failed to read blob"),
+        b"fn main() {\n    run();\n}\n"
+    );
+    assert_eq!(
+        entry(&backend, tree, "src").map(|entry| entry.kind),
+        Some(Kind::Tree)
+    );
+    assert_eq!(
+        entry(&backend, tree, "logo.png").map(|entry| entry.id),
+        Some(fixture.logo)
+    );
+    assert_eq!(
+        backend.blob(fixture.logo).expect("failed to read blob"),
+        LOGO
+    );
+    assert_eq!(entry(&backend, tree, "missing"), None);
+    assert_eq!(entry(&backend, tree, "src/missing"), None);
+    assert_eq!(entry(&backend, tree, "README.md/missing"), None);
+
+    // tags, with the committer for lightweight tags
+    let mut tags = backend.tags().expect("failed to read tags");
+    tags.sort_by(|a, b| a.name.cmp(&b.name));
+    let committer = backend
+        .commit("v0.1")
+        .expect("failed to look up commit")
+        .expect("commit is missing")
+        .committer;
+    assert_eq!(
+        tags,
+        [
+            Tag {
+                name: "logo".to_string(),
+                target: fixture.logo,
+                message: "The logo\n".to_string(),
+                tagger: Signature {
+                    time: TIME + 8 * 60,
+                    offset: 0,
+                    ..committer.clone()
+                },
+            },
+            Tag {
+                name: "v0.1".to_string(),
+                target: c1,
+                message: String::new(),
+                tagger: committer.clone(),
+            },
+            Tag {
+                name: "v1.0".to_string(),
+                target: c3,
+                message: "Release 1.0\n".to_string(),
+                tagger: Signature {
+                    time: TIME + 7 * 60,
+                    offset: 60,
+                    ..committer
+                },
+            },
+        ]
+    );
+
+    // a repository without commits, and one not on a branch
+    let empty = B::open(&fixture.empty).expect("failed to open repository");
+    assert!(empty.head_name().is_err());
+    assert_eq!(
+        empty.commit("HEAD").expect("failed to look up commit"),
+        None
+    );
+    assert_eq!(empty.tags().expect("failed to read tags"), []);
+
+    let detached = B::open(&fixture.detached).expect("failed to open repository");
+    assert_eq!(detached.head_name().expect("failed to get HEAD"), "HEAD");
+    assert!(
+        detached
+            .commit("HEAD")
+            .expect("failed to look up commit")
+            .is_some()
+    );
+}
+
+#[test]
+fn libgit2_conforms() {
+    conforms::<Libgit2>();
+}
+
+#[cfg(feature = "gix")]
+#[test]
+fn gitoxide_conforms() {
+    conforms::<Gitoxide>();
+}
+
+/// Everything the backends are asked about a repository.
+#[derive(Debug, PartialEq, Eq)]
+struct Survey {
+    head: Option<Commit>,
+    history: Vec<Commit>,
+    /// The history walked in topological order
+    topo: Vec<Id>,
+    /// The history of each entry at the top of the tree
+    paths: Vec<Vec<Id>>,
+    tree: Vec<Entry>,
+    blobs: Vec<Vec<u8>>,
+    tags: Vec<Tag>,
+}
+
+/// Asks a backend everything the pages of a repository would.
+fn survey<B: Backend>(dir: &Path) -> (Survey, Duration) {
+    let start = Instant::now();
+
+    let backend = B::open(dir).expect("failed to open repository");
+    let head = backend.commit("HEAD").expect("failed to look up HEAD");
+
+    let mut survey = Survey {
+        head: head.clone(),
+        history: Vec::new(),
+        topo: Vec::new(),
+        paths: Vec::new(),
+        tree: Vec::new(),
+        blobs: Vec::new(),
+        tags: backend.tags().expect("failed to read tags"),
+    };
+    survey.tags.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if let Some(head) = head {
+        survey.history = backend
+            .history(head.id, None, 10_000)
+            .expect("failed to walk history")
+            .into_iter()
+            .map(|id| {
+                backend
+                    .commit(&id.to_string())
+                    .expect("failed to look up commit")
+                    .expect("commit is missing")
+            })
+            .collect();
+        survey.topo = backend
+            .walk(&[head.id], &[], Sorting::Topo)
+            .expect("failed to walk history")
+            .take(10_000)
+            .collect::<Result<_, _>>()
+            .expect("failed to walk history");
+        survey.tree = backend.tree(head.tree).expect("failed to read tree");
+
+        for entry in &survey.tree {
+            let history = backend
+                .history(head.id, Some(Path::new(&entry.name)), 20)
+                .expect("failed to walk history");
+            survey.paths.push(history);
+
+            if entry.kind == Kind::Blob {
+                survey
+                    .blobs
+                    .push(backend.blob(entry.id).expect("failed to read blob"));
+            }
+        }
+    }
+
+    (survey, start.elapsed())
+}
+
+/// Compares two surveys one part at a time, so a difference in a large
+/// repository can be found.
+#[cfg(feature = "gix")]
+fn agree(dir: &Path, a: &Survey, b: &Survey) {
+    let dir = dir.display();
+
+    assert_eq!(a.head, b.head, "HEAD differs in {dir}");
+
+    if let Some(i) =
+        (0..a.history.len().max(b.history.len())).find(|i| a.history.get(*i) != b.history.get(*i))
+    {
+        panic!(
+            "history of {dir} differs at commit {i}:\n{:?}\n{:?}",
+            a.history.get(i),
+            b.history.get(i),
+        );
+    }
+
+    assert!(a.topo == b.topo, "topological order differs in {dir}");
+    assert_eq!(a.tree, b.tree, "tree differs in {dir}");
+
+    for ((entry, a), b) in a.tree.iter().zip(&a.paths).zip(&b.paths) {
+        assert_eq!(a, b, "history of {} differs in {dir}", entry.name);
+    }
+
+    assert!(a.blobs == b.blobs, "blobs differ in {dir}");
+    assert_eq!(a.tags, b.tags, "tags differ in {dir}");
+}
+
+#[test]
+fn backends_agree() {
+    let fixture = fixture();
+    let mut dirs = vec![
+        fixture.dir.clone(),
+        fixture.empty.clone(),
+        fixture.detached.clone(),
+    ];
+
+    if let Some(repos) = env::var_os("BILE_CONFORMANCE_REPOS") {
+        dirs.extend(env::split_paths(&repos));
+    }
+
+    for dir in dirs {
+        let (libgit2, took) = survey::<Libgit2>(&dir);
+        eprintln!("{}: libgit2 took {took:?}", dir.display());
+
+        #[cfg(feature = "gix")]
+        {
+            let (gitoxide, took) = survey::<Gitoxide>(&dir);
+            eprintln!("{}: gitoxide took {took:?}", dir.display());
+
+            agree(&dir, &libgit2, &gitoxide);
+        }
+
+        #[cfg(not(feature = "gix"))]
+        drop(libgit2);
+    }
+}
+
+/// The ids of the commits the JSON log lists for `query`, following every page.
+async fn log(bile: &Bile, query: &str) -> Vec<Id> {
+    let mut ids = Vec::new();
+    let mut cursor = None;
+
+    loop {
+        let uri = match &cursor {
+            Some(cursor) => format!("/api/v1/repos/history.git/commits?{query}&cursor={cursor}"),
+            None => format!("/api/v1/repos/history.git/commits?{query}"),
+        };
+        let request = Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .expect("failed to build request");
+
+        let response = bile
+            .routes()
+            .oneshot(request)
+            .await
+            .expect("failed to send request");
+        assert_eq!(response.status(), StatusCode::OK, "{query}");
+
+        let body = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("failed to read body");
+        let page: serde_json::Value = serde_json::from_slice(&body).expect("body is not JSON");
+
+        for commit in page["commits"].as_array().expect("commits are missing") {
+            let id = commit["id"].as_str().expect("id is missing");
+            ids.push(Oid::from_str(id).expect("id is not valid").into());
+        }
+
+        match page["next_cursor"].as_str() {
+            Some(next) => cursor = Some(next.to_string()),
+            None => return ids,
+        }
+    }
+}
+
+/// The log served with the selected backend lists the commits libgit2 walks.
+#[tokio::test]
+async fn log_agrees_with_libgit2() {
+    let fixture = fixture();
+    let libgit2 = Libgit2::open(&fixture.dir).expect("failed to open repository");
+
+    let bile = Bile::init(Config {
+        project_root: fixture
+            .dir
+            .parent()
+            .expect("fixture has a parent")
+            .canonicalize()
+            .expect("failed to canonicalize root"),
+        // a few pages, to continue walks from their cursors
+        log_per_page: 2,
+        ..Config::default()
+    });
+
+    for (spec, path) in [
+        ("main", None),
+        ("feature", None),
+        ("main", Some("README.md")),
+        ("main", Some("docs/guide.md")),
+        ("main", Some("src")),
+        ("feature", Some("src/main.rs")),
+        ("main", Some("missing")),
+    ] {
+        let tip = libgit2
+            .resolve(spec)
+            .expect("failed to resolve commit")
+            .expect("commit is missing");
+
+        let expected: Vec<Id> = libgit2
+            .walk(&[tip], &[], Sorting::Time)
+            .expect("failed to walk history")
+            .map(|id| id.expect("failed to walk history"))
+            .filter(|id| {
+                path.is_none_or(|path| {
+                    libgit2
+                        .changes(*id, Path::new(path))
+                        .expect("failed to check for changes")
+                })
+            })
+            .collect();
+
+        let query = match path {
+            Some(path) => format!("ref={spec}&path={path}"),
+            None => format!("ref={spec}"),
+        };
+
+        assert_eq!(log(&bile, &query).await, expected, "{query}");
+    }
+}
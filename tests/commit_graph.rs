@@ -0,0 +1,185 @@
+//! Reads commit-graphs written by `git commit-graph write --changed-paths`, the
+//! Bloom filters only rule out commits when paths are hashed like git does.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use bile::commit_graph::{BloomKey, CommitGraph};
+use git2::{Oid, Repository, Sort};
+
+const TIME: i64 = 1_700_000_000;
+
+/// Paths the fixture commits change, the non ASCII one is hashed differently
+/// by version 1 and 2 filters.
+const PATHS: &[&str] = &[
+    "README.md",
+    "src/main.rs",
+    "src/lib.rs",
+    "docs/ünïcode.md",
+    "docs/guide/setup.md",
+];
+
+/// A path no commit has.
+const UNTOUCHED: &str = "never/touched.md";
+
+fn git(dir: &Path, minute: i64, args: &[&str]) {
+    let date = format!("{} +0000", TIME + minute * 60);
+
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "-c",
+            "user.name=Fixture",
+            "-c",
+            "user.email=fixture@example.com",
+        ])
+        .args(args)
+        .env("GIT_AUTHOR_DATE", &date)
+        .env("GIT_COMMITTER_DATE", &date)
+        .stdout(Stdio::null())
+        .status()
+        .expect("failed to run git");
+
+    assert!(status.success(), "git {args:?} failed with {status}");
+}
+
+fn change(dir: &Path, minute: i64, paths: &[&str]) {
+    for path in paths {
+        let file = dir.join(path);
+        fs::create_dir_all(file.parent().expect("path has a parent"))
+            .expect("failed to create directory");
+        fs::write(&file, format!("{path} at {minute}\n")).expect("failed to write file");
+    }
+
+    git(dir, minute, &["add", "--all"]);
+    git(
+        dir,
+        minute,
+        &["commit", "--quiet", "-m", &format!("commit {minute}")],
+    );
+}
+
+/// A repository with a linear history, an octopus merge and a commit-graph
+/// written with `version` filters, if git knows that version.
+fn fixture(version: u32) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("commit-graph-v{version}"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("failed to create directory");
+
+    git(&dir, 0, &["init", "--quiet", "--initial-branch=main"]);
+
+    for minute in 0..24 {
+        let first = PATHS[minute % PATHS.len()];
+        let second = PATHS[(minute * 3 + 1) % PATHS.len()];
+
+        let paths = if minute % 4 == 0 {
+            vec![first, second]
+        } else {
+            vec![first]
+        };
+
+        change(&dir, minute as i64, &paths);
+    }
+
+    for (minute, branch) in [(24, "a"), (25, "b")] {
+        git(&dir, minute, &["checkout", "--quiet", "-b", branch, "main"]);
+        change(&dir, minute, &[&format!("{branch}/file.txt")]);
+    }
+
+    git(&dir, 26, &["checkout", "--quiet", "main"]);
+    change(&dir, 26, &["README.md"]);
+    git(&dir, 27, &["merge", "--quiet", "--no-edit", "a", "b"]);
+
+    git(
+        &dir,
+        28,
+        &[
+            "-c",
+            &format!("commitGraph.changedPathsVersion={version}"),
+            "commit-graph",
+            "write",
+            "--reachable",
+            "--changed-paths",
+        ],
+    );
+
+    dir
+}
+
+/// The entry at a path in the first parent and in the commit itself.
+fn ids(repo: &Repository, id: Oid, path: &str) -> (Option<Oid>, Option<Oid>) {
+    let commit = repo.find_commit(id).expect("commit is missing");
+    let entry = |commit: &git2::Commit<'_>| {
+        let tree = commit.tree().expect("tree is missing");
+
+        tree.get_path(Path::new(path)).ok().map(|entry| entry.id())
+    };
+
+    let parent = commit.parents().next().and_then(|parent| entry(&parent));
+
+    (parent, entry(&commit))
+}
+
+fn check(version: u32) {
+    let dir = fixture(version);
+    let repo = Repository::open(&dir).expect("failed to open repository");
+    let graph = CommitGraph::open(&dir.join(".git/objects")).expect("commit-graph is missing");
+
+    assert!(graph.has_bloom());
+
+    let mut walk = repo.revwalk().expect("failed to create revwalk");
+    walk.push_head().expect("failed to push HEAD");
+    walk.set_sorting(Sort::TOPOLOGICAL)
+        .expect("failed to set sorting");
+
+    let mut commits = 0;
+    let mut ruled_out = 0;
+
+    for id in walk {
+        let id = id.expect("failed to walk");
+        let commit = repo.find_commit(id).expect("commit is missing");
+
+        assert_eq!(graph.tree_id(id), Some(commit.tree_id()));
+        assert_eq!(
+            graph.parent_ids(id),
+            Some(commit.parent_ids().collect::<Vec<_>>())
+        );
+
+        for path in PATHS.iter().chain(&["a/file.txt", "b/file.txt"]) {
+            let (before, after) = ids(&repo, id, path);
+            let key = BloomKey::new(Path::new(path)).expect("path is not the root");
+
+            if before != after {
+                assert!(
+                    !graph.unchanged(id, &key),
+                    "{id} changed {path} but the filter rules it out",
+                );
+            }
+        }
+
+        let key = BloomKey::new(Path::new(UNTOUCHED)).expect("path is not the root");
+
+        commits += 1;
+        ruled_out += usize::from(graph.unchanged(id, &key));
+    }
+
+    assert!(
+        ruled_out * 2 > commits,
+        "only {ruled_out} of {commits} commits ruled out"
+    );
+}
+
+#[test]
+fn bloom_filters_v1() {
+    check(1);
+}
+
+/// Written as version 1 by git before 2.45, which doesn't know version 2.
+#[test]
+fn bloom_filters_v2() {
+    check(2);
+}
@@ -0,0 +1,206 @@
+//! Caches pages of objects named by their full id for good, and answers
+//! clients that already have a page with `304 Not Modified`.
+
+use std::{fs, path::PathBuf};
+
+use axum::{
+    body::{self, Body},
+    http::{HeaderValue, Request, StatusCode, header},
+    response::Response,
+};
+use bile::{Bile, config::Config};
+use git2::{Oid, Repository, Signature, Time};
+use tower::util::ServiceExt as _;
+
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// A repository with one commit on `main`, tagged `v1.0`.
+fn fixture() -> (Bile, Oid) {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conditional");
+    let _ = fs::remove_dir_all(&root);
+
+    let dir = root.join("cached.git");
+    let repo = Repository::init_bare(&dir).expect("failed to create repository");
+    fs::write(dir.join("git-daemon-export-ok"), "").expect("failed to export repository");
+
+    let mut builder = repo.treebuilder(None).expect("failed to build tree");
+    let blob = repo.blob(b"fn main() {}\n").expect("failed to write blob");
+    builder
+        .insert("main.rs", blob, 0o100_644)
+        .expect("failed to insert blob");
+    let tree = builder.write().expect("failed to write tree");
+    let tree = repo.find_tree(tree).expect("tree is missing");
+
+    let signature = Signature::new(
+        "Fixture",
+        "fixture@example.com",
+        &Time::new(1_700_000_000, 0),
+    )
+    .expect("failed to create signature");
+    let commit = repo
+        .commit(
+            Some("refs/heads/main"),
+            &signature,
+            &signature,
+            "root\n",
+            &tree,
+            &[],
+        )
+        .expect("failed to commit");
+    repo.set_head("refs/heads/main")
+        .expect("failed to set HEAD");
+
+    let object = repo.find_object(commit, None).expect("commit is missing");
+    repo.tag_lightweight("v1.0", &object, false)
+        .expect("failed to tag");
+
+    let bile = Bile::init(Config {
+        project_root: root.canonicalize().expect("failed to canonicalize root"),
+        ..Config::default()
+    });
+
+    (bile, commit)
+}
+
+async fn get(bile: &Bile, uri: &str, headers: &[(header::HeaderName, &str)]) -> Response {
+    let mut request = Request::builder().uri(uri);
+    for (name, value) in headers {
+        request = request.header(name, *value);
+    }
+    let request = request
+        .body(Body::empty())
+        .expect("failed to build request");
+
+    bile.routes()
+        .oneshot(request)
+        .await
+        .expect("failed to send request")
+}
+
+async fn text(response: Response) -> String {
+    let body = body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("failed to read body");
+
+    String::from_utf8(body.to_vec()).expect("body is not UTF-8")
+}
+
+/// The `ETag` of a response, dropping the response so it lets go of its worker.
+fn etag(response: Response) -> HeaderValue {
+    response
+        .headers()
+        .get(header::ETAG)
+        .expect("response has no ETag")
+        .clone()
+}
+
+#[tokio::test]
+async fn full_ids_are_immutable() {
+    let (bile, commit) = fixture();
+
+    for uri in [
+        format!("/cached.git/commit/{commit}"),
+        format!("/cached.git/tree/{commit}"),
+        format!("/cached.git/tree/{commit}/item/main.rs"),
+    ] {
+        let response = get(&bile, &uri, &[]).await;
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            IMMUTABLE,
+            "{uri}"
+        );
+
+        // the page would change when the branches and tags do
+        let html = text(response).await;
+        assert!(!html.contains("class=\"badge"), "{uri}: {html}");
+        assert!(!html.contains("/log/main\""), "{uri}: {html}");
+    }
+
+    let short = &commit.to_string()[..7];
+
+    for uri in [
+        format!("/cached.git/commit/{short}"),
+        "/cached.git/tree/main".to_string(),
+        "/cached.git/tree/main/item/main.rs".to_string(),
+    ] {
+        let response = get(&bile, &uri, &[]).await;
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            "no-cache",
+            "{uri}"
+        );
+
+        let html = text(response).await;
+        assert!(
+            html.contains("class=\"badge tag\">v1.0</a>"),
+            "{uri}: {html}"
+        );
+        assert!(
+            html.contains("class=\"badge branch\">main</a>"),
+            "{uri}: {html}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn not_modified() {
+    let (bile, commit) = fixture();
+
+    for uri in [
+        format!("/cached.git/commit/{commit}"),
+        "/cached.git/tree/main/item/main.rs".to_string(),
+    ] {
+        let response = get(&bile, &uri, &[]).await;
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        let etag = etag(response);
+        let etag = etag.to_str().expect("ETag is not ASCII");
+        assert!(etag.starts_with('"'), "{uri} has a weak ETag {etag}");
+
+        let response = get(&bile, &uri, &[(header::IF_NONE_MATCH, etag)]).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{uri}");
+        assert_eq!(response.headers()[header::ETAG], etag, "{uri}");
+        assert!(text(response).await.is_empty(), "{uri}");
+
+        let response = get(&bile, &uri, &[(header::IF_NONE_MATCH, "\"other\"")]).await;
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        text(response).await;
+    }
+}
+
+#[tokio::test]
+async fn etag_per_format() {
+    let (bile, commit) = fixture();
+    let uri = format!("/cached.git/commit/{commit}");
+
+    let response = get(&bile, &uri, &[(header::ACCEPT, "text/html")]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = etag(response);
+
+    let response = get(&bile, &uri, &[(header::ACCEPT, "application/json")]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .is_ok_and(|content_type| content_type.starts_with("application/json"))
+    );
+    let json = etag(response);
+
+    assert_ne!(html, json);
+
+    // a cached page is not handed to a client asking for another format
+    let response = get(
+        &bile,
+        &uri,
+        &[
+            (header::ACCEPT, "application/json"),
+            (
+                header::IF_NONE_MATCH,
+                html.to_str().expect("ETag is not ASCII"),
+            ),
+        ],
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
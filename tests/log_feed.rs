@@ -0,0 +1,157 @@
+//! Serves the feeds of the log of a path at `log/{ref}/{path}/feed.xml` and
+//! `feed.atom`, and links them from the log.
+
+use std::{fs, path::PathBuf};
+
+use axum::{
+    body::{self, Body},
+    http::{Request, StatusCode, header},
+};
+use bile::{Bile, config::Config};
+use git2::{Oid, Repository, Signature, Time};
+use tower::util::ServiceExt as _;
+
+fn fixture() -> Bile {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("log-feed");
+    let _ = fs::remove_dir_all(&root);
+
+    let dir = root.join("feeds.git");
+    let repo = Repository::init_bare(&dir).expect("failed to create repository");
+    fs::write(dir.join("git-daemon-export-ok"), "").expect("failed to export repository");
+
+    let commit = |minute: i64, files: &[(&str, &str)], message: &str, parents: &[Oid]| {
+        let mut api = repo.treebuilder(None).expect("failed to build tree");
+        let mut root = repo.treebuilder(None).expect("failed to build tree");
+
+        for (path, content) in files {
+            let blob = repo.blob(content.as_bytes()).expect("failed to write blob");
+            match path.strip_prefix("api/") {
+                Some(name) => api.insert(name, blob, 0o100_644),
+                None => root.insert(path, blob, 0o100_644),
+            }
+            .expect("failed to insert blob");
+        }
+
+        let api = api.write().expect("failed to write tree");
+        root.insert("api", api, 0o040_000)
+            .expect("failed to insert tree");
+        let tree = root.write().expect("failed to write tree");
+        let tree = repo.find_tree(tree).expect("tree is missing");
+
+        let signature = Signature::new(
+            "Fixture",
+            "fixture@example.com",
+            &Time::new(1_700_000_000 + minute * 60, 0),
+        )
+        .expect("failed to create signature");
+        let parents: Vec<_> = parents
+            .iter()
+            .map(|id| repo.find_commit(*id).expect("parent is missing"))
+            .collect();
+
+        repo.commit(
+            Some("refs/heads/main"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents.iter().collect::<Vec<_>>(),
+        )
+        .expect("failed to commit")
+    };
+
+    let first = commit(
+        1,
+        &[("api/lib.rs", "one"), ("feed.xml", "<rss/>")],
+        "add api\n",
+        &[],
+    );
+    commit(
+        2,
+        &[("api/lib.rs", "one"), ("feed.xml", "<rss></rss>")],
+        "change the feed file\n",
+        &[first],
+    );
+    repo.set_head("refs/heads/main")
+        .expect("failed to set HEAD");
+
+    Bile::init(Config {
+        project_root: root.canonicalize().expect("failed to canonicalize root"),
+        ..Config::default()
+    })
+}
+
+/// The content type and body of a response.
+async fn get(bile: &Bile, uri: &str) -> (String, String) {
+    let request = Request::builder()
+        .uri(uri)
+        .body(Body::empty())
+        .expect("failed to build request");
+
+    let response = bile
+        .routes()
+        .oneshot(request)
+        .await
+        .expect("failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK, "{uri}");
+
+    let content_type = response.headers()[header::CONTENT_TYPE]
+        .to_str()
+        .expect("content type is not ASCII")
+        .to_string();
+    let body = body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("failed to read body");
+
+    (
+        content_type,
+        String::from_utf8(body.to_vec()).expect("body is not UTF-8"),
+    )
+}
+
+#[tokio::test]
+async fn path_feeds() {
+    let bile = fixture();
+
+    for uri in [
+        "/feeds.git/log/main/api/feed.xml",
+        "/feeds.git/log/main/api?feed=rss",
+    ] {
+        let (content_type, body) = get(&bile, uri).await;
+
+        assert!(content_type.contains("xml"), "{uri} is {content_type}");
+        assert!(body.contains("<rss"), "{uri}: {body}");
+        assert!(body.contains("add api"), "{uri}: {body}");
+        assert!(!body.contains("change the feed file"), "{uri}: {body}");
+    }
+
+    for uri in [
+        "/feeds.git/log/main/api/feed.atom",
+        "/feeds.git/log/main/api?feed=atom",
+    ] {
+        let (content_type, body) = get(&bile, uri).await;
+
+        assert!(content_type.contains("atom+xml"), "{uri} is {content_type}");
+        assert!(body.contains("add api"), "{uri}: {body}");
+        assert!(
+            body.contains("href=\"/feeds.git/log/main/api/feed.atom\""),
+            "{uri} links another feed: {body}"
+        );
+    }
+
+    let (_, html) = get(&bile, "/feeds.git/log/main/api").await;
+    assert!(
+        html.contains("href=\"/feeds.git/log/main/api/feed.xml\""),
+        "{html}"
+    );
+    assert!(
+        html.contains("href=\"/feeds.git/log/main/api/feed.atom\""),
+        "{html}"
+    );
+
+    // without a path it is the feed of the whole branch
+    let (_, body) = get(&bile, "/feeds.git/log/main/feed.xml").await;
+    assert!(body.contains("<rss"), "{body}");
+    assert!(body.contains("change the feed file"), "{body}");
+}
@@ -0,0 +1,129 @@
+//! Draws the graph of a history with a commit made with a clock ahead, walking
+//! by time alone would show it before its children and leave a lane waiting
+//! for it forever.
+
+use std::{fs, path::PathBuf};
+
+use axum::{
+    body::{self, Body},
+    http::{Request, StatusCode},
+};
+use bile::{Bile, config::Config};
+use git2::{Oid, Repository, Signature, Time};
+use tower::util::ServiceExt as _;
+
+const TIME: i64 = 1_700_000_000;
+
+fn commit(repo: &Repository, minute: i64, parents: &[Oid], message: &str) -> Oid {
+    let signature = Signature::new(
+        "Fixture",
+        "fixture@example.com",
+        &Time::new(TIME + minute * 60, 0),
+    )
+    .expect("failed to create signature");
+
+    let mut builder = repo.treebuilder(None).expect("failed to build tree");
+    let blob = repo.blob(message.as_bytes()).expect("failed to write blob");
+    builder
+        .insert("file.txt", blob, 0o100_644)
+        .expect("failed to insert blob");
+    let tree = builder.write().expect("failed to write tree");
+    let tree = repo.find_tree(tree).expect("tree is missing");
+
+    let parents: Vec<_> = parents
+        .iter()
+        .map(|id| repo.find_commit(*id).expect("parent is missing"))
+        .collect();
+
+    repo.commit(
+        None,
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents.iter().collect::<Vec<_>>(),
+    )
+    .expect("failed to commit")
+}
+
+/// The values of `attribute` in the order they appear in `html`.
+fn attributes<'a>(html: &'a str, attribute: &str) -> Vec<&'a str> {
+    let start = format!("{attribute}=\"");
+
+    html.match_indices(&start)
+        .filter_map(|(at, _)| {
+            let value = &html[at + start.len()..];
+            value.split_once('"').map(|(value, _)| value)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn graph_keeps_children_before_parents() {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("log-graph");
+    let _ = fs::remove_dir_all(&root);
+
+    let dir = root.join("skewed.git");
+    let repo = Repository::init_bare(&dir).expect("failed to create repository");
+    fs::write(dir.join("git-daemon-export-ok"), "").expect("failed to export repository");
+
+    let c1 = commit(&repo, 1, &[], "root\n");
+    // made with a clock ahead
+    let c2 = commit(&repo, 9, &[c1], "skewed\n");
+    let c3 = commit(&repo, 3, &[c2], "after the skewed one\n");
+    let c4 = commit(&repo, 4, &[c3], "main\n");
+    let f1 = commit(&repo, 5, &[c2], "feature\n");
+    let merge = commit(&repo, 6, &[c4, f1], "Merge branch 'feature'\n");
+
+    repo.reference("refs/heads/main", merge, true, "fixture")
+        .expect("failed to create branch");
+    repo.set_head("refs/heads/main")
+        .expect("failed to set HEAD");
+
+    let bile = Bile::init(Config {
+        project_root: root.canonicalize().expect("failed to canonicalize root"),
+        ..Config::default()
+    });
+
+    let request = Request::builder()
+        .uri("/skewed.git/log?graph=1")
+        .body(Body::empty())
+        .expect("failed to build request");
+
+    let response = bile
+        .routes()
+        .oneshot(request)
+        .await
+        .expect("failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("failed to read body");
+    let html = str::from_utf8(&body).expect("body is not UTF-8");
+
+    let mut shown: Vec<String> = Vec::new();
+    for href in attributes(html, "href") {
+        if let Some(id) = href.strip_prefix("/skewed.git/commit/")
+            && !shown.iter().any(|shown| shown == id)
+        {
+            shown.push(id.to_string());
+        }
+    }
+
+    let expected: Vec<String> = [merge, f1, c4, c3, c2, c1]
+        .iter()
+        .map(Oid::to_string)
+        .collect();
+
+    assert_eq!(shown, expected);
+
+    // two lanes at most, one for each branch
+    let widths = attributes(html, "svg width");
+    assert_eq!(widths.len(), expected.len());
+    assert!(
+        widths.iter().all(|width| *width == "12" || *width == "24"),
+        "a lane is left waiting: {widths:?}",
+    );
+}
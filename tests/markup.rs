@@ -0,0 +1,247 @@
+//! Renders reStructuredText, `AsciiDoc` and Org documents the way the file
+//! page shows them, raw HTML in them has to come out escaped.
+
+use std::{fs, path::PathBuf};
+
+use axum::{
+    body::{self, Body},
+    http::{Request, StatusCode},
+};
+use bile::{Bile, config::Config};
+use git2::{Repository, Signature, Time};
+use tower::util::ServiceExt as _;
+
+const ESCAPED: &str = "&#60;script&#62;alert(1)&#60;/script&#62;";
+
+const RST: &str = "\
+Title
+=====
+
+Some **strong**, *emphasised* and ``<b>literal</b>`` text with a `link`_.
+
+.. _link: https://example.com/
+
+<script>alert(1)</script>
+
+- first
+- second
+
+.. note:: Careful <script>alert(1)</script>
+
+A literal block::
+
+    <script>alert(1)</script>
+
+`unsafe <javascript:alert(1)>`_
+";
+
+const ASCIIDOC: &str = "\
+= Title
+
+Some *strong*, _emphasised_ and `<b>literal</b>` text with a https://example.com/[link].
+
+<script>alert(1)</script>
+
+* first
+* second
+
+NOTE: Careful <script>alert(1)</script>
+
+----
+<script>alert(1)</script>
+----
+
+link:javascript:alert(1)[unsafe]
+";
+
+const ORG: &str = "\
+#+TITLE: Title
+
+Some *strong*, /emphasised/ and =<b>literal</b>= text with a [[https://example.com/][link]].
+
+<script>alert(1)</script>
+
+- first
+- second
+
+#+BEGIN_QUOTE
+Careful <script>alert(1)</script>
+#+END_QUOTE
+
+: <script>alert(1)</script>
+
+[[javascript:alert(1)][unsafe]]
+";
+
+/// A repository with `files` in its only commit, served from a project root of its own.
+fn fixture(name: &str, files: &[(&str, &str)]) -> Bile {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("markup-{name}"));
+    let _ = fs::remove_dir_all(&root);
+
+    let dir = root.join("docs.git");
+    let repo = Repository::init_bare(&dir).expect("failed to create repository");
+    fs::write(dir.join("git-daemon-export-ok"), "").expect("failed to export repository");
+
+    let mut builder = repo.treebuilder(None).expect("failed to build tree");
+    for (path, content) in files {
+        let blob = repo.blob(content.as_bytes()).expect("failed to write blob");
+        builder
+            .insert(path, blob, 0o100_644)
+            .expect("failed to insert blob");
+    }
+    let tree = builder.write().expect("failed to write tree");
+    let tree = repo.find_tree(tree).expect("tree is missing");
+
+    let signature = Signature::new(
+        "Fixture",
+        "fixture@example.com",
+        &Time::new(1_700_000_000, 0),
+    )
+    .expect("failed to create signature");
+    repo.commit(
+        Some("refs/heads/main"),
+        &signature,
+        &signature,
+        "docs\n",
+        &tree,
+        &[],
+    )
+    .expect("failed to commit");
+    repo.set_head("refs/heads/main")
+        .expect("failed to set HEAD");
+
+    Bile::init(Config {
+        project_root: root.canonicalize().expect("failed to canonicalize root"),
+        ..Config::default()
+    })
+}
+
+/// The rendered document shown on the page of `path`.
+async fn render(bile: &Bile, path: &str) -> String {
+    let request = Request::builder()
+        .uri(format!("/docs.git/tree/main/item/{path}"))
+        .body(Body::empty())
+        .expect("failed to build request");
+
+    let response = bile
+        .routes()
+        .oneshot(request)
+        .await
+        .expect("failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("failed to read body");
+    let html = String::from_utf8(body.to_vec()).expect("body is not UTF-8");
+
+    let (_, document) = html
+        .split_once("<div class=\"readme\">")
+        .expect("document is not rendered");
+
+    document.to_string()
+}
+
+/// Checks the markup shared by the fixture documents.
+fn check(html: &str) {
+    for expected in [
+        "<strong>strong</strong>",
+        "<em>emphasised</em>",
+        "<code>&#60;b&#62;literal&#60;/b&#62;</code>",
+        "<a href=\"https://example.com/\">link</a>",
+        "<li>first",
+        "<li>second",
+    ] {
+        assert!(html.contains(expected), "{expected} is missing in {html}");
+    }
+
+    assert!(
+        !html.contains("<script"),
+        "raw HTML is not escaped in {html}"
+    );
+    assert!(!html.contains("<b>"), "raw HTML is not escaped in {html}");
+    // once as a paragraph and once in a block
+    assert!(
+        html.matches(ESCAPED).count() >= 2,
+        "raw HTML is missing in {html}"
+    );
+    assert!(
+        !html.contains("href=\"javascript:"),
+        "unsafe link is kept in {html}"
+    );
+}
+
+#[tokio::test]
+async fn restructuredtext() {
+    let bile = fixture("rst", &[("doc.rst", RST)]);
+    let html = render(&bile, "doc.rst").await;
+
+    check(&html);
+    assert!(html.contains("<h1>Title</h1>"), "{html}");
+    assert!(html.contains("<div class=\"admonition note\">"), "{html}");
+    assert!(html.contains(&format!("<pre>{ESCAPED}</pre>")), "{html}");
+}
+
+#[tokio::test]
+async fn asciidoc() {
+    let bile = fixture("asciidoc", &[("doc.adoc", ASCIIDOC)]);
+    let html = render(&bile, "doc.adoc").await;
+
+    check(&html);
+    assert!(html.contains("<h1>Title</h1>"), "{html}");
+    assert!(html.contains("<div class=\"admonition note\">"), "{html}");
+    assert!(
+        html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"),
+        "{html}"
+    );
+}
+
+#[tokio::test]
+async fn org() {
+    let bile = fixture("org", &[("doc.org", ORG)]);
+    let html = render(&bile, "doc.org").await;
+
+    check(&html);
+    assert!(html.contains("<h1 class=\"title\">Title</h1>"), "{html}");
+    assert!(html.contains("<blockquote>"), "{html}");
+    assert!(html.contains(&format!("<pre>{ESCAPED}</pre>")), "{html}");
+}
+
+/// Deeply nested blocks and spans that never close must not overflow the
+/// stack or take quadratic time.
+#[tokio::test]
+async fn hostile_documents() {
+    let quotes: String = (0..2000)
+        .map(|depth| format!("{}quote\n\n", " ".repeat(depth)))
+        .collect();
+    let blocks: String = (0..2000)
+        .map(|depth| format!("#+BEGIN_B{depth}\n"))
+        .collect();
+    let spans = "*a _b `c ".repeat(50_000);
+
+    let bile = fixture(
+        "hostile",
+        &[
+            ("quotes.rst", &quotes),
+            ("blocks.org", &blocks),
+            ("spans.rst", &spans),
+            ("spans.adoc", &spans),
+            ("spans.org", &spans),
+        ],
+    );
+
+    let html = render(&bile, "quotes.rst").await;
+    assert!(html.contains("<pre>"), "deep quotes are not cut off");
+
+    let html = render(&bile, "blocks.org").await;
+    assert!(html.contains("<pre>"), "deep blocks are not cut off");
+
+    for path in ["spans.rst", "spans.adoc", "spans.org"] {
+        let html = render(&bile, path).await;
+        assert!(
+            !html.contains("<strong>"),
+            "{path} has a span that never closes"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Compares the served `OpenAPI` description against a checked in snapshot so
+//! changes to the API are always visible in review.
+//!
+//! Run with `BILE_UPDATE_SNAPSHOTS=1` to update the snapshot.
+
+use std::{env, fs, path::PathBuf};
+
+use axum::{
+    body::{self, Body},
+    http::{Request, StatusCode},
+};
+use bile::{Bile, config::Config};
+use tower::util::ServiceExt as _;
+
+#[tokio::test]
+async fn openapi_matches_snapshot() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+    let bile = Bile::init(Config {
+        project_root: root.clone(),
+        ..Config::default()
+    });
+
+    let request = Request::builder()
+        .uri("/api/v1/openapi.json")
+        .body(Body::empty())
+        .expect("failed to build request");
+
+    let response = bile
+        .routes()
+        .oneshot(request)
+        .await
+        .expect("failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("failed to read body");
+    let value: serde_json::Value = serde_json::from_slice(&body).expect("body is not json");
+    let actual = serde_json::to_string_pretty(&value).expect("failed to format json") + "\n";
+
+    let snapshot = root.join("tests/snapshots/openapi.json");
+
+    if env::var_os("BILE_UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&snapshot, &actual).expect("failed to write snapshot");
+        return;
+    }
+
+    let expected = fs::read_to_string(&snapshot).expect("failed to read snapshot");
+
+    assert!(
+        actual == expected,
+        "the OpenAPI description changed, run the tests with BILE_UPDATE_SNAPSHOTS=1 to update {}",
+        snapshot.display()
+    );
+}
@@ -0,0 +1,148 @@
+//! Turns requests away with 503 Service Unavailable while every worker is
+//! busy, and takes the worker back from clients that stop reading.
+
+use std::{fs, path::PathBuf, time::Duration};
+
+use axum::{
+    body::{self, Body},
+    http::{Request, StatusCode, header},
+    response::Response,
+};
+use bile::{Bile, config::Config};
+use futures_util::StreamExt as _;
+use git2::{Repository, Signature, Time};
+use tower::util::ServiceExt as _;
+
+/// Far more than is written ahead of a client that stopped reading.
+const LARGE: usize = 4 * 1024 * 1024;
+
+/// A repository with a large file, served by a single worker.
+fn fixture(name: &str, config: Config) -> Bile {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("pool-{name}"));
+    let _ = fs::remove_dir_all(&root);
+
+    let dir = root.join("pool.git");
+    let repo = Repository::init_bare(&dir).expect("failed to create repository");
+    fs::write(dir.join("git-daemon-export-ok"), "").expect("failed to export repository");
+
+    let mut builder = repo.treebuilder(None).expect("failed to build tree");
+    let blob = repo.blob(&vec![b'x'; LARGE]).expect("failed to write blob");
+    builder
+        .insert("large.bin", blob, 0o100_644)
+        .expect("failed to insert blob");
+    let tree = builder.write().expect("failed to write tree");
+    let tree = repo.find_tree(tree).expect("tree is missing");
+
+    let signature = Signature::new(
+        "Fixture",
+        "fixture@example.com",
+        &Time::new(1_700_000_000, 0),
+    )
+    .expect("failed to create signature");
+    repo.commit(
+        Some("refs/heads/main"),
+        &signature,
+        &signature,
+        "large\n",
+        &tree,
+        &[],
+    )
+    .expect("failed to commit");
+    repo.set_head("refs/heads/main")
+        .expect("failed to set HEAD");
+
+    Bile::init(Config {
+        project_root: root.canonicalize().expect("failed to canonicalize root"),
+        max_workers: 1,
+        ..config
+    })
+}
+
+async fn get(bile: &Bile, uri: &str) -> Response {
+    let request = Request::builder()
+        .uri(uri)
+        .body(Body::empty())
+        .expect("failed to build request");
+
+    bile.routes()
+        .oneshot(request)
+        .await
+        .expect("failed to send request")
+}
+
+#[tokio::test]
+async fn saturated() {
+    let bile = fixture(
+        "saturated",
+        Config {
+            max_queued: 0,
+            queue_timeout: 2,
+            ..Config::default()
+        },
+    );
+
+    // the body keeps the worker until it is sent
+    let held = get(&bile, "/pool.git/tree/main/raw/large.bin").await;
+    assert_eq!(held.status(), StatusCode::OK);
+
+    let response = get(&bile, "/pool.git/tree/main/raw/large.bin").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+
+    drop(held);
+
+    let response = get(&bile, "/pool.git/tree/main/raw/large.bin").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn stalled_reader() {
+    let bile = fixture(
+        "stalled",
+        Config {
+            queue_timeout: 30,
+            send_timeout: 1,
+            ..Config::default()
+        },
+    );
+
+    let stalled = get(&bile, "/pool.git/tree/main/raw/large.bin").await;
+    assert_eq!(stalled.status(), StatusCode::OK);
+
+    // reads a little and then stops, like a client whose socket is full
+    let mut stalled = stalled.into_body().into_data_stream();
+    let first = stalled
+        .next()
+        .await
+        .expect("body is empty")
+        .expect("failed to read body");
+    assert!(!first.is_empty());
+
+    let response = tokio::time::timeout(
+        Duration::from_secs(10),
+        get(&bile, "/pool.git/tree/main/raw/large.bin"),
+    )
+    .await
+    .expect("the stalled client kept the worker");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("failed to read body");
+    assert_eq!(body.len(), LARGE);
+
+    // the stalled body was cut off rather than ending as if it was whole
+    let mut read = first.len();
+    let mut cut = false;
+    while let Some(chunk) = stalled.next().await {
+        match chunk {
+            Ok(chunk) => read += chunk.len(),
+            Err(_) => {
+                cut = true;
+                break;
+            }
+        }
+    }
+    assert!(cut, "the body ended after {read} bytes without an error");
+    assert!(read < LARGE);
+}
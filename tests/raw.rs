@@ -0,0 +1,199 @@
+//! Serves files raw, linked from the file view by URLs that keep working
+//! whatever characters the path has, and parts of them for `Range` requests.
+
+use std::{fs, path::PathBuf};
+
+use axum::{
+    body::{self, Body},
+    http::{Request, StatusCode, header},
+    response::Response,
+};
+use bile::{Bile, config::Config};
+use git2::{Repository, Signature, Time};
+use tower::util::ServiceExt as _;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01";
+const IMAGE: &str = "we \"ird\" #1?.png";
+const TEXT: &str = "notes 100%.txt";
+
+/// A repository with `files` in its only commit, served from a project root of its own.
+fn fixture(name: &str, files: &[(&str, &[u8])]) -> Bile {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("raw-{name}"));
+    let _ = fs::remove_dir_all(&root);
+
+    let dir = root.join("files.git");
+    let repo = Repository::init_bare(&dir).expect("failed to create repository");
+    fs::write(dir.join("git-daemon-export-ok"), "").expect("failed to export repository");
+
+    let mut builder = repo.treebuilder(None).expect("failed to build tree");
+    for (path, content) in files {
+        let blob = repo.blob(content).expect("failed to write blob");
+        builder
+            .insert(path, blob, 0o100_644)
+            .expect("failed to insert blob");
+    }
+    let tree = builder.write().expect("failed to write tree");
+    let tree = repo.find_tree(tree).expect("tree is missing");
+
+    let signature = Signature::new(
+        "Fixture",
+        "fixture@example.com",
+        &Time::new(1_700_000_000, 0),
+    )
+    .expect("failed to create signature");
+    repo.commit(
+        Some("refs/heads/main"),
+        &signature,
+        &signature,
+        "files\n",
+        &tree,
+        &[],
+    )
+    .expect("failed to commit");
+    repo.set_head("refs/heads/main")
+        .expect("failed to set HEAD");
+
+    Bile::init(Config {
+        project_root: root.canonicalize().expect("failed to canonicalize root"),
+        ..Config::default()
+    })
+}
+
+async fn send(bile: &Bile, request: Request<Body>) -> Response {
+    bile.routes()
+        .oneshot(request)
+        .await
+        .expect("failed to send request")
+}
+
+async fn get(bile: &Bile, uri: &str) -> Response {
+    let request = Request::builder()
+        .uri(uri)
+        .body(Body::empty())
+        .expect("failed to build request");
+
+    send(bile, request).await
+}
+
+async fn text(response: Response) -> String {
+    let body = body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("failed to read body");
+
+    String::from_utf8(body.to_vec()).expect("body is not UTF-8")
+}
+
+/// The value of the first `attribute` in `html` after `after`.
+fn attribute<'a>(html: &'a str, after: &str, attribute: &str) -> &'a str {
+    let (_, rest) = html.split_once(after).expect("element is missing");
+    let (_, value) = rest
+        .split_once(&format!("{attribute}=\""))
+        .expect("attribute is missing");
+
+    value.split_once('"').expect("attribute is not closed").0
+}
+
+#[tokio::test]
+async fn links_encode_paths() {
+    let bile = fixture("links", &[(IMAGE, PNG), (TEXT, b"one\ntwo\n")]);
+
+    let response = get(
+        &bile,
+        "/files.git/tree/main/item/we%20%22ird%22%20%231%3F.png",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = text(response).await;
+
+    let raw = attribute(&html, "<img", "src");
+    assert_eq!(raw, "/files.git/tree/main/raw/we%20%22ird%22%20%231%3F.png");
+
+    let response = get(&bile, raw).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("failed to read body");
+    assert_eq!(&body[..], PNG);
+
+    let response = get(&bile, "/files.git/tree/main/item/notes%20100%25.txt").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = text(response).await;
+
+    assert!(
+        html.contains("/item/notes%20100%25.txt#L1'"),
+        "line links are not encoded: {html}"
+    );
+    assert!(
+        !html.contains("notes 100%.txt#L1"),
+        "line links are not encoded: {html}"
+    );
+
+    let response = get(&bile, "/files.git/tree/main/raw/notes%20100%25.txt").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_LENGTH],
+        "8",
+        "the raw file is not the whole file"
+    );
+}
+
+#[tokio::test]
+async fn ranges() {
+    let bile = fixture("ranges", &[(TEXT, b"one\ntwo\n")]);
+    let uri = "/files.git/tree/main/raw/notes%20100%25.txt";
+
+    let range = |range: &str, if_range: Option<&str>| {
+        let mut request = Request::builder().uri(uri).header(header::RANGE, range);
+        if let Some(if_range) = if_range {
+            request = request.header(header::IF_RANGE, if_range);
+        }
+
+        request
+            .body(Body::empty())
+            .expect("failed to build request")
+    };
+
+    let response = get(&bile, uri).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::ACCEPT_RANGES], "bytes");
+    let etag = response.headers()[header::ETAG]
+        .to_str()
+        .expect("ETag is not ASCII")
+        .to_string();
+    assert_eq!(text(response).await, "one\ntwo\n");
+
+    for (asked, content_range, part) in [
+        ("bytes=2-4", "bytes 2-4/8", "e\nt"),
+        ("bytes=4-", "bytes 4-7/8", "two\n"),
+        ("bytes=-3", "bytes 5-7/8", "wo\n"),
+        ("bytes=6-100", "bytes 6-7/8", "o\n"),
+    ] {
+        let response = send(&bile, range(asked, None)).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT, "{asked}");
+        assert_eq!(
+            response.headers()[header::CONTENT_RANGE],
+            content_range,
+            "{asked}"
+        );
+        assert_eq!(
+            response.headers()[header::CONTENT_LENGTH],
+            part.len().to_string().as_str(),
+            "{asked}"
+        );
+        assert_eq!(text(response).await, part, "{asked}");
+    }
+
+    let response = send(&bile, range("bytes=8-", None)).await;
+    assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */8");
+
+    // only a part of the copy the client has is sent
+    let response = send(&bile, range("bytes=4-", Some(&etag))).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(text(response).await, "two\n");
+
+    let response = send(&bile, range("bytes=4-", Some("\"stale\""))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(header::CONTENT_RANGE).is_none());
+    assert_eq!(text(response).await, "one\ntwo\n");
+}
@@ -0,0 +1,105 @@
+//! Lists tags whose tagger git accepts but libgit2 can't build a signature
+//! for, like one without an email.
+
+use std::{fs, path::PathBuf};
+
+use axum::{
+    body::{self, Body},
+    http::{Request, StatusCode},
+};
+use bile::{Bile, config::Config};
+use git2::{ObjectType, Repository, Signature, Time};
+use tower::util::ServiceExt as _;
+
+/// A repository with an annotated tag `v1.0` tagged by `tagger`.
+fn fixture(tagger: &str) -> Bile {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("refs");
+    let _ = fs::remove_dir_all(&root);
+
+    let dir = root.join("tags.git");
+    let repo = Repository::init_bare(&dir).expect("failed to create repository");
+    fs::write(dir.join("git-daemon-export-ok"), "").expect("failed to export repository");
+
+    let signature = Signature::new(
+        "Fixture",
+        "fixture@example.com",
+        &Time::new(1_700_000_000, 0),
+    )
+    .expect("failed to create signature");
+    let tree = repo
+        .treebuilder(None)
+        .and_then(|builder| builder.write())
+        .expect("failed to write tree");
+    let tree = repo.find_tree(tree).expect("tree is missing");
+    let commit = repo
+        .commit(
+            Some("refs/heads/main"),
+            &signature,
+            &signature,
+            "root\n",
+            &tree,
+            &[],
+        )
+        .expect("failed to commit");
+    repo.set_head("refs/heads/main")
+        .expect("failed to set HEAD");
+
+    // written by hand, libgit2 refuses to make a tag with such a tagger
+    let tag = format!(
+        "object {commit}\ntype commit\ntag v1.0\ntagger {tagger} 1700000060 +0000\n\nRelease 1.0\n"
+    );
+    let tag = repo
+        .odb()
+        .and_then(|odb| odb.write(ObjectType::Tag, tag.as_bytes()))
+        .expect("failed to write tag");
+    repo.reference("refs/tags/v1.0", tag, true, "fixture")
+        .expect("failed to create tag");
+
+    Bile::init(Config {
+        project_root: root.canonicalize().expect("failed to canonicalize root"),
+        ..Config::default()
+    })
+}
+
+async fn get(bile: &Bile, uri: &str) -> String {
+    let request = Request::builder()
+        .uri(uri)
+        .body(Body::empty())
+        .expect("failed to build request");
+
+    let response = bile
+        .routes()
+        .oneshot(request)
+        .await
+        .expect("failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK, "{uri}");
+
+    let body = body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("failed to read body");
+
+    String::from_utf8(body.to_vec()).expect("body is not UTF-8")
+}
+
+#[tokio::test]
+async fn tagger_without_email() {
+    let bile = fixture("Release Bot <>");
+
+    let body = get(&bile, "/tags.git/refs.xml").await;
+    assert!(body.contains("<title>v1.0</title>"), "{body}");
+
+    for uri in [
+        "/tags.git/refs",
+        "/tags.git/refs.atom",
+        "/api/v1/repos/tags.git/refs",
+    ] {
+        let body = get(&bile, uri).await;
+
+        assert!(body.contains("v1.0"), "{uri} is missing the tag: {body}");
+        assert!(
+            body.contains("Release Bot"),
+            "{uri} is missing the tagger: {body}"
+        );
+    }
+}